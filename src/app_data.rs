@@ -1,9 +1,37 @@
 use std::{
+    collections::{HashMap, VecDeque},
     env::{self, current_dir, var},
     fmt, fs,
-    path::PathBuf,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Component, Path, PathBuf},
+    process,
+    sync::Arc,
+    time::SystemTime,
 };
 
+use crate::{
+    Clock, FileSystem, ManagedFile, Progress, SharedClock, SharedFileSystem, owner::OWNER_FILE, pid,
+};
+
+/// Environment variable that replaces the platform base directory
+const BASE_DIR_VAR: &str = "APPDATA_BASE_DIR";
+
+/// Environment variable naming the persistent volume for [`AppData::prefer_container_volume`]
+const CONTAINER_VOLUME_VAR: &str = "DATA_DIR";
+/// Conventional mount point of persistent storage in container images
+const CONTAINER_VOLUME: &str = "/data";
+
+/// Marker left in a legacy directory once its contents were moved by `migrate_from`
+const MIGRATED_MARKER: &str = ".migrated";
+/// File explaining the data directory, written by [`AppData::write_readme`]
+pub(crate) const README_FILE: &str = "README.txt";
+
+/// A file name passed to [`AppData::delete_files`] with the result of deleting it
+type DeleteOutcome = (String, Result<(), AppDataError>);
+
+/// Byte order mark some editors put at the start of UTF-8 text files
+pub(crate) const UTF8_BOM: char = '\u{feff}';
+
 /// Custom error type
 #[derive(Debug, Clone)]
 pub enum AppDataError {
@@ -13,6 +41,36 @@ pub enum AppDataError {
     IoError(String),
     /// Failed to get current directory
     CurrentDirError(String),
+    /// Failed to convert file contents to or from a value
+    Serialization(String),
+    /// The data directory is not writable by the current user
+    PermissionDenied(String),
+    /// The data directory is on a read-only filesystem
+    ReadOnlyFilesystem(String),
+    /// Refused to use a filesystem root, a system directory or the bare system base as data directory
+    RefusedUnsafeCreate(PathBuf),
+    /// A relative path is absolute, empty or escapes the data directory
+    InvalidPath(String),
+    /// A write was attempted in read-only mode
+    ReadOnly(String),
+    /// The OS user passed to `for_user` doesn't exist
+    UserNotFound(String),
+    /// A required file doesn't exist
+    FileNotFound(PathBuf),
+    /// A directory could not be created because something else is in the way
+    NotADirectory(PathBuf),
+    /// The application name can't be used for a directory in the shared system base
+    InvalidAppName(String),
+    /// The write succeeded, but copying it to the [`mirror_to`](AppData::mirror_to) directory failed
+    MirrorFailed(String),
+    /// A file that must be new already exists
+    AlreadyExists(PathBuf),
+    /// The data directory is a symlink whose target doesn't exist
+    DanglingSymlink(PathBuf),
+    /// The data directory was claimed by a different application
+    OwnershipConflict(String),
+    /// The operation needs the real filesystem, but a custom [`FileSystem`] backend is set
+    Unsupported(String),
 }
 
 impl fmt::Display for AppDataError {
@@ -27,6 +85,51 @@ impl fmt::Display for AppDataError {
             AppDataError::CurrentDirError(msg) => {
                 write!(f, "Failed to get current directory: {}", msg)
             }
+            AppDataError::Serialization(msg) => {
+                write!(f, "Serialization error: {}", msg)
+            }
+            AppDataError::PermissionDenied(path) => {
+                write!(f, "Permission denied: {}", path)
+            }
+            AppDataError::ReadOnlyFilesystem(path) => {
+                write!(f, "Read-only filesystem: {}", path)
+            }
+            AppDataError::RefusedUnsafeCreate(path) => {
+                write!(f, "Refused to use {} as data directory", path.display())
+            }
+            AppDataError::InvalidPath(path) => {
+                write!(f, "Invalid relative path: {}", path)
+            }
+            AppDataError::ReadOnly(target) => {
+                write!(f, "Read-only mode, refused to modify {}", target)
+            }
+            AppDataError::UserNotFound(user) => {
+                write!(f, "User {} not found", user)
+            }
+            AppDataError::FileNotFound(path) => {
+                write!(f, "File not found: {}", path.display())
+            }
+            AppDataError::NotADirectory(path) => {
+                write!(f, "Not a directory: {}", path.display())
+            }
+            AppDataError::InvalidAppName(reason) => {
+                write!(f, "Invalid app name: {}", reason)
+            }
+            AppDataError::MirrorFailed(msg) => {
+                write!(f, "Write succeeded, mirroring failed: {}", msg)
+            }
+            AppDataError::AlreadyExists(path) => {
+                write!(f, "File already exists: {}", path.display())
+            }
+            AppDataError::DanglingSymlink(path) => {
+                write!(f, "Dangling symlink: {}", path.display())
+            }
+            AppDataError::OwnershipConflict(msg) => {
+                write!(f, "Ownership conflict: {}", msg)
+            }
+            AppDataError::Unsupported(operation) => {
+                write!(f, "{} is not supported by a custom file system", operation)
+            }
         }
     }
 }
@@ -39,25 +142,522 @@ impl From<std::io::Error> for AppDataError {
     }
 }
 
+/// Check that `relative` names a file strictly inside a directory: not empty, not absolute and
+/// without `..` components
+pub(crate) fn checked_relative(relative: &str) -> Result<&Path, AppDataError> {
+    let path = Path::new(relative);
+    let mut has_name = false;
+    for component in path.components() {
+        match component {
+            Component::Normal(_) => has_name = true,
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(AppDataError::InvalidPath(relative.to_string()));
+            }
+        }
+    }
+    if !has_name {
+        return Err(AppDataError::InvalidPath(relative.to_string()));
+    }
+    Ok(path)
+}
+
+/// Map an error from writing into `dir`, singling out permission problems
+fn write_error(err: io::Error, dir: &Path) -> AppDataError {
+    match err.kind() {
+        io::ErrorKind::PermissionDenied => {
+            AppDataError::PermissionDenied(dir.display().to_string())
+        }
+        io::ErrorKind::ReadOnlyFilesystem => {
+            AppDataError::ReadOnlyFilesystem(dir.display().to_string())
+        }
+        _ => err.into(),
+    }
+}
+
+/// Create `dir` and its parents, tolerating concurrent creators, and return whether this call
+/// created `dir` itself
+///
+/// Losing a creation race to another thread or process is success. Afterwards `dir` must be a
+/// directory, so a file in the way fails with [`AppDataError::NotADirectory`] instead of an
+/// unrelated IO error.
+pub(crate) fn create_dir_race_safe(dir: &Path) -> Result<bool, AppDataError> {
+    if let Some(parent) = dir.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        let created = fs::create_dir_all(parent);
+        if !parent.is_dir() {
+            return Err(create_dir_error(created, parent));
+        }
+    }
+    let created = fs::create_dir(dir);
+    if dir.is_dir() {
+        return Ok(created.is_ok());
+    }
+    Err(create_dir_error(created, dir))
+}
+
+/// The target of `path` when it is a symlink pointing to nothing, resolved against the
+/// symlink's directory
+fn dangling_symlink_target(path: &Path) -> io::Result<Option<PathBuf>> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() && !path.exists() => {
+            let target = fs::read_link(path)?;
+            Ok(Some(match path.parent() {
+                Some(parent) => parent.join(target),
+                None => target,
+            }))
+        }
+        Ok(_) => Ok(None),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// The error for a failed creation of `dir`, which is not a directory afterwards
+fn create_dir_error(created: io::Result<()>, dir: &Path) -> AppDataError {
+    match created {
+        Err(err) if err.kind() != io::ErrorKind::AlreadyExists && !dir.exists() => {
+            write_error(err, dir)
+        }
+        _ => AppDataError::NotADirectory(dir.to_path_buf()),
+    }
+}
+
+/// Whether the process runs under a CI service, as signalled by `CI` or `GITHUB_ACTIONS`
+fn is_ci(env: EnvLookup) -> bool {
+    ["CI", "GITHUB_ACTIONS"].iter().any(|key| {
+        env(key).is_some_and(|value| !value.is_empty() && value != "0" && value != "false")
+    })
+}
+
+/// Whether `dir` is a directory the current process can create files in, probed by creating
+/// and removing a file
+fn is_writable_dir(dir: &Path) -> bool {
+    if !dir.is_dir() {
+        return false;
+    }
+    let probe = dir.join(format!(".appdata-probe-{}", process::id()));
+    let created = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&probe)
+        .is_ok();
+    created && fs::remove_file(&probe).is_ok()
+}
+
+/// Per-process temporary base used instead of the system directory when sandboxed in CI
+fn ci_sandbox_dir() -> PathBuf {
+    env::temp_dir().join(format!("app_data-ci-{}", process::id()))
+}
+
+/// Top-level directories that belong to the operating system
+#[cfg(not(target_os = "windows"))]
+const SYSTEM_DIRS: &[&str] = &[
+    "Applications",
+    "Library",
+    "System",
+    "Users",
+    "bin",
+    "boot",
+    "dev",
+    "etc",
+    "home",
+    "lib",
+    "lib64",
+    "opt",
+    "private",
+    "proc",
+    "sbin",
+    "sys",
+    "usr",
+    "var",
+];
+#[cfg(target_os = "windows")]
+const SYSTEM_DIRS: &[&str] = &[
+    "Program Files",
+    "Program Files (x86)",
+    "ProgramData",
+    "Users",
+    "Windows",
+];
+
+/// Whether `path` is a filesystem root or an operating system directory such as `/usr`
+fn is_system_location(path: &Path) -> bool {
+    let Some(parent) = path.parent() else {
+        return true;
+    };
+    if cfg!(unix) && path == Path::new("/usr/local") {
+        return true;
+    }
+    parent.parent().is_none()
+        && path.file_name().is_some_and(|name| {
+            let name = name.to_string_lossy();
+            SYSTEM_DIRS.iter().any(|dir| {
+                if cfg!(target_os = "windows") {
+                    dir.eq_ignore_ascii_case(&name)
+                } else {
+                    *dir == name
+                }
+            })
+        })
+}
+
+/// Quote `path` for POSIX shells: kept as is when it only contains safe characters, otherwise
+/// wrapped in single quotes with embedded quotes written as `'\''`
+#[cfg(not(target_os = "windows"))]
+fn shell_quote(path: &Path) -> Result<String, AppDataError> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| AppDataError::InvalidPath(path.display().to_string()))?;
+    let safe = |c: char| c.is_ascii_alphanumeric() || "/._-+=:,@%".contains(c);
+    if !path.is_empty() && path.chars().all(safe) {
+        return Ok(path.to_string());
+    }
+    Ok(format!("'{}'", path.replace('\'', "'\\''")))
+}
+
+/// Quote `path` for `cmd.exe` and PowerShell: wrapped in double quotes, which Windows paths can't
+/// contain themselves
+#[cfg(target_os = "windows")]
+fn shell_quote(path: &Path) -> Result<String, AppDataError> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| AppDataError::InvalidPath(path.display().to_string()))?;
+    Ok(format!("\"{}\"", path))
+}
+
+/// Percent-encode `bytes` for the path part of a URL, keeping `/` and unreserved characters
+pub(crate) fn push_url_encoded(url: &mut String, bytes: &[u8]) {
+    for &byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                url.push(byte as char)
+            }
+            _ => url.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+}
+
+/// `file://` URL of an absolute path, with a trailing `/`
+#[cfg(not(target_os = "windows"))]
+fn dir_to_file_url(path: &Path) -> String {
+    let mut url = String::from("file://");
+    push_url_encoded(&mut url, path.as_os_str().as_encoded_bytes());
+    if !url.ends_with('/') {
+        url.push('/');
+    }
+    url
+}
+
+/// `file://` URL of an absolute path, with a trailing `/`.
+/// Handles drive letters, UNC shares and the `\\?\` extended-length prefix
+#[cfg(target_os = "windows")]
+fn dir_to_file_url(path: &Path) -> String {
+    let raw = path.to_string_lossy().replace('\\', "/");
+    let text = if let Some(rest) = raw.strip_prefix("//?/UNC/") {
+        format!("//{}", rest)
+    } else if let Some(rest) = raw.strip_prefix("//?/") {
+        rest.to_string()
+    } else {
+        raw.clone()
+    };
+    let mut url = String::from("file:");
+    if let Some(share) = text.strip_prefix("//") {
+        url.push_str("//");
+        push_url_encoded(&mut url, share.as_bytes());
+    } else {
+        url.push_str("///");
+        let bytes = text.as_bytes();
+        if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+            url.push_str(&text[..2]);
+            push_url_encoded(&mut url, &bytes[2..]);
+        } else {
+            push_url_encoded(&mut url, bytes);
+        }
+    }
+    if !url.ends_with('/') {
+        url.push('/');
+    }
+    url
+}
+
+/// Length from which most Win32 file APIs reject a path unless it is in extended-length form
+#[cfg(target_os = "windows")]
+const MAX_PATH: usize = 260;
+
+/// Convert a path of `MAX_PATH` characters or more into the `\\?\` extended-length form, which
+/// Windows accepts up to about 32767 characters. Shorter paths are returned unchanged
 #[cfg(target_os = "windows")]
+pub(crate) fn long_path(path: PathBuf) -> PathBuf {
+    let Some(text) = path.to_str() else {
+        return path;
+    };
+    // Extended-length paths are used verbatim, so separators joined onto them must be `\`
+    if let Some(rest) = text.strip_prefix(r"\\?\") {
+        return PathBuf::from(format!(r"\\?\{}", rest.replace('/', r"\")));
+    }
+    if text.encode_utf16().count() < MAX_PATH {
+        return path;
+    }
+    // `absolute` resolves `.`, `..` and `/` the way Windows would, which the prefix disables
+    let Some(absolute) = std::path::absolute(&path)
+        .ok()
+        .and_then(|absolute| absolute.to_str().map(str::to_string))
+    else {
+        return path;
+    };
+    match absolute.strip_prefix(r"\\") {
+        Some(unc) => PathBuf::from(format!(r"\\?\UNC\{}", unc)),
+        None => PathBuf::from(format!(r"\\?\{}", absolute)),
+    }
+}
+
+/// Paths of any length work on this platform
+#[cfg(not(target_os = "windows"))]
+pub(crate) fn long_path(path: PathBuf) -> PathBuf {
+    path
+}
+
+/// Canonicalize `path`, also when its last components don't exist yet: the longest existing
+/// ancestor is canonicalized and the rest appended. `None` when the missing rest contains `..`,
+/// which can't be resolved without following links that don't exist
+fn canonicalize_partial(path: &Path) -> io::Result<Option<PathBuf>> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    loop {
+        match fs::canonicalize(existing) {
+            Ok(mut canonical) => {
+                for component in rest.iter().rev() {
+                    match component {
+                        Component::Normal(name) => canonical.push(name),
+                        Component::CurDir => {}
+                        _ => return Ok(None),
+                    }
+                }
+                return Ok(Some(canonical));
+            }
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                let (Some(parent), Some(last)) =
+                    (existing.parent(), existing.components().next_back())
+                else {
+                    return Err(err);
+                };
+                rest.push(last);
+                existing = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+            }
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+/// `name` as Windows stores it as a directory name: Windows silently drops trailing spaces and
+/// dots, so `"MyApp "` creates `MyApp`
+pub(crate) fn windows_dir_name(name: &str) -> &str {
+    name.trim_end_matches([' ', '.'])
+}
+
+/// `name` as a lowercase slug for [`AppData::slugify`]: letters and digits are kept, runs of
+/// spaces, `-`, `_` and `.` become a single `-`, and everything else is dropped
+pub(crate) fn slugify(name: &str) -> String {
+    let mut slug = String::new();
+    let mut separate = false;
+    for c in crate::unicode::compose(name).chars() {
+        if c.is_alphanumeric() {
+            if separate && !slug.is_empty() {
+                slug.push('-');
+            }
+            separate = false;
+            slug.extend(c.to_lowercase());
+        } else if c.is_whitespace() || matches!(c, '-' | '_' | '.') {
+            separate = true;
+        }
+    }
+    slug
+}
+
+/// Refuse an application directory name that is empty, which would share the base directory
+/// with every other app. `dir_name` is `app_name` as used on disk
+pub(crate) fn check_app_dir_name(app_name: &str, dir_name: &str) -> Result<(), AppDataError> {
+    if app_name.is_empty() {
+        Err(AppDataError::InvalidAppName("empty".to_string()))
+    } else if dir_name.is_empty() {
+        Err(AppDataError::InvalidAppName(format!(
+            "{:?} leaves an empty directory name",
+            app_name
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Turn `name` into a single safe path component: separators and characters invalid on any
+/// supported platform become `_`, and `.`/`..` can't escape the parent
+pub(crate) fn sanitize_component(name: &str) -> String {
+    let sanitized: String = name
+        .trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    match sanitized.as_str() {
+        "" | "." | ".." => "_".to_string(),
+        _ => sanitized,
+    }
+}
+
+/// Call `visit` for every regular file in `dir`, descending into subdirectories when
+/// `recursive`. Symlinks are not followed
+fn visit_files(
+    dir: &Path,
+    recursive: bool,
+    mut visit: impl FnMut(&fs::DirEntry),
+) -> io::Result<()> {
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if recursive {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() {
+                visit(&entry);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// All regular files below `dir`, recursively, in sorted order
+pub(crate) fn walk_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    visit_files(dir, true, |entry| files.push(entry.path()))?;
+    files.sort();
+    Ok(files)
+}
+
+/// Move a file or directory tree, copying and deleting it when it can't simply be renamed,
+/// e.g. across filesystems
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if fs::symlink_metadata(from)?.is_dir() {
+        fs::create_dir_all(to)?;
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            move_path(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::remove_dir(from)
+    } else {
+        fs::copy(from, to)?;
+        fs::remove_file(from)
+    }
+}
+
+/// Sibling path of `path` with a process-unique suffix, used for staging and backups
+pub(crate) fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}", process::id(), suffix));
+    path.with_file_name(name)
+}
+
+/// Write `contents` to `path`, flushing it to disk when `durable` is set
+pub(crate) fn write_contents(path: &Path, contents: &[u8], durable: bool) -> io::Result<()> {
+    if !durable {
+        return fs::write(path, contents);
+    }
+    let mut file = fs::File::create(path)?;
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/// Flush a directory entry change (such as a rename) to disk
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> io::Result<()> {
+    fs::File::open(dir)?.sync_all()
+}
+
+/// Directories can't be opened for syncing on this platform, renames are flushed by the OS
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+/// Flush the directory containing `path`
+pub(crate) fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) => sync_dir(dir),
+        None => Ok(()),
+    }
+}
+
+/// Open the external file `src` for [`AppData::import_file`], failing with
+/// [`AppDataError::FileNotFound`] when it doesn't exist
+fn open_import(src: &Path) -> Result<fs::File, AppDataError> {
+    match fs::File::open(src) {
+        Ok(file) => Ok(file),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            Err(AppDataError::FileNotFound(src.to_path_buf()))
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Write `contents` to a staging file next to `path`, removing it again on failure
+fn stage_file(path: &Path, contents: &[u8], durable: bool) -> io::Result<PathBuf> {
+    let staged = sibling_path(path, "tmp");
+    if let Err(err) = write_contents(&staged, contents, durable) {
+        let _ = fs::remove_file(&staged);
+        return Err(err);
+    }
+    Ok(staged)
+}
+
+/// Lookup of environment variables, the process environment or a supplied map
+pub(crate) type EnvLookup<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// The platform application directory, read from the process environment
 pub fn get_sys_app_data_dir() -> Result<PathBuf, AppDataError> {
-    var("APPDATA")
+    sys_app_data_dir_with(&|key| var(key).ok())
+}
+
+#[cfg(target_os = "windows")]
+fn sys_app_data_dir_with(env: EnvLookup) -> Result<PathBuf, AppDataError> {
+    env("APPDATA")
         .map(PathBuf::from)
-        .map_err(|_| AppDataError::EnvVarNotFound("APPDATA".to_string()))
+        .ok_or_else(|| AppDataError::EnvVarNotFound("APPDATA".to_string()))
 }
 
 #[cfg(target_os = "macos")]
-pub fn get_sys_app_data_dir() -> Result<PathBuf, AppDataError> {
-    var("HOME")
+fn sys_app_data_dir_with(env: EnvLookup) -> Result<PathBuf, AppDataError> {
+    env("HOME")
         .map(|home| PathBuf::from(home).join("Library/Application Support"))
-        .map_err(|_| AppDataError::EnvVarNotFound("HOME".to_string()))
+        .ok_or_else(|| AppDataError::EnvVarNotFound("HOME".to_string()))
 }
 
-#[cfg(target_os = "linux")]
-pub fn get_sys_app_data_dir() -> Result<PathBuf, AppDataError> {
-    if let Ok(xdg) = var("XDG_DATA_HOME") {
+/// Linux, the BSDs and illumos/Solaris follow the XDG base directory conventions.
+/// An empty or relative `XDG_DATA_HOME` is ignored, as the specification requires
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+fn sys_app_data_dir_with(env: EnvLookup) -> Result<PathBuf, AppDataError> {
+    if let Some(xdg) = env("XDG_DATA_HOME").filter(|xdg| Path::new(xdg).is_absolute()) {
         Ok(PathBuf::from(xdg))
-    } else if let Ok(home) = var("HOME") {
+    } else if let Some(home) = env("HOME") {
         Ok(PathBuf::from(home).join(".local/share"))
     } else {
         Err(AppDataError::EnvVarNotFound(
@@ -66,6 +666,86 @@ pub fn get_sys_app_data_dir() -> Result<PathBuf, AppDataError> {
     }
 }
 
+/// Fallback for any other target, so new platforms still build
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris"
+)))]
+fn sys_app_data_dir_with(env: EnvLookup) -> Result<PathBuf, AppDataError> {
+    env("HOME")
+        .map(|home| PathBuf::from(home).join(".local/share"))
+        .ok_or_else(|| AppDataError::EnvVarNotFound("HOME".to_string()))
+}
+
+/// The user's home directory: `USERPROFILE`, or `HOMEDRIVE` followed by `HOMEPATH`
+#[cfg(target_os = "windows")]
+fn home_dir_with(env: EnvLookup) -> Result<PathBuf, AppDataError> {
+    let non_empty = |key: &str| env(key).filter(|value| !value.is_empty());
+    if let Some(profile) = non_empty("USERPROFILE") {
+        return Ok(PathBuf::from(profile));
+    }
+    match (non_empty("HOMEDRIVE"), non_empty("HOMEPATH")) {
+        (Some(drive), Some(path)) => Ok(PathBuf::from(drive + &path)),
+        _ => Err(AppDataError::EnvVarNotFound(
+            "USERPROFILE and HOMEDRIVE/HOMEPATH".to_string(),
+        )),
+    }
+}
+
+/// The user's home directory from `HOME`
+#[cfg(not(target_os = "windows"))]
+fn home_dir_with(env: EnvLookup) -> Result<PathBuf, AppDataError> {
+    env("HOME")
+        .filter(|home| !home.is_empty())
+        .map(PathBuf::from)
+        .ok_or_else(|| AppDataError::EnvVarNotFound("HOME".to_string()))
+}
+
+/// List the names of the application directories in the system base directory, or in
+/// `APPDATA_BASE_DIR` when it is set, in sorted order
+///
+/// Only names are returned, nothing inside the directories is read. A missing base directory
+/// gives an empty list.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 按排序列出系统基础目录（设置了 `APPDATA_BASE_DIR` 时为该目录）中的应用目录名称。
+/// 只返回名称，不读取目录内容。基础目录不存在时返回空列表
+/// </details>
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// let installed = app_data::list_apps_in_system_dir().unwrap();
+/// for app in installed.iter().filter(|app| app.starts_with("my_suite_")) {
+///     println!("found data for {}", app);
+/// }
+/// ```
+pub fn list_apps_in_system_dir() -> Result<Vec<String>, AppDataError> {
+    let base = match var(BASE_DIR_VAR) {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => get_sys_app_data_dir()?,
+    };
+    if !base.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut apps = Vec::new();
+    for entry in fs::read_dir(base)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            apps.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    apps.sort();
+    Ok(apps)
+}
+
 /// # Examples
 ///
 /// ```rust
@@ -87,6 +767,142 @@ pub struct AppData {
     /// 是否强制在运行目录下创建 data 目录
     /// </details>
     pub force_local: bool,
+    /// On Unix, use a hidden `./.<app_name>` directory instead of `./data` under the startup path
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 Unix 上，运行目录下使用隐藏的 `./.<app_name>` 目录代替 `./data`
+    /// </details>
+    pub local_hidden: bool,
+    /// Optional version subfolder, so data of different app versions can live side by side
+    /// <details><summary><b>中文说明</b></summary>
+    /// 可选的版本子目录，使不同版本的应用数据可以并存
+    /// </details>
+    pub version_subdir: Option<String>,
+    /// Flush written files (and renames) to disk before write helpers return
+    /// <details><summary><b>中文说明</b></summary>
+    /// 写入辅助方法返回前将文件（及重命名）刷新到磁盘
+    /// </details>
+    pub durable: bool,
+    /// Optional grouping folder between the system directory and the app, `<sys>/<group>/<app_name>`
+    /// <details><summary><b>中文说明</b></summary>
+    /// 可选的分组目录，位于系统目录和应用目录之间，即 `<sys>/<group>/<app_name>`
+    /// </details>
+    pub group: Option<String>,
+    /// When running under CI, resolve into a per-process temporary directory instead of the real
+    /// user directories
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 CI 中运行时，解析到每个进程独立的临时目录，而不是真实的用户目录
+    /// </details>
+    pub sandbox_in_ci: bool,
+    /// Also read override variables such as `APPDATA_BASE_DIR` from a `.env` file in the startup path
+    /// <details><summary><b>中文说明</b></summary>
+    /// 同时从运行目录下的 `.env` 文件读取 `APPDATA_BASE_DIR` 等覆盖变量
+    /// </details>
+    #[cfg(feature = "dotenv")]
+    pub load_dotenv: bool,
+    /// Clock used by TTL and timestamp features, the system clock by default
+    /// <details><summary><b>中文说明</b></summary>
+    /// TTL 与时间戳相关功能使用的时钟，默认为系统时钟
+    /// </details>
+    pub clock: SharedClock,
+    /// On macOS, compose accented characters of `app_name` (NFC) before using it as a directory name
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 macOS 上，将 `app_name` 中的重音字符组合为 NFC 形式后再用作目录名
+    /// </details>
+    pub normalize_unicode: bool,
+    /// Treat an empty local `./data` directory as absent and fall back to the system directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将空的本地 `./data` 目录视为不存在，回退到系统目录
+    /// </details>
+    pub ignore_empty_local: bool,
+    /// Record every directory creation and file write in `.appdata-audit.log`
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 `.appdata-audit.log` 中记录每次目录创建和文件写入
+    /// </details>
+    pub audit: bool,
+    /// Maximum number of snapshots kept per file, unlimited when `None`
+    /// <details><summary><b>中文说明</b></summary>
+    /// 每个文件最多保留的快照数量，为 `None` 时不限制
+    /// </details>
+    pub max_snapshots: Option<usize>,
+    /// Never create the data directory and refuse every write
+    /// <details><summary><b>中文说明</b></summary>
+    /// 从不创建数据目录，并拒绝所有写入
+    /// </details>
+    pub read_only: bool,
+    /// Resolve the system directory of this OS user instead of the current one
+    /// <details><summary><b>中文说明</b></summary>
+    /// 解析该操作系统用户（而非当前用户）的系统目录
+    /// </details>
+    pub user: Option<String>,
+    /// Explicit base directory for the application directory, taking precedence over the local
+    /// `./data` directory and every environment variable
+    /// <details><summary><b>中文说明</b></summary>
+    /// 显式指定的应用目录所在基础目录，优先级高于本地 `./data` 目录和所有环境变量
+    /// </details>
+    pub base_dir: Option<PathBuf>,
+    /// Directory bundled read-only resources are looked up in, the executable's directory when `None`
+    /// <details><summary><b>中文说明</b></summary>
+    /// 查找随程序分发的只读资源的目录，为 `None` 时使用可执行文件所在目录
+    /// </details>
+    pub resource_root: Option<PathBuf>,
+    /// Resolve `HOME` to its symlink target before deriving platform directories from it
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在由 `HOME` 推导平台目录之前，先将其解析为符号链接的目标
+    /// </details>
+    pub resolve_home_symlink: bool,
+    /// Write a `README.txt` explaining the directory into a freshly created data directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在新创建的数据目录中写入说明该目录用途的 `README.txt`
+    /// </details>
+    pub write_readme: bool,
+    /// Text of the `README.txt`, a note naming the app when `None`
+    /// <details><summary><b>中文说明</b></summary>
+    /// `README.txt` 的内容，为 `None` 时使用注明应用名称的默认说明
+    /// </details>
+    pub readme_text: Option<String>,
+    /// Don't look for a local `./data` directory, resolve straight to the system directory.
+    /// `force_local` still uses the local directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 不检测本地 `./data` 目录，直接解析到系统目录。`force_local` 仍然使用本地目录
+    /// </details>
+    pub skip_local_probe: bool,
+    /// Secondary directory every successful write is also copied to
+    /// <details><summary><b>中文说明</b></summary>
+    /// 每次成功写入后同时复制到的备用目录
+    /// </details>
+    pub mirror_dir: Option<PathBuf>,
+    /// Create the missing target when the data directory is a dangling symlink, instead of
+    /// failing with [`AppDataError::DanglingSymlink`]
+    /// <details><summary><b>中文说明</b></summary>
+    /// 数据目录是悬空符号链接时创建缺失的目标，而不是返回 [`AppDataError::DanglingSymlink`]
+    /// </details>
+    pub recreate_symlink_target: bool,
+    /// Check the `.appdata-owner` marker written by [`claim`](AppData::claim) and claim freshly
+    /// created data directories
+    /// <details><summary><b>中文说明</b></summary>
+    /// 检查 `claim` 写入的 `.appdata-owner` 标记，并占用新创建的数据目录
+    /// </details>
+    pub verify_owner: bool,
+    /// Use a lowercase slug of `app_name` as directory name, e.g. `my-cool-app` for `My Cool App!`
+    /// <details><summary><b>中文说明</b></summary>
+    /// 使用 `app_name` 的小写短名作为目录名，例如 `My Cool App!` 对应 `my-cool-app`
+    /// </details>
+    pub slugify: bool,
+    /// Backend storing the data directory and its files, the real filesystem by default
+    /// <details><summary><b>中文说明</b></summary>
+    /// 存储数据目录及其文件的后端，默认为真实文件系统
+    /// </details>
+    pub file_system: SharedFileSystem,
+    /// Use a writable container volume, `$DATA_DIR` or `/data`, ahead of the platform directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 优先使用可写的容器卷（`$DATA_DIR` 或 `/data`），而不是平台目录
+    /// </details>
+    pub prefer_container_volume: bool,
+    /// On Unix, derive platform directories from the home of `SUDO_USER` instead of `HOME`
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 Unix 上由 `SUDO_USER` 的主目录而不是 `HOME` 推导平台目录
+    /// </details>
+    pub respect_sudo_user: bool,
 }
 
 /// Create a new AppData instance
@@ -95,25 +911,48 @@ impl AppData {
         Self {
             app_name: app_name.to_string(),
             force_local: false,
+            local_hidden: false,
+            version_subdir: None,
+            durable: false,
+            group: None,
+            sandbox_in_ci: false,
+            #[cfg(feature = "dotenv")]
+            load_dotenv: false,
+            clock: SharedClock::default(),
+            normalize_unicode: false,
+            ignore_empty_local: false,
+            audit: false,
+            max_snapshots: None,
+            read_only: false,
+            user: None,
+            base_dir: None,
+            resource_root: None,
+            resolve_home_symlink: false,
+            write_readme: false,
+            readme_text: None,
+            skip_local_probe: false,
+            mirror_dir: None,
+            recreate_symlink_target: false,
+            verify_owner: false,
+            slugify: false,
+            file_system: SharedFileSystem::default(),
+            prefer_container_volume: false,
+            respect_sudo_user: false,
         }
     }
 
     pub fn with_force_local(app_name: &str, force_local: bool) -> Self {
         Self {
-            app_name: app_name.to_string(),
             force_local,
+            ..Self::new(app_name)
         }
     }
-}
 
-impl AppData {
-    /// Return the application data directory according to the search rules, ensuring that the directory is valid and exists
-    ///
-    /// <strong>Besides logging, you don't need to care about this method, just keep track of the data file path</strong>
+    /// Like [`Default`], but returns an error when `CARGO_PKG_NAME` is not set instead of
+    /// silently falling back to `force_local`
     ///
     /// <details><summary><b>中文说明</b></summary>
-    /// 按照寻找规则返回应用数据目录,会确保目录有效且存在。
-    /// <strong>除了记录日志，你并不需要关心此方法,保持关注数据文件路径即可</strong>
+    /// 与 [`Default`] 类似，但在未设置 `CARGO_PKG_NAME` 时返回错误，而不是静默回退为 `force_local`
     /// </details>
     ///
     /// # Examples
@@ -121,119 +960,4390 @@ impl AppData {
     /// ```rust
     /// use app_data::AppData;
     ///
-    /// let app_data = AppData::default();
-    /// let data_dir = app_data.ensure_data_dir().unwrap();
-    /// println!("data_dir: {}", data_dir.display());
+    /// let app_data = AppData::try_default().unwrap_or_else(|_| AppData::new("my_app"));
     /// ```
-    pub fn ensure_data_dir(&self) -> Result<PathBuf, AppDataError> {
-        let path = current_dir().map_err(|e| AppDataError::CurrentDirError(e.to_string()))?;
-        let root_path = path.join("data");
-        if root_path.exists() {
-            return Ok(root_path);
-        }
-        if self.force_local {
-            fs::create_dir_all(&root_path)?;
-            return Ok(root_path);
-        }
-        let sys_path = get_sys_app_data_dir()?.join(&self.app_name);
-        if !sys_path.exists() {
-            fs::create_dir_all(&sys_path)?;
-        }
-        Ok(sys_path)
+    pub fn try_default() -> Result<Self, AppDataError> {
+        env::var("CARGO_PKG_NAME")
+            .map(|app_name| Self::new(&app_name))
+            .map_err(|_| AppDataError::EnvVarNotFound("CARGO_PKG_NAME".to_string()))
     }
 
-    /// 获取数据目录中的文件路径
+    /// Namespace the data directory by app version, e.g. `<data>/my_app/v2`
     ///
-    /// Get the file path in the data directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按应用版本划分数据目录，例如 `<data>/my_app/v2`
+    /// </details>
     ///
     /// # Examples
     ///
     /// ```rust
     /// use app_data::AppData;
     ///
-    /// let app_data = AppData::new("my_app");
-    /// let file_path = app_data.get_file_path("config.json").unwrap();
+    /// let app_data = AppData::with_version("my_app", "v2");
+    /// assert_eq!(app_data.version_subdir.as_deref(), Some("v2"));
     /// ```
-    pub fn get_file_path(&self, file_name: &str) -> Result<PathBuf, AppDataError> {
-        let data_dir = self.ensure_data_dir()?;
-        Ok(data_dir.join(file_name))
+    pub fn with_version(app_name: &str, version: &str) -> Self {
+        Self {
+            version_subdir: Some(version.to_string()),
+            ..Self::new(app_name)
+        }
     }
-}
 
-impl Default for AppData {
-    /// Default using `CARGO_PKG_NAME` as the application name, if `CARGO_PKG_NAME` is not set,
-    /// then `force_local` is true
+    /// Place the system data directory inside a shared grouping folder, `<sys>/<group>/<app_name>`,
+    /// so a suite of related tools can be backed up or removed together
+    ///
+    /// The group name is sanitized into a single path component.
     ///
     /// <details><summary><b>中文说明</b></summary>
-    /// 默认使用 `CARGO_PKG_NAME` 作为应用名称，如果 `CARGO_PKG_NAME` 未设置，则`force_local` 为true
+    /// 将系统数据目录放入共享的分组目录 `<sys>/<group>/<app_name>`，便于一组相关工具统一备份或删除。
+    /// 分组名会被清理为单个路径组件
     /// </details>
-    fn default() -> Self {
-        let app_name = env::var("CARGO_PKG_NAME");
-        if app_name.is_err() {
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::with_group("editor", "my_suite");
+    /// assert_eq!(app_data.group.as_deref(), Some("my_suite"));
+    /// ```
+    pub fn with_group(app_name: &str, group: &str) -> Self {
+        Self {
+            group: Some(sanitize_component(group)),
+            ..Self::new(app_name)
+        }
+    }
+
+    /// Create an instance for another application that shares every option of this one, such as
+    /// the base directory, group, version and local mode
+    ///
+    /// Launchers and suite tools can configure one instance and switch the name to reach each
+    /// app's data. Nothing is resolved or created. Note that plain local mode uses `./data`
+    /// regardless of the name, so siblings only get separate local directories with
+    /// [`local_hidden`](AppData::local_hidden).
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 为另一个应用创建实例，共享当前实例的全部选项，例如基础目录、分组、版本和本地模式。
+    /// 启动器和套件工具可以只配置一个实例，再切换名称访问各个应用的数据。不会解析或创建任何目录。
+    /// 注意普通本地模式无论名称如何都使用 `./data`，只有配合 `local_hidden` 时各应用才有独立的本地目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let editor = AppData::with_group("editor", "my_suite");
+    /// let viewer = editor.sibling("viewer");
+    /// assert_eq!(viewer.app_name, "viewer");
+    /// assert_eq!(viewer.group.as_deref(), Some("my_suite"));
+    /// ```
+    pub fn sibling(&self, other_app_name: &str) -> Self {
+        Self {
+            app_name: other_app_name.to_string(),
+            ..self.clone()
+        }
+    }
+
+    /// Turn `app_name` into a portable slug before using it as a directory name, so
+    /// `AppData::new("My Cool App!")` resolves to `my-cool-app`
+    ///
+    /// Letters and digits are lowercased and kept, including non-ASCII ones, spaces, `-`, `_`
+    /// and `.` collapse into single hyphens, and other characters are dropped. Off by default,
+    /// which uses `app_name` as given.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在用作目录名之前将 `app_name` 转换为可移植的短名，使 `AppData::new("My Cool App!")` 解析为 `my-cool-app`。
+    /// 字母和数字（包括非 ASCII 字符）转为小写并保留，空格、`-`、`_` 和 `.` 合并为单个连字符，其他字符被去除。
+    /// 默认关闭，按原样使用 `app_name`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("My Cool App!").slugify(true);
+    /// ```
+    pub fn slugify(mut self, slugify: bool) -> Self {
+        self.slugify = slugify;
+        self
+    }
+
+    /// Use a hidden `./.<app_name>` directory instead of `./data` for local mode on Unix,
+    /// matching conventions like `./.git` and `./.cache`. Has no effect on other platforms
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 Unix 上本地模式使用隐藏的 `./.<app_name>` 目录代替 `./data`，与 `./.git`、`./.cache` 等惯例一致。
+    /// 其他平台无效果
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::with_force_local("my_app", true).local_hidden(true);
+    /// ```
+    pub fn local_hidden(mut self, local_hidden: bool) -> Self {
+        self.local_hidden = local_hidden;
+        self
+    }
+
+    /// Skip looking for a local `./data` directory and resolve straight to the system directory
+    ///
+    /// For apps that never run in portable mode this saves a `current_dir()` call and a stat on
+    /// every resolution, which can be slow on network-mounted working directories.
+    /// [`force_local`](AppData::force_local) still always uses the local directory.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 跳过本地 `./data` 目录检测，直接解析到系统目录。对于从不以便携模式运行的应用，
+    /// 可省去每次解析时的 `current_dir()` 调用和一次 stat，在网络挂载的工作目录上这可能很慢。
+    /// `force_local` 仍然始终使用本地目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_server").skip_local_probe(true);
+    /// ```
+    pub fn skip_local_probe(mut self, skip_local_probe: bool) -> Self {
+        self.skip_local_probe = skip_local_probe;
+        self
+    }
+
+    /// Write a `README.txt` into the data directory when it is created, telling users who come
+    /// across the directory which app owns it and that deleting it resets the app
+    ///
+    /// An existing `README.txt` is never overwritten. Change the text with
+    /// [`readme_text`](AppData::readme_text).
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 创建数据目录时写入 `README.txt`，告诉偶然发现该目录的用户它属于哪个应用，以及删除它会重置应用。
+    /// 已存在的 `README.txt` 不会被覆盖。可通过 `readme_text` 修改内容
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").write_readme(true);
+    /// ```
+    pub fn write_readme(mut self, write_readme: bool) -> Self {
+        self.write_readme = write_readme;
+        self
+    }
+
+    /// Replace the default text of the [`write_readme`](AppData::write_readme) file
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 替换 `write_readme` 写入文件的默认内容
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app")
+    ///     .write_readme(true)
+    ///     .readme_text("Settings and saves of My App. Delete to start over.\n");
+    /// ```
+    pub fn readme_text(mut self, text: &str) -> Self {
+        self.readme_text = Some(text.to_string());
+        self
+    }
+
+    /// Treat an empty local `./data` directory as not present, so a stray empty directory left by
+    /// another tool doesn't switch resolution to local mode. `force_local` still always uses it
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将空的本地 `./data` 目录视为不存在，避免其他工具遗留的空目录意外切换到本地模式。
+    /// `force_local` 仍然始终使用本地目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").ignore_empty_local(true);
+    /// ```
+    pub fn ignore_empty_local(mut self, ignore_empty_local: bool) -> Self {
+        self.ignore_empty_local = ignore_empty_local;
+        self
+    }
+
+    /// Append a line with the timestamp, action and path to `.appdata-audit.log` in the data
+    /// directory each time a directory is created or a file is written. Off by default
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 每次创建目录或写入文件时，向数据目录中的 `.appdata-audit.log` 追加一行，记录时间戳、操作和路径。默认关闭
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").audit(true);
+    /// ```
+    pub fn audit(mut self, audit: bool) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Also copy every successful write to `dir`, such as a local backup or a synced folder,
+    /// keeping the same relative paths. `None` turns mirroring off
+    ///
+    /// The data directory stays the primary copy: when copying to the mirror fails after the
+    /// write succeeded, the write method returns [`AppDataError::MirrorFailed`], which callers
+    /// can treat as a warning. [`FileAppender`](crate::FileAppender)s copy their file each time
+    /// they are flushed. Deletions are not mirrored.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将每次成功的写入同时复制到 `dir`（例如本地备份或同步文件夹），保持相同的相对路径。`None` 关闭镜像。
+    /// 数据目录仍是主副本：写入成功但复制到镜像失败时，写入方法返回 [`AppDataError::MirrorFailed`]，调用方可将其视为警告。
+    /// `FileAppender` 在每次刷新时复制其文件。删除操作不会被镜像
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::{AppData, AppDataError};
+    ///
+    /// let app_data = AppData::new("my_app").mirror_to(Some("/mnt/backup/my_app".into()));
+    /// match app_data.write_file("notes.md", "# Notes") {
+    ///     Ok(()) => {}
+    ///     Err(AppDataError::MirrorFailed(msg)) => eprintln!("warning: {}", msg),
+    ///     Err(err) => panic!("{}", err),
+    /// }
+    /// ```
+    pub fn mirror_to(mut self, dir: Option<PathBuf>) -> Self {
+        self.mirror_dir = dir;
+        self
+    }
+
+    /// Keep at most `max` snapshots per file, deleting the oldest when
+    /// [`snapshot_file`](AppData::snapshot_file) takes a new one
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 每个文件最多保留 `max` 个快照，`snapshot_file` 生成新快照时删除最旧的快照
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").max_snapshots(10);
+    /// ```
+    pub fn max_snapshots(mut self, max: usize) -> Self {
+        self.max_snapshots = Some(max);
+        self
+    }
+
+    /// Run against a read-only installation, such as an immutable OS image: `ensure_data_dir`
+    /// returns the expected path without creating it, reads work as usual, and every method
+    /// that would create, modify or delete something returns [`AppDataError::ReadOnly`] up front
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 用于只读安装环境（如不可变系统镜像）：`ensure_data_dir` 返回预期路径但不创建目录，读取照常进行，
+    /// 所有会创建、修改或删除内容的方法都会提前返回 [`AppDataError::ReadOnly`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").read_only(true);
+    /// assert!(app_data.write_file("config.toml", "").is_err());
+    /// ```
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Resolve `HOME` to its symlink target once during resolution, so that every app sees the
+    /// same path, e.g. `/export/home/user` instead of a `/home/user` link to it
+    ///
+    /// Off by default, since some setups rely on the symlink path. Only directories derived
+    /// from `HOME` are affected, explicit variables such as `XDG_DATA_HOME` are used as given.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在解析时将 `HOME` 解析为符号链接的目标，使所有应用看到相同的路径，例如 `/export/home/user`
+    /// 而不是指向它的 `/home/user` 链接。默认关闭，因为有些环境依赖符号链接路径。
+    /// 只影响由 `HOME` 推导的目录，`XDG_DATA_HOME` 等显式变量按原样使用
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").resolve_home_symlink(true);
+    /// ```
+    pub fn resolve_home_symlink(mut self, resolve_home_symlink: bool) -> Self {
+        self.resolve_home_symlink = resolve_home_symlink;
+        self
+    }
+
+    /// Create the missing target when the data directory is a symlink to a directory that no
+    /// longer exists, e.g. a synced folder that was moved
+    ///
+    /// Off by default: [`ensure_data_dir`](AppData::ensure_data_dir) then fails with
+    /// [`AppDataError::DanglingSymlink`], so the link can be fixed instead of silently starting
+    /// over in an empty directory. When enabled, the target is created and treated as a freshly
+    /// created data directory.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 数据目录是指向已不存在目录的符号链接时（例如同步文件夹被移动），创建缺失的目标。
+    /// 默认关闭：此时 `ensure_data_dir` 返回 [`AppDataError::DanglingSymlink`]，以便修复链接，而不是悄悄从空目录重新开始。
+    /// 开启后会创建目标目录，并视为新创建的数据目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").recreate_symlink_target(true);
+    /// ```
+    pub fn recreate_symlink_target(mut self, recreate_symlink_target: bool) -> Self {
+        self.recreate_symlink_target = recreate_symlink_target;
+        self
+    }
+
+    /// Make [`ensure_data_dir`](AppData::ensure_data_dir) fail with
+    /// [`AppDataError::OwnershipConflict`] when the data directory was
+    /// [`claim`](AppData::claim)ed by a different app or version
+    ///
+    /// Freshly created data directories are claimed right away. Existing directories without a
+    /// marker are accepted, so enabling this doesn't break directories created before.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 数据目录已被其他应用或版本通过 `claim` 占用时，使 `ensure_data_dir` 返回 [`AppDataError::OwnershipConflict`]。
+    /// 新创建的数据目录会立即被占用。没有标记的现有目录会被接受，因此开启后不影响之前创建的目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").verify_owner(true);
+    /// ```
+    pub fn verify_owner(mut self, verify_owner: bool) -> Self {
+        self.verify_owner = verify_owner;
+        self
+    }
+
+    /// Resolve to `<volume>/<app_name>` when the process runs with a writable persistent volume,
+    /// as mounted in Docker or Kubernetes deployments
+    ///
+    /// The volume is the directory named by `DATA_DIR`, or `/data` on Unix. It is only used
+    /// when it exists and a probe file can be created in it, otherwise resolution continues
+    /// with the platform directory. Explicit placements (`base_dir`, `APPDATA_BASE_DIR`,
+    /// `for_user` and the CI sandbox) still take precedence. Off by default.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 进程带有可写的持久卷时（如 Docker 或 Kubernetes 部署中挂载的卷），解析到 `<volume>/<app_name>`。
+    /// 卷为 `DATA_DIR` 指定的目录，Unix 上默认为 `/data`。只有目录存在且能在其中创建探测文件时才会使用，否则继续解析到平台目录。
+    /// 显式指定的位置（`base_dir`、`APPDATA_BASE_DIR`、`for_user` 和 CI 沙箱）仍然优先。默认关闭
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_service").prefer_container_volume(true);
+    /// ```
+    pub fn prefer_container_volume(mut self, prefer_container_volume: bool) -> Self {
+        self.prefer_container_volume = prefer_container_volume;
+        self
+    }
+
+    /// When running under `sudo`, resolve platform directories in the home of the invoking
+    /// user named by `SUDO_USER` instead of root's, so data and config don't vanish into
+    /// `/root`
+    ///
+    /// The home directory is looked up in the user database. Only directories derived from
+    /// `HOME` are affected, explicit variables such as `XDG_CONFIG_HOME` are used as given.
+    /// Without `SUDO_USER`, or when the user can't be found, `HOME` is used. Has no effect on
+    /// Windows. Off by default.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 `sudo` 下运行时，在 `SUDO_USER` 指定的调用用户主目录而不是 root 的主目录中解析平台目录，避免数据和配置落入 `/root`。
+    /// 主目录从用户数据库中查询。只影响由 `HOME` 推导的目录，`XDG_CONFIG_HOME` 等显式变量按原样使用。
+    /// 没有 `SUDO_USER` 或找不到该用户时使用 `HOME`。在 Windows 上无效果。默认关闭
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_cli").respect_sudo_user(true);
+    /// ```
+    pub fn respect_sudo_user(mut self, respect_sudo_user: bool) -> Self {
+        self.respect_sudo_user = respect_sudo_user;
+        self
+    }
+
+    /// Resolve the system directory of the OS user `username` instead of the user the process
+    /// runs as, for example from a Windows service or Linux daemon writing into a logged-in
+    /// user's profile
+    ///
+    /// The home directory comes from the user database (`/etc/passwd`, Directory Services on
+    /// macOS, the `ProfileList` registry key on Windows). The user's own environment can't be
+    /// seen, so `XDG_DATA_HOME` of that user is not honored. Unknown users fail with
+    /// [`AppDataError::UserNotFound`] on resolution.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 解析操作系统用户 `username` 的系统目录，而不是进程运行身份的目录，
+    /// 例如 Windows 服务或 Linux 守护进程需要写入已登录用户的配置目录时。
+    /// 主目录来自用户数据库（`/etc/passwd`、macOS 上的目录服务、Windows 上的 `ProfileList` 注册表项）。
+    /// 无法读取该用户自己的环境变量，因此不会使用其 `XDG_DATA_HOME`。用户不存在时解析返回 [`AppDataError::UserNotFound`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").for_user("alice");
+    /// app_data.write_file("sync-state.json", "{}").unwrap();
+    /// ```
+    pub fn for_user(mut self, username: &str) -> Self {
+        self.user = Some(username.to_string());
+        self
+    }
+
+    /// Look up bundled resources in `root` instead of the executable's directory
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 `root` 中而不是可执行文件所在目录中查找随程序分发的资源
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").with_resource_root("/usr/share/my_app");
+    /// ```
+    pub fn with_resource_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.resource_root = Some(root.into());
+        self
+    }
+
+    /// Apply a base directory passed on the command line, such as the value of a `--data-dir`
+    /// flag parsed by the host's own CLI, as [`base_dir`](AppData::base_dir), the override with
+    /// the highest precedence
+    ///
+    /// `None` (the flag wasn't given) leaves the instance unchanged. A relative path is taken
+    /// relative to the current directory, an empty one fails with [`AppDataError::InvalidPath`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将命令行传入的基础目录（例如宿主程序自行解析的 `--data-dir` 参数值）设置为优先级最高的覆盖项 `base_dir`。
+    /// `None`（未提供该参数）时保持不变。相对路径以当前目录为基准，空路径返回 [`AppDataError::InvalidPath`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let data_dir_flag = std::env::args().skip_while(|arg| arg != "--data-dir").nth(1);
+    /// let mut app_data = AppData::new("my_app");
+    /// app_data.with_base_arg(data_dir_flag.as_deref()).unwrap();
+    /// ```
+    pub fn with_base_arg(&mut self, arg: Option<&str>) -> Result<(), AppDataError> {
+        let Some(arg) = arg else {
+            return Ok(());
+        };
+        if arg.is_empty() {
+            return Err(AppDataError::InvalidPath(arg.to_string()));
+        }
+        let path = Path::new(arg);
+        self.base_dir = Some(if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            current_dir()
+                .map_err(|e| AppDataError::CurrentDirError(e.to_string()))?
+                .join(path)
+        });
+        Ok(())
+    }
+
+    /// Fail with [`AppDataError::ReadOnly`] in read-only mode
+    pub(crate) fn deny_read_only(&self, target: &str) -> Result<(), AppDataError> {
+        if self.read_only {
+            return Err(AppDataError::ReadOnly(target.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Call `fsync` after every write, and on the parent directory after atomic renames, so
+    /// that a successful write survives a power loss. Off by default for performance
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 每次写入后调用 `fsync`，原子重命名后同时同步父目录，确保写入成功后即使断电也不会丢失。
+    /// 出于性能考虑默认关闭
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").durable(true);
+    /// ```
+    pub fn durable(mut self, durable: bool) -> Self {
+        self.durable = durable;
+        self
+    }
+
+    /// When the `CI` or `GITHUB_ACTIONS` environment variable is set, resolve into a unique
+    /// per-process temporary directory instead of the home directory, keeping tests hermetic
+    /// and parallel runs apart. Outside CI this has no effect
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 设置了 `CI` 或 `GITHUB_ACTIONS` 环境变量时，解析到每个进程唯一的临时目录而不是用户主目录，
+    /// 使测试相互隔离、并行运行互不干扰。非 CI 环境下无效果
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").sandbox_in_ci(true);
+    /// ```
+    pub fn sandbox_in_ci(mut self, sandbox_in_ci: bool) -> Self {
+        self.sandbox_in_ci = sandbox_in_ci;
+        self
+    }
+
+    /// On macOS, normalize `app_name` to its composed (NFC) form, so composed and decomposed
+    /// spellings of an accented name like `Café` resolve to the same directory. HFS+ stores file
+    /// names decomposed, so without this a directory may exist but not be found by exact name.
+    /// Has no effect on other platforms
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 macOS 上将 `app_name` 规范化为组合形式（NFC），使 `Café` 等带重音名称的组合与分解写法解析到同一目录。
+    /// HFS+ 以分解形式存储文件名，不做规范化时可能出现目录存在却按名称找不到的情况。其他平台无效果
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("Café").normalize_unicode(true);
+    /// ```
+    pub fn normalize_unicode(mut self, normalize_unicode: bool) -> Self {
+        self.normalize_unicode = normalize_unicode;
+        self
+    }
+
+    /// Read time from `clock` instead of the system clock, e.g. a [`FakeClock`](crate::FakeClock)
+    /// to test TTL expiry without sleeping
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 使用 `clock` 代替系统时钟读取时间，例如用 [`FakeClock`](crate::FakeClock) 测试 TTL 过期而无需等待
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{sync::Arc, time::SystemTime};
+    /// use app_data::{AppData, FakeClock};
+    ///
+    /// let app_data = AppData::new("my_app").with_clock(Arc::new(FakeClock::new(SystemTime::now())));
+    /// ```
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    /// Store the data directory and its files in `file_system` instead of the real filesystem,
+    /// e.g. an in-memory, encrypted or archive-backed backend
+    ///
+    /// The backend serves creating the data directory and the basic file operations listed on
+    /// [`FileSystem`]. The data directory is only created in the backend, so the
+    /// [`write_readme`](AppData::write_readme) file, the [`verify_owner`](AppData::verify_owner)
+    /// marker and its check and [`recreate_symlink_target`](AppData::recreate_symlink_target)
+    /// don't apply. Writes through the backend are neither recorded in the audit log, copied by
+    /// [`mirror_to`](AppData::mirror_to) nor flushed for [`durable`](AppData::durable).
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将数据目录及其文件存储在 `file_system` 中而不是真实文件系统，例如内存、加密或基于归档文件的后端。
+    /// 后端负责创建数据目录以及 [`FileSystem`] 中列出的基本文件操作。数据目录只在后端中创建，因此 `write_readme` 文件、
+    /// `verify_owner` 标记及其检查和 `recreate_symlink_target` 都不生效。通过后端的写入不会记录到审计日志，不会被 `mirror_to` 复制，也不会因 `durable` 而刷新到磁盘
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::sync::Arc;
+    /// use app_data::{AppData, StdFileSystem};
+    ///
+    /// let app_data = AppData::new("my_app").with_file_system(Arc::new(StdFileSystem));
+    /// ```
+    pub fn with_file_system(mut self, file_system: Arc<dyn FileSystem>) -> Self {
+        self.file_system = SharedFileSystem::new(file_system);
+        self
+    }
+
+    /// Read override variables such as `APPDATA_BASE_DIR` from a `.env` file in the startup
+    /// path, so they don't need to be exported in the shell. The real environment always wins
+    /// over values from `.env`
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 从运行目录下的 `.env` 文件读取 `APPDATA_BASE_DIR` 等覆盖变量，无需在 shell 中导出。
+    /// 真实环境变量始终优先于 `.env` 中的值
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").load_dotenv(true);
+    /// ```
+    #[cfg(feature = "dotenv")]
+    pub fn load_dotenv(mut self, load_dotenv: bool) -> Self {
+        self.load_dotenv = load_dotenv;
+        self
+    }
+}
+
+impl AppData {
+    /// Return the application data directory according to the search rules, ensuring that the directory is valid and exists
+    ///
+    /// <strong>Besides logging, you don't need to care about this method, just keep track of the data file path</strong>
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按照寻找规则返回应用数据目录,会确保目录有效且存在。
+    /// <strong>除了记录日志，你并不需要关心此方法,保持关注数据文件路径即可</strong>
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::default();
+    /// let data_dir = app_data.ensure_data_dir().unwrap();
+    /// println!("data_dir: {}", data_dir.display());
+    /// ```
+    pub fn ensure_data_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.ensure_data_dir_with_init(|_| Ok(()))
+    }
+
+    /// Like [`ensure_data_dir`](AppData::ensure_data_dir), and run `init` right after the data
+    /// directory was freshly created, e.g. to seed default files
+    ///
+    /// `init` runs exactly once, in the call that created the directory, even when several
+    /// threads or processes race to create it. When the directory already exists `init` is
+    /// skipped. Concurrent callers may see the directory before `init` has finished. If `init`
+    /// fails, the directory is removed again so the next call retries.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 与 `ensure_data_dir` 相同，并在数据目录刚被创建后立即运行 `init`，例如写入默认文件。
+    /// 即使多个线程或进程同时创建目录，`init` 也只会在创建该目录的那次调用中运行一次；目录已存在时跳过 `init`。
+    /// 并发调用方可能在 `init` 完成前就看到该目录。
+    /// `init` 失败时会再次删除该目录，以便下一次调用重试
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data
+    ///     .ensure_data_dir_with_init(|dir| {
+    ///         std::fs::write(dir.join("config.toml"), "theme = \"light\"")?;
+    ///         Ok(())
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn ensure_data_dir_with_init(
+        &self,
+        init: impl FnOnce(&Path) -> Result<(), AppDataError>,
+    ) -> Result<PathBuf, AppDataError> {
+        let path = self.resolve_data_dir()?;
+        self.check_safe_location(&path)?;
+        let path = long_path(path);
+        if let Some(file_system) = self.file_system.custom() {
+            if !file_system.exists(&path) && !self.read_only {
+                file_system.create_dir_all(&path)?;
+                if let Err(err) = init(&path) {
+                    let _ = file_system.remove_dir_all(&path);
+                    return Err(err);
+                }
+            }
+            return Ok(path);
+        }
+        if path.is_dir() || self.read_only {
+            return self.checked_owner(path);
+        }
+        // The directory actually created, the symlink's target when recreating it
+        let created_dir = match dangling_symlink_target(&path)? {
+            Some(target) if self.recreate_symlink_target => target,
+            Some(_) => return Err(AppDataError::DanglingSymlink(path)),
+            None => path.clone(),
+        };
+        if !create_dir_race_safe(&created_dir)? {
+            return self.checked_owner(path);
+        }
+        self.audit_event("create_dir", &path)?;
+        if self.verify_owner {
+            self.write_owner(&path)?;
+        }
+        if self.write_readme {
+            self.create_readme(&path)?;
+        }
+        if let Err(err) = init(&path) {
+            let _ = fs::remove_dir_all(&created_dir);
+            return Err(err);
+        }
+        Ok(path)
+    }
+
+    /// `data_dir`, after checking its owner when [`verify_owner`](AppData::verify_owner) is set
+    fn checked_owner(&self, data_dir: PathBuf) -> Result<PathBuf, AppDataError> {
+        if self.verify_owner {
+            self.check_owner(&data_dir)?;
+        }
+        Ok(data_dir)
+    }
+
+    /// Write the [`write_readme`](AppData::write_readme) file into `dir` unless it exists
+    fn create_readme(&self, dir: &Path) -> Result<(), AppDataError> {
+        let text = match &self.readme_text {
+            Some(text) => text.clone(),
+            None => format!(
+                "This directory holds the data of {}.\n\
+                 It was created automatically. Deleting it resets {} to its initial state.\n",
+                self.app_name, self.app_name
+            ),
+        };
+        let readme = dir.join(README_FILE);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&readme)
+        {
+            Ok(mut file) => file.write_all(text.as_bytes())?,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => return Ok(()),
+            Err(err) => return Err(err.into()),
+        }
+        self.audit_event("write", &readme)
+    }
+
+    /// List the version subfolders next to this instance's data directory
+    ///
+    /// Returns an empty list when the application directory doesn't exist yet.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 列出与当前数据目录并列的版本子目录。应用目录尚不存在时返回空列表
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::with_version("my_app", "v2");
+    /// for version in app_data.list_versions().unwrap() {
+    ///     println!("found data for {}", version);
+    /// }
+    /// ```
+    pub fn list_versions(&self) -> Result<Vec<String>, AppDataError> {
+        let app_dir = self.resolve_app_dir()?;
+        if !app_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut versions = Vec::new();
+        for entry in fs::read_dir(app_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                versions.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        versions.sort();
+        Ok(versions)
+    }
+
+    /// Count the regular files in the data directory, including those in subdirectories when
+    /// `recursive`
+    ///
+    /// The files are counted while walking, without collecting their paths. Symlinks are not
+    /// followed, and a missing data directory counts as empty.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 统计数据目录中的普通文件数量，`recursive` 为 true 时包含子目录中的文件。
+    /// 遍历时直接计数，不收集路径。不跟随符号链接，数据目录不存在时计为 0
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let saves = AppData::new("my_game").file_count(true).unwrap();
+    /// println!("{} saved games", saves);
+    /// ```
+    pub fn file_count(&self, recursive: bool) -> Result<usize, AppDataError> {
+        let data_dir = self.data_dir_path()?;
+        if self.file_system.custom().is_some() {
+            let files = self.stored_files(&data_dir)?;
+            return Ok(files
+                .iter()
+                .filter(|path| recursive || path.parent() == Some(&data_dir))
+                .count());
+        }
+        if !data_dir.is_dir() {
+            return Ok(0);
+        }
+        let mut count = 0;
+        visit_files(&data_dir, recursive, |_| count += 1)?;
+        Ok(count)
+    }
+
+    /// List every file below the data directory ordered by modification time
+    ///
+    /// Files with the same modification time are ordered by path, so the result is stable.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按修改时间列出数据目录下的所有文件。修改时间相同的文件按路径排序，结果稳定
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// for path in app_data.list_files_by_mtime(true).unwrap().iter().take(5) {
+    ///     println!("recent: {}", path.display());
+    /// }
+    /// ```
+    pub fn list_files_by_mtime(&self, newest_first: bool) -> Result<Vec<PathBuf>, AppDataError> {
+        self.deny_custom_file_system("list_files_by_mtime")?;
+        let data_dir = self.data_dir_path()?;
+        if !data_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for path in walk_files(&data_dir)? {
+            let modified = fs::metadata(&path)?.modified()?;
+            files.push((modified, path));
+        }
+        files.sort_by(|(a_time, a_path), (b_time, b_path)| {
+            let by_time = if newest_first {
+                b_time.cmp(a_time)
+            } else {
+                a_time.cmp(b_time)
+            };
+            by_time.then_with(|| a_path.cmp(b_path))
+        });
+        Ok(files.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// Get the least recently modified file below the data directory, or `None` when there are no
+    /// files. Ties are broken by path like [`list_files_by_mtime`](AppData::list_files_by_mtime)
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取数据目录下最久未修改的文件，没有文件时返回 `None`。修改时间相同时按路径排序，与 `list_files_by_mtime` 一致
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if let Some(oldest) = app_data.oldest_file().unwrap() {
+    ///     println!("evict {} first", oldest.display());
+    /// }
+    /// ```
+    pub fn oldest_file(&self) -> Result<Option<PathBuf>, AppDataError> {
+        Ok(self.list_files_by_mtime(false)?.into_iter().next())
+    }
+
+    /// Get the `plugins` directory inside the data directory, creating it if it doesn't exist
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取数据目录中的 `plugins` 目录，不存在时自动创建
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let plugins_dir = AppData::new("my_app").plugins_dir().unwrap();
+    /// println!("drop plugins into {}", plugins_dir.display());
+    /// ```
+    pub fn plugins_dir(&self) -> Result<PathBuf, AppDataError> {
+        let dir = self.ensure_data_dir()?.join("plugins");
+        if !dir.exists() && !self.read_only {
+            create_dir_race_safe(&dir)?;
+            self.audit_event("create_dir", &dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// Find the plugins with extension `ext` (with or without the leading `.`) in
+    /// [`plugins_dir`](AppData::plugins_dir), in sorted order
+    ///
+    /// Both files and directories match, so bundle plugins such as `reverb.vst3/` are found too.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按排序返回 `plugins` 目录中扩展名为 `ext`（可带或不带前导 `.`）的插件。
+    /// 文件和目录都会匹配，因此 `reverb.vst3/` 这类目录形式的插件也能被发现
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// for plugin in AppData::new("my_app").discover_plugins("wasm").unwrap() {
+    ///     println!("loading {}", plugin.display());
+    /// }
+    /// ```
+    pub fn discover_plugins(&self, ext: &str) -> Result<Vec<PathBuf>, AppDataError> {
+        let ext = ext.strip_prefix('.').unwrap_or(ext);
+        let mut plugins = Vec::new();
+        let dir = self.plugins_dir()?;
+        if !dir.is_dir() {
+            return Ok(plugins);
+        }
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|found| found == ext) {
+                plugins.push(path);
+            }
+        }
+        plugins.sort();
+        Ok(plugins)
+    }
+
+    /// Return the data directory as a percent-encoded `file://` URL ending with `/`, e.g. for a
+    /// webview base URL or links to local files
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 以百分号编码的 `file://` URL（以 `/` 结尾）返回数据目录，可用作 webview 的基础 URL 或本地文件链接
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let url = app_data.data_dir_url().unwrap();
+    /// assert!(url.starts_with("file://"));
+    /// ```
+    pub fn data_dir_url(&self) -> Result<String, AppDataError> {
+        Ok(dir_to_file_url(&self.ensure_data_dir()?))
+    }
+
+    /// Return the data directory quoted for the platform shell (POSIX `sh` on Unix, `cmd.exe`
+    /// and PowerShell on Windows), for generated shell commands and config snippets
+    ///
+    /// Paths that aren't valid UTF-8 fail with [`AppDataError::InvalidPath`]. On Windows `cmd.exe`
+    /// still expands `%VAR%` inside quotes.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 返回按平台 shell 规则加引号的数据目录（Unix 上为 POSIX `sh`，Windows 上为 `cmd.exe` 和 PowerShell），
+    /// 用于生成 shell 命令和配置片段。非 UTF-8 路径返回 [`AppDataError::InvalidPath`]。
+    /// 在 Windows 上，`cmd.exe` 仍会展开引号内的 `%VAR%`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let dir = AppData::new("my_app").data_dir_shell().unwrap();
+    /// println!("tar czf backup.tar.gz -C {} .", dir);
+    /// ```
+    pub fn data_dir_shell(&self) -> Result<String, AppDataError> {
+        shell_quote(&self.ensure_data_dir()?)
+    }
+
+    /// Whether `other` resolves to a different data directory than this instance
+    ///
+    /// Useful to diagnose data "disappearing" between runs because of a different app name or
+    /// local/system mode. Nothing is created on disk.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 判断 `other` 与当前实例解析出的数据目录是否不同。
+    /// 可用于排查因应用名或本地/系统模式不同导致数据在多次运行间"消失"的问题。不会在磁盘上创建任何内容
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let system = AppData::new("my_app");
+    /// let local = AppData::with_force_local("my_app", true);
+    /// if system.resolved_differs_from(&local).unwrap() {
+    ///     println!("local and system mode use different directories");
+    /// }
+    /// ```
+    pub fn resolved_differs_from(&self, other: &AppData) -> Result<bool, AppDataError> {
+        Ok(self.resolve_data_dir()? != other.resolve_data_dir()?)
+    }
+
+    /// Whether this instance and `other` could clobber each other's files on disk
+    ///
+    /// Unlike [`resolved_differs_from`](Self::resolved_differs_from) this also reports a
+    /// collision when one data directory lies inside the other, e.g. an unversioned instance and
+    /// a versioned one of the same app. Versions and groups are taken into account. Nothing is
+    /// created on disk.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 判断当前实例与 `other` 是否可能在磁盘上互相覆盖文件。
+    /// 与 [`resolved_differs_from`](Self::resolved_differs_from) 不同，当一个数据目录位于另一个之内时
+    /// （例如同一应用的无版本实例与带版本实例）也视为冲突。会考虑版本和分组。不会在磁盘上创建任何内容
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let cli = AppData::new("my_suite");
+    /// let gui = AppData::with_version("my_suite", "gui");
+    /// assert!(cli.would_collide_with(&gui).unwrap());
+    /// ```
+    pub fn would_collide_with(&self, other: &AppData) -> Result<bool, AppDataError> {
+        let own = self.resolve_data_dir()?;
+        let other = other.resolve_data_dir()?;
+        Ok(own.starts_with(&other) || other.starts_with(&own))
+    }
+
+    /// Refuse roots, system directories and the bare system base (an empty `app_name`), so a
+    /// misconfiguration can't scatter files in surprising places
+    pub(crate) fn check_safe_location(&self, path: &Path) -> Result<(), AppDataError> {
+        self.check_safe_location_with(path, &|key| self.env_var(key), &|key| var(key).ok())
+    }
+
+    /// [`check_safe_location`](Self::check_safe_location) with the variables of
+    /// [`resolve_app_dir_with`](Self::resolve_app_dir_with)
+    fn check_safe_location_with(
+        &self,
+        path: &Path,
+        overrides: EnvLookup,
+        env: EnvLookup,
+    ) -> Result<(), AppDataError> {
+        let is_shared_base = self
+            .shared_base_dir_with(overrides, env)
+            .is_ok_and(|base| base == path);
+        if is_shared_base || is_system_location(path) {
+            return Err(AppDataError::RefusedUnsafeCreate(path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    /// Resolve the application directory, without the version subfolder and without creating it
+    fn resolve_app_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.resolve_app_dir_with(&|key| self.env_var(key), &|key| var(key).ok())
+    }
+
+    /// [`resolve_app_dir`](Self::resolve_app_dir) reading override variables such as
+    /// `APPDATA_BASE_DIR` from `overrides` and platform variables from `env`
+    fn resolve_app_dir_with(
+        &self,
+        overrides: EnvLookup,
+        env: EnvLookup,
+    ) -> Result<PathBuf, AppDataError> {
+        if let Some(local) = self.local_app_dir(env)? {
+            return Ok(local);
+        }
+        check_app_dir_name(&self.app_name, &self.app_dir_name())?;
+        Ok(self
+            .shared_base_dir_with(overrides, env)?
+            .join(self.app_dir_name()))
+    }
+
+    /// The local directory under the current directory, when it takes precedence over the
+    /// shared base directory
+    pub(crate) fn local_app_dir(&self, env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+        let sandboxed = self.sandbox_in_ci && is_ci(env);
+        if sandboxed || self.base_dir.is_some() || (self.skip_local_probe && !self.force_local) {
+            return Ok(None);
+        }
+        let path = current_dir().map_err(|e| AppDataError::CurrentDirError(e.to_string()))?;
+        let root_path = path.join(self.local_dir_name());
+        let in_use = self.force_local || (root_path.exists() && !self.ignores_local(&root_path));
+        Ok(in_use.then_some(root_path))
+    }
+
+    /// The application folder of a companion directory such as the cache below `platform_base`,
+    /// or `None` when the data directory is placed explicitly (local mode, `base_dir`,
+    /// `APPDATA_BASE_DIR`, `for_user`, the CI sandbox) or the platform has no such base
+    pub(crate) fn platform_companion_dir(
+        &self,
+        platform_base: fn(EnvLookup) -> Result<Option<PathBuf>, AppDataError>,
+    ) -> Result<Option<PathBuf>, AppDataError> {
+        let env = |key: &str| var(key).ok();
+        let explicit = self.base_dir.is_some()
+            || self
+                .env_var(BASE_DIR_VAR)
+                .is_some_and(|dir| !dir.is_empty())
+            || self.user.is_some()
+            || (self.sandbox_in_ci && is_ci(&env))
+            || self
+                .container_volume_with(&|key| self.env_var(key))
+                .is_some();
+        if explicit || self.local_app_dir(&env)?.is_some() {
+            return Ok(None);
+        }
+        let Some(mut dir) = platform_base(&self.platform_env(&env))? else {
+            return Ok(None);
+        };
+        if let Some(group) = &self.group {
+            dir.push(sanitize_component(group));
+        }
+        dir.push(self.app_dir_name());
+        if let Some(version) = &self.version_subdir {
+            dir.push(version);
+        }
+        Ok(Some(dir))
+    }
+
+    /// Whether an existing local directory should be ignored because it is empty
+    fn ignores_local(&self, root_path: &Path) -> bool {
+        self.ignore_empty_local
+            && fs::read_dir(root_path).is_ok_and(|mut entries| entries.next().is_none())
+    }
+
+    /// `app_name` as used for directory names. On Windows trailing spaces and dots are
+    /// trimmed, as Windows would do on creation, so creating and looking up agree
+    fn app_dir_name(&self) -> String {
+        if self.slugify {
+            return slugify(&self.app_name);
+        }
+        #[cfg(target_os = "macos")]
+        if self.normalize_unicode {
+            return crate::unicode::compose(&self.app_name);
+        }
+        if cfg!(target_os = "windows") {
+            return windows_dir_name(&self.app_name).to_string();
+        }
+        self.app_name.clone()
+    }
+
+    /// Directory the application directory is created in: the base of
+    /// [`base_dir_with`](Self::base_dir_with) followed by the group folder
+    fn shared_base_dir_with(
+        &self,
+        overrides: EnvLookup,
+        env: EnvLookup,
+    ) -> Result<PathBuf, AppDataError> {
+        let mut base = self.base_dir_with(overrides, env)?;
+        if let Some(group) = &self.group {
+            base.push(sanitize_component(group));
+        }
+        Ok(base)
+    }
+
+    /// `base_dir`, `APPDATA_BASE_DIR`, the platform directory of `user`, the CI sandbox or the
+    /// platform directory. Variables are read as in
+    /// [`resolve_app_dir_with`](Self::resolve_app_dir_with)
+    fn base_dir_with(&self, overrides: EnvLookup, env: EnvLookup) -> Result<PathBuf, AppDataError> {
+        Ok(if let Some(dir) = &self.base_dir {
+            dir.clone()
+        } else if let Some(dir) = overrides(BASE_DIR_VAR).filter(|dir| !dir.is_empty()) {
+            PathBuf::from(dir)
+        } else if let Some(user) = &self.user {
+            crate::user::user_app_data_dir(user)?
+        } else if self.sandbox_in_ci && is_ci(env) {
+            ci_sandbox_dir()
+        } else if let Some(volume) = self.container_volume_with(overrides) {
+            volume
+        } else {
+            sys_app_data_dir_with(&self.platform_env(env))?
+        })
+    }
+
+    /// The writable container volume to use when
+    /// [`prefer_container_volume`](AppData::prefer_container_volume) is set
+    fn container_volume_with(&self, overrides: EnvLookup) -> Option<PathBuf> {
+        if !self.prefer_container_volume {
+            return None;
+        }
+        let volume = match overrides(CONTAINER_VOLUME_VAR).filter(|dir| !dir.is_empty()) {
+            Some(dir) => PathBuf::from(dir),
+            None if cfg!(unix) => PathBuf::from(CONTAINER_VOLUME),
+            None => return None,
+        };
+        is_writable_dir(&volume).then_some(volume)
+    }
+
+    /// `env` with `HOME` replaced by the home of `SUDO_USER` when
+    /// [`respect_sudo_user`](AppData::respect_sudo_user) is set, and by its canonical path when
+    /// [`resolve_home_symlink`](AppData::resolve_home_symlink) is set
+    fn platform_env<'a>(&'a self, env: EnvLookup<'a>) -> impl Fn(&str) -> Option<String> + 'a {
+        move |key| {
+            let sudo_home = (cfg!(unix) && self.respect_sudo_user && key == "HOME")
+                .then(|| env("SUDO_USER").filter(|user| !user.is_empty()))
+                .flatten()
+                .and_then(|user| crate::user::home_dir(&user).ok())
+                .and_then(|home| home.into_os_string().into_string().ok());
+            let value = sudo_home.or_else(|| env(key))?;
+            if self.resolve_home_symlink && key == "HOME" {
+                let resolved = fs::canonicalize(&value)
+                    .ok()
+                    .and_then(|home| home.into_os_string().into_string().ok());
+                if let Some(resolved) = resolved {
+                    return Some(resolved);
+                }
+            }
+            Some(value)
+        }
+    }
+
+    /// Look up an override variable in the process environment, then in `./.env` when enabled
+    fn env_var(&self, key: &str) -> Option<String> {
+        if let Ok(value) = var(key) {
+            return Some(value);
+        }
+        #[cfg(feature = "dotenv")]
+        if self.load_dotenv {
+            return crate::dotenv::lookup(key);
+        }
+        None
+    }
+
+    /// Get the home directory of the current user, as the crate reads it to derive platform
+    /// directories
+    ///
+    /// This is `HOME` on Unix and `USERPROFILE` on Windows, falling back to `HOMEDRIVE` followed
+    /// by `HOMEPATH`. A missing or empty variable fails with [`AppDataError::EnvVarNotFound`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取当前用户的主目录，与本库推导平台目录时读取的一致。
+    /// Unix 上为 `HOME`，Windows 上为 `USERPROFILE`，缺失时回退为 `HOMEDRIVE` 加 `HOMEPATH`。
+    /// 变量缺失或为空时返回 [`AppDataError::EnvVarNotFound`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let ssh_config = AppData::home_dir().unwrap().join(".ssh/config");
+    /// ```
+    pub fn home_dir() -> Result<PathBuf, AppDataError> {
+        home_dir_with(&|key| var(key).ok())
+    }
+
+    /// Get the base directory the application directories live in, such as `~/.local/share`,
+    /// without the application or group folder and without creating anything
+    ///
+    /// Use it for files shared between several apps. Explicit overrides (`base_dir`,
+    /// `APPDATA_BASE_DIR`, `for_user`) replace the platform directory here as well.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取各应用目录所在的基础目录（例如 `~/.local/share`），不包含应用或分组文件夹，也不创建任何目录。
+    /// 可用于多个应用共享的文件。显式覆盖（`base_dir`、`APPDATA_BASE_DIR`、`for_user`）同样会替换此处的平台目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let shared = AppData::new("my_app").system_base().unwrap().join("my_suite-shared.json");
+    /// ```
+    pub fn system_base(&self) -> Result<PathBuf, AppDataError> {
+        self.base_dir_with(&|key| self.env_var(key), &|key| var(key).ok())
+    }
+
+    /// Check whether the application directory could be created in the system base, without
+    /// creating it
+    ///
+    /// The nearest existing ancestor of the application directory under
+    /// [`system_base`](AppData::system_base), which may be that directory itself, must be a
+    /// writable directory. Installers can use this to choose between system and local mode up
+    /// front instead of failing deep in directory creation.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 检查能否在系统基础目录中创建应用目录，但不实际创建。
+    /// `system_base` 下应用目录最近的已存在祖先（可能就是应用目录本身）必须是可写目录。
+    /// 安装程序可以据此提前在系统模式和本地模式之间做出选择，而不是在创建目录的深处失败
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let system = AppData::new("my_app");
+    /// let app_data = if system.can_create_system_dir().unwrap() {
+    ///     system
+    /// } else {
+    ///     AppData::with_force_local("my_app", true)
+    /// };
+    /// ```
+    pub fn can_create_system_dir(&self) -> Result<bool, AppDataError> {
+        let app_dir = self
+            .shared_base_dir_with(&|key| self.env_var(key), &|key| var(key).ok())?
+            .join(self.app_dir_name());
+        Ok(app_dir
+            .ancestors()
+            .find(|dir| fs::symlink_metadata(dir).is_ok())
+            .is_some_and(is_writable_dir))
+    }
+
+    /// Resolve the data directory like [`ensure_data_dir`](AppData::ensure_data_dir) does, but
+    /// read every environment variable from `env` instead of the process environment, and don't
+    /// create anything
+    ///
+    /// This lets tests drive the platform branches deterministically and in parallel, and lets
+    /// servers resolve paths for request-scoped environments. The local `./data` directory
+    /// under the current directory is still considered.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 与 `ensure_data_dir` 相同地解析数据目录，但所有环境变量都从 `env` 读取而不是进程环境，并且不创建任何目录。
+    /// 这样测试可以确定且并行地覆盖各平台分支，服务器也可以为请求级别的环境解析路径。仍会考虑当前目录下的本地 `./data` 目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::collections::HashMap;
+    /// use app_data::AppData;
+    ///
+    /// let env = HashMap::from([("APPDATA_BASE_DIR".to_string(), "/srv/tenant-a".to_string())]);
+    /// let dir = AppData::new("my_app").resolve_with_env(&env).unwrap();
+    /// # if !std::path::Path::new("data").exists() {
+    /// assert_eq!(dir, std::path::Path::new("/srv/tenant-a/my_app"));
+    /// # }
+    /// ```
+    pub fn resolve_with_env(&self, env: &HashMap<String, String>) -> Result<PathBuf, AppDataError> {
+        let lookup = |key: &str| env.get(key).cloned();
+        let app_dir = self.resolve_app_dir_with(&lookup, &lookup)?;
+        let path = match &self.version_subdir {
+            Some(version) => app_dir.join(version),
+            None => app_dir,
+        };
+        self.check_safe_location_with(&path, &lookup, &lookup)?;
+        Ok(path)
+    }
+
+    /// Resolve the data directory without creating it
+    pub(crate) fn resolve_data_dir(&self) -> Result<PathBuf, AppDataError> {
+        let app_dir = self.resolve_app_dir()?;
+        Ok(match &self.version_subdir {
+            Some(version) => app_dir.join(version),
+            None => app_dir,
+        })
+    }
+
+    /// Check that the data directory is writable by creating and deleting a probe file in it
+    ///
+    /// Call this at startup to fail fast with a clear message instead of on the first save.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 通过在数据目录中创建并删除探测文件，检查目录是否可写。
+    /// 建议在启动时调用，以便尽早给出明确错误，而不是在第一次保存时才失败
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if let Err(err) = app_data.check_writable() {
+    ///     eprintln!("Cannot store data: {}", err);
+    /// }
+    /// ```
+    pub fn check_writable(&self) -> Result<(), AppDataError> {
+        self.deny_read_only("the data directory")?;
+        let dir = self.ensure_data_dir()?;
+        let probe = dir.join(format!(".appdata-probe-{}", process::id()));
+        let written = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&probe)
+            .and_then(|mut file| file.write_all(b"probe"));
+        let removed = fs::remove_file(&probe);
+        written.map_err(|e| write_error(e, &dir))?;
+        removed.map_err(|e| write_error(e, &dir))
+    }
+
+    /// Get when the data directory was created, as a "first installed" date for trial periods
+    /// or an about box
+    ///
+    /// Uses the creation time where the filesystem records one and the modification time
+    /// otherwise, whichever is earlier. Nothing is created: a missing data directory fails with
+    /// [`AppDataError::NotADirectory`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取数据目录的创建时间，可作为试用期或“关于”窗口中的“首次安装”日期。
+    /// 文件系统记录了创建时间时使用创建时间，否则使用修改时间，取两者中较早者。
+    /// 不会创建任何目录：数据目录不存在时返回 [`AppDataError::NotADirectory`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::{Duration, SystemTime};
+    /// use app_data::AppData;
+    ///
+    /// let installed = AppData::new("my_app").installed_at().unwrap();
+    /// let age = SystemTime::now().duration_since(installed).unwrap_or_default();
+    /// if age > Duration::from_secs(30 * 24 * 60 * 60) {
+    ///     println!("the trial has expired");
+    /// }
+    /// ```
+    pub fn installed_at(&self) -> Result<SystemTime, AppDataError> {
+        let data_dir = self.resolve_data_dir()?;
+        let metadata = match fs::metadata(&data_dir) {
+            Ok(metadata) if metadata.is_dir() => metadata,
+            _ => return Err(AppDataError::NotADirectory(data_dir)),
+        };
+        let modified = metadata.modified()?;
+        Ok(match metadata.created() {
+            Ok(created) => created.min(modified),
+            Err(_) => modified,
+        })
+    }
+
+    /// Get the permission bits the data directory actually has, e.g. `0o755`, or `None` when it
+    /// doesn't exist yet or the platform has no Unix modes
+    ///
+    /// Directories are created with the process umask applied, so an app expecting a private
+    /// directory can check for group or world access bits and warn.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取数据目录实际具有的权限位，例如 `0o755`；目录尚不存在或平台没有 Unix 权限模式时返回 `None`。
+    /// 目录创建时会应用进程的 umask，期望私有目录的应用可以据此检查组或其他用户的访问权限并给出警告
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data.ensure_data_dir().unwrap();
+    /// if app_data.effective_dir_mode().is_some_and(|mode| mode & 0o077 != 0) {
+    ///     eprintln!("warning: the data directory is accessible by other users");
+    /// }
+    /// ```
+    pub fn effective_dir_mode(&self) -> Option<u32> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let metadata = fs::metadata(self.resolve_data_dir().ok()?).ok()?;
+            metadata
+                .is_dir()
+                .then(|| metadata.permissions().mode() & 0o7777)
+        }
+        #[cfg(not(unix))]
+        None
+    }
+
+    /// Remove lock files left behind by crashed processes, returning how many were removed
+    ///
+    /// A lock file is a `*.lock` file in the data directory containing the pid of its owner.
+    /// It is stale when that process is no longer running. Files that don't contain a pid are
+    /// left alone.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 删除崩溃进程遗留的锁文件，返回删除的数量。
+    /// 锁文件是数据目录中内容为持有者 pid 的 `*.lock` 文件；持有进程已不在运行时即视为过期。
+    /// 内容不是 pid 的文件不会被处理
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let cleared = app_data.clear_stale_locks().unwrap();
+    /// println!("removed {} stale locks", cleared);
+    /// ```
+    pub fn clear_stale_locks(&self) -> Result<usize, AppDataError> {
+        self.deny_custom_file_system("clear_stale_locks")?;
+        self.deny_read_only("lock files")?;
+        let dir = self.ensure_data_dir()?;
+        let mut cleared = 0;
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "lock") {
+                continue;
+            }
+            let Ok(contents) = fs::read_to_string(&path) else {
+                continue;
+            };
+            let Ok(pid) = contents.trim().parse::<u32>() else {
+                continue;
+            };
+            if pid != process::id() && !pid::is_alive(pid) {
+                fs::remove_file(&path)?;
+                cleared += 1;
+            }
+        }
+        Ok(cleared)
+    }
+
+    /// Name of the data directory under the startup path
+    fn local_dir_name(&self) -> String {
+        if cfg!(unix) && self.local_hidden && !self.app_name.is_empty() {
+            format!(".{}", self.app_dir_name())
+        } else {
+            "data".to_string()
+        }
+    }
+
+    /// 获取数据目录中的文件路径
+    ///
+    /// Get the file path in the data directory
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let file_path = app_data.get_file_path("config.json").unwrap();
+    /// ```
+    pub fn get_file_path(&self, file_name: &str) -> Result<PathBuf, AppDataError> {
+        let data_dir = self.ensure_data_dir()?;
+        Ok(long_path(data_dir.join(file_name)))
+    }
+
+    /// Get the path of the data directory without creating it
+    ///
+    /// Read-only helpers such as [`read_file`](AppData::read_file) and
+    /// [`file_exists`](AppData::file_exists) resolve through this, so merely checking for a
+    /// saved file never creates the directory. Writes still go through
+    /// [`ensure_data_dir`](AppData::ensure_data_dir).
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取数据目录的路径，但不创建它。
+    /// `read_file`、`file_exists` 等只读方法通过它解析路径，因此仅检查存档文件是否存在时不会创建目录。写入仍通过 `ensure_data_dir`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let data_dir = app_data.data_dir_path().unwrap();
+    /// println!("data would live in {}", data_dir.display());
+    /// ```
+    pub fn data_dir_path(&self) -> Result<PathBuf, AppDataError> {
+        Ok(long_path(self.resolve_data_dir()?))
+    }
+
+    /// Path of a file in the data directory for reading, without creating the directory
+    pub(crate) fn read_path(&self, file_name: &str) -> Result<PathBuf, AppDataError> {
+        Ok(long_path(self.data_dir_path()?.join(file_name)))
+    }
+
+    /// Get the path of a possibly nested file in the data directory, creating its parent
+    /// directories but not the file itself
+    ///
+    /// `relative` must stay inside the data directory: absolute paths and `..` components are
+    /// rejected with [`AppDataError::InvalidPath`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取数据目录中可能带有子目录的文件路径，并创建其所有父目录，但不创建文件本身。
+    /// `relative` 必须位于数据目录内：绝对路径和 `..` 会返回 [`AppDataError::InvalidPath`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let path = app_data.ensure_parent("profiles/default/settings.toml").unwrap();
+    /// std::fs::write(path, "theme = \"dark\"").unwrap();
+    /// ```
+    pub fn ensure_parent(&self, relative: &str) -> Result<PathBuf, AppDataError> {
+        self.deny_read_only(relative)?;
+        let relative = checked_relative(relative)?;
+        let path = long_path(self.ensure_data_dir()?.join(relative));
+        if let Some(file_system) = self.file_system.custom() {
+            if let Some(parent) = path.parent() {
+                file_system.create_dir_all(parent)?;
+            }
+            return Ok(path);
+        }
+        if let Some(parent) = path.parent().filter(|parent| !parent.exists()) {
+            create_dir_race_safe(parent)?;
+            self.audit_event("create_dir", parent)?;
+        }
+        Ok(path)
+    }
+
+    /// Get the location for an embedded key-value database such as sled or redb:
+    /// `kv/<namespace>` in the data directory, creating `kv` but not the store itself
+    ///
+    /// `namespace` is sanitized into a single path component, so every namespace gets its own
+    /// sibling path and none can escape the `kv` directory.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取嵌入式键值数据库（如 sled 或 redb）的位置：数据目录中的 `kv/<namespace>`，会创建 `kv` 但不创建存储本身。
+    /// `namespace` 会被清理为单个路径组件，因此每个命名空间都有各自的同级路径，且都无法逃出 `kv` 目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let sessions = app_data.kv_path("sessions").unwrap();
+    /// ```
+    pub fn kv_path(&self, namespace: &str) -> Result<PathBuf, AppDataError> {
+        self.deny_custom_file_system("kv_path")?;
+        self.ensure_parent(&format!("kv/{}", sanitize_component(namespace)))
+    }
+
+    /// On first run, copy a directory of default files into the data directory, returning how
+    /// many files were copied
+    ///
+    /// `source` is copied recursively, but only while the data directory is still empty, so
+    /// defaults the user later edits or deletes are never brought back. Files that already
+    /// exist are skipped rather than overwritten.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 首次运行时将默认文件目录复制到数据目录，返回复制的文件数量。
+    /// `source` 会被递归复制，但仅在数据目录仍为空时进行，因此用户之后修改或删除的默认文件不会被恢复。
+    /// 已存在的文件会被跳过而不是覆盖
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let copied = app_data.seed_from_dir(Path::new("assets/defaults")).unwrap();
+    /// println!("installed {} default files", copied);
+    /// ```
+    pub fn seed_from_dir(&self, source: &Path) -> Result<usize, AppDataError> {
+        self.seed_from_dir_with_progress(source, |_| {})
+    }
+
+    /// [`seed_from_dir`](AppData::seed_from_dir), calling `progress` after every copied file and
+    /// once at the end, so a GUI can show a progress bar while large defaults are installed
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 与 `seed_from_dir` 相同，但每复制一个文件以及结束时都会调用 `progress`，便于界面在安装大量默认文件时显示进度条
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data
+    ///     .seed_from_dir_with_progress(Path::new("assets/defaults"), |progress| {
+    ///         println!("{:.0}%", progress.fraction() * 100.0);
+    ///     })
+    ///     .unwrap();
+    /// ```
+    pub fn seed_from_dir_with_progress(
+        &self,
+        source: &Path,
+        mut progress: impl FnMut(Progress),
+    ) -> Result<usize, AppDataError> {
+        self.deny_custom_file_system("seed_from_dir_with_progress")?;
+        self.deny_read_only("the data directory")?;
+        let data_dir = self.ensure_data_dir()?;
+        let mut pending = Vec::new();
+        if fs::read_dir(&data_dir)?.next().is_none() {
+            for file in walk_files(source)? {
+                let Ok(relative) = file.strip_prefix(source) else {
+                    continue;
+                };
+                let target = long_path(data_dir.join(relative));
+                if !target.exists() {
+                    let size = fs::metadata(&file)?.len();
+                    pending.push((file, target, size));
+                }
+            }
+        }
+
+        let mut state = Progress {
+            files_total: pending.len(),
+            bytes_total: pending.iter().map(|(_, _, size)| size).sum(),
+            ..Progress::default()
+        };
+        for (file, target, size) in pending {
+            if let Some(parent) = target.parent() {
+                create_dir_race_safe(parent)?;
+            }
+            fs::copy(&file, &target).map_err(|err| write_error(err, &data_dir))?;
+            self.audit_event("write", &target)?;
+            self.mirror_write(&target)?;
+            state.files_done += 1;
+            state.bytes_done += size;
+            if !state.is_complete() {
+                progress(state);
+            }
+        }
+        progress(state);
+        Ok(state.files_done)
+    }
+
+    /// Move the contents of a legacy data location, such as `~/.my_app` from before the app used
+    /// this crate, into the data directory. Returns whether anything was migrated
+    ///
+    /// Nothing is moved when the data directory already has contents, the legacy directory
+    /// doesn't exist, or it was migrated before. After moving, a `.migrated` marker recording
+    /// the new location is left in the legacy directory.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将旧数据位置（例如使用本库之前的 `~/.my_app`）中的内容移动到数据目录，返回是否进行了迁移。
+    /// 数据目录已有内容、旧目录不存在或之前已迁移过时不会移动任何内容。
+    /// 移动完成后，会在旧目录中留下记录新位置的 `.migrated` 标记文件
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if app_data.migrate_from(Path::new("/home/alice/.my_app")).unwrap() {
+    ///     println!("moved old data into {}", app_data.ensure_data_dir().unwrap().display());
+    /// }
+    /// ```
+    pub fn migrate_from(&self, legacy: &Path) -> Result<bool, AppDataError> {
+        self.deny_custom_file_system("migrate_from")?;
+        self.deny_read_only("the data directory")?;
+        if !legacy.is_dir() || legacy.join(MIGRATED_MARKER).exists() {
+            return Ok(false);
+        }
+        let data_dir = self.ensure_data_dir()?;
+        if data_dir.starts_with(legacy) || legacy.starts_with(&data_dir) {
+            return Err(AppDataError::InvalidPath(legacy.display().to_string()));
+        }
+        // The README and ownership marker written on creation don't count as data
+        let mut entries = fs::read_dir(&data_dir)?;
+        let is_data = |entry: fs::DirEntry| {
+            let name = entry.file_name();
+            name != README_FILE && name != OWNER_FILE
+        };
+        if entries.any(|entry| entry.is_ok_and(is_data)) {
+            return Ok(false);
+        }
+        for entry in fs::read_dir(legacy)? {
+            let entry = entry?;
+            let target = data_dir.join(entry.file_name());
+            move_path(&entry.path(), &target).map_err(|err| write_error(err, &data_dir))?;
+            self.audit_event("write", &target)?;
+            self.mirror_tree(&target)?;
+        }
+        fs::write(legacy.join(MIGRATED_MARKER), data_dir.display().to_string())?;
+        Ok(true)
+    }
+
+    /// Get the path of a read-only resource shipped with the application, such as an icon or a
+    /// default template, kept apart from the writable data directory
+    ///
+    /// Resources are looked up next to the executable, or in
+    /// [`with_resource_root`](AppData::with_resource_root) when set. A missing resource fails
+    /// with [`AppDataError::FileNotFound`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取随应用分发的只读资源（如图标或默认模板）的路径，与可写的数据目录分开。
+    /// 资源在可执行文件旁查找，设置了 `with_resource_root` 时在该目录中查找。资源不存在时返回 [`AppDataError::FileNotFound`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let icon = app_data.resource_path("assets/icon.png").unwrap();
+    /// ```
+    pub fn resource_path(&self, name: &str) -> Result<PathBuf, AppDataError> {
+        let relative = checked_relative(name)?;
+        let root = match &self.resource_root {
+            Some(root) => root.clone(),
+            None => {
+                let exe = env::current_exe()?;
+                exe.parent()
+                    .map(Path::to_path_buf)
+                    .ok_or(AppDataError::FileNotFound(exe))?
+            }
+        };
+        let path = root.join(relative);
+        if !path.exists() {
+            return Err(AppDataError::FileNotFound(path));
+        }
+        Ok(path)
+    }
+
+    /// Get the part of `absolute` relative to the data directory, the inverse of [`get_file_path`]
+    ///
+    /// Returns [`AppDataError::InvalidPath`] when the path isn't inside the data directory.
+    ///
+    /// [`get_file_path`]: AppData::get_file_path
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取 `absolute` 相对于数据目录的部分，是 [`get_file_path`] 的逆操作。
+    /// 路径不在数据目录内时返回 [`AppDataError::InvalidPath`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let path = app_data.get_file_path("config.json").unwrap();
+    /// assert_eq!(app_data.relativize(&path).unwrap(), std::path::Path::new("config.json"));
+    /// ```
+    pub fn relativize(&self, absolute: &Path) -> Result<PathBuf, AppDataError> {
+        let data_dir = self.resolve_data_dir()?;
+        absolute
+            .strip_prefix(&data_dir)
+            .ok()
+            .filter(|relative| {
+                relative
+                    .components()
+                    .all(|component| matches!(component, Component::Normal(_)))
+            })
+            .map(Path::to_path_buf)
+            .ok_or_else(|| AppDataError::InvalidPath(absolute.display().to_string()))
+    }
+
+    /// Whether `path` is located inside the data directory (or is the data directory), after
+    /// resolving symlinks on both sides, e.g. to check that a user-supplied path is safe to write
+    ///
+    /// A symlink inside the data directory pointing elsewhere counts as outside. `path` doesn't
+    /// need to exist; a relative `path` is taken relative to the current directory. Nothing is
+    /// created.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在解析两侧的符号链接后，判断 `path` 是否位于数据目录内（或就是数据目录），例如检查用户提供的路径是否可以安全写入。
+    /// 数据目录内指向其他位置的符号链接视为在目录外。`path` 不必存在；相对路径以当前目录为基准。不会创建任何目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if !app_data.contains(Path::new("/etc/passwd")).unwrap() {
+    ///     eprintln!("refusing to write outside the data directory");
+    /// }
+    /// ```
+    pub fn contains(&self, path: &Path) -> Result<bool, AppDataError> {
+        let Some(data_dir) = canonicalize_partial(&self.resolve_data_dir()?)? else {
+            return Ok(false);
+        };
+        Ok(canonicalize_partial(path)?.is_some_and(|path| path.starts_with(&data_dir)))
+    }
+
+    /// Read the whole contents of a file in the data directory
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 读取数据目录中文件的全部内容
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let bytes = app_data.read_file("cache.bin").unwrap();
+    /// ```
+    pub fn read_file(&self, file_name: &str) -> Result<Vec<u8>, AppDataError> {
+        let path = self.read_path(file_name)?;
+        match self.file_system.custom() {
+            Some(file_system) => Ok(file_system.read(&path)?),
+            None => Ok(fs::read(path)?),
+        }
+    }
+
+    /// Read a file in the data directory as UTF-8 text
+    ///
+    /// A leading UTF-8 byte order mark, as written by some Windows editors, is stripped.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 以 UTF-8 文本读取数据目录中的文件。部分 Windows 编辑器写入的 UTF-8 BOM 会被去除
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let text = app_data.read_to_string("config.toml").unwrap();
+    /// ```
+    pub fn read_to_string(&self, file_name: &str) -> Result<String, AppDataError> {
+        let mut text = match self.file_system.custom() {
+            Some(_) => String::from_utf8(self.read_file(file_name)?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?,
+            None => fs::read_to_string(self.read_path(file_name)?)?,
+        };
+        if text.starts_with(UTF8_BOM) {
+            text.drain(..UTF8_BOM.len_utf8());
+        }
+        Ok(text)
+    }
+
+    /// Stream a file in the data directory into `writer`, returning the number of bytes copied
+    ///
+    /// The counterpart of [`write_from_reader`](AppData::write_from_reader) for exporting into
+    /// an HTTP response, stdout or an encoder without loading the whole file. A missing file
+    /// fails with [`AppDataError::FileNotFound`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将数据目录中的文件以流式方式写入 `writer`，返回复制的字节数。
+    /// 与 `write_from_reader` 相对应，可在不整体加载文件的情况下导出到 HTTP 响应、标准输出或编码器。
+    /// 文件不存在时返回 [`AppDataError::FileNotFound`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data.read_into_writer("report.csv", &mut std::io::stdout()).unwrap();
+    /// ```
+    pub fn read_into_writer(
+        &self,
+        file_name: &str,
+        writer: &mut impl Write,
+    ) -> Result<u64, AppDataError> {
+        let path = self.read_path(file_name)?;
+        if let Some(file_system) = self.file_system.custom() {
+            let contents = match file_system.read(&path) {
+                Ok(contents) => contents,
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    return Err(AppDataError::FileNotFound(path));
+                }
+                Err(err) => return Err(err.into()),
+            };
+            writer.write_all(&contents)?;
+            return Ok(contents.len() as u64);
+        }
+        let mut file = match fs::File::open(&path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                return Err(AppDataError::FileNotFound(path));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        Ok(io::copy(&mut file, writer)?)
+    }
+
+    /// Copy a file from the data directory to `dest` outside of it, e.g. for a "Save As" or
+    /// "Export" action
+    ///
+    /// Missing parent directories of `dest` are created, and an existing `dest` is replaced
+    /// atomically, so it never holds a partial copy. A missing source fails with
+    /// [`AppDataError::FileNotFound`], and a `file_name` that is absolute or contains `..` with
+    /// [`AppDataError::InvalidPath`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将数据目录中的文件复制到目录之外的 `dest`，例如用于“另存为”或“导出”操作。
+    /// 会创建 `dest` 缺失的父目录，已存在的 `dest` 会被原子地替换，因此不会出现复制了一半的文件。
+    /// 源文件不存在时返回 [`AppDataError::FileNotFound`]，`file_name` 为绝对路径或包含 `..` 时返回 [`AppDataError::InvalidPath`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data
+    ///     .export_file("report.csv", Path::new("/home/me/Documents/report.csv"))
+    ///     .unwrap();
+    /// ```
+    pub fn export_file(&self, file_name: &str, dest: &Path) -> Result<(), AppDataError> {
+        let source = long_path(self.data_dir_path()?.join(checked_relative(file_name)?));
+        if !self.stored_is_file(&source) {
+            return Err(AppDataError::FileNotFound(source));
+        }
+        if let Some(parent) = dest
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            fs::create_dir_all(parent).map_err(|err| write_error(err, parent))?;
+        }
+        let staged = sibling_path(dest, "tmp");
+        let copied = match self.file_system.custom() {
+            Some(file_system) => file_system
+                .read(&source)
+                .and_then(|contents| fs::write(&staged, contents)),
+            None => fs::copy(&source, &staged).map(|_| ()),
+        }
+        .and_then(|_| fs::rename(&staged, dest));
+        if let Err(err) = copied {
+            let _ = fs::remove_file(&staged);
+            return Err(write_error(err, dest));
+        }
+        Ok(())
+    }
+
+    /// Stream a file in the data directory line by line instead of loading it into memory
+    ///
+    /// The file stays open for as long as the iterator lives.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按行流式读取数据目录中的文件，而不是一次性读入内存。迭代器存活期间文件保持打开
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// for line in app_data.read_lines("events.log").unwrap() {
+    ///     println!("{}", line.unwrap());
+    /// }
+    /// ```
+    pub fn read_lines(
+        &self,
+        file_name: &str,
+    ) -> Result<impl Iterator<Item = Result<String, AppDataError>>, AppDataError> {
+        let path = self.read_path(file_name)?;
+        let reader: Box<dyn BufRead> = match self.file_system.custom() {
+            Some(file_system) => Box::new(io::Cursor::new(file_system.read(&path)?)),
+            None => Box::new(BufReader::new(fs::File::open(path)?)),
+        };
+        Ok(reader.lines().map(|line| Ok(line?)))
+    }
+
+    /// Write a file in the data directory, replacing any existing contents
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 写入数据目录中的文件，覆盖已有内容
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data.write_file("config.toml", "theme = \"dark\"").unwrap();
+    /// ```
+    pub fn write_file(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<(), AppDataError> {
+        self.deny_read_only(file_name)?;
+        let path = self.get_file_path(file_name)?;
+        if let Some(file_system) = self.file_system.custom() {
+            return Ok(file_system.write(&path, contents.as_ref())?);
+        }
+        write_contents(&path, contents.as_ref(), self.durable)?;
+        self.audit_event("write", &path)?;
+        self.mirror_write(&path)
+    }
+
+    /// Create a new file in the data directory, failing with [`AppDataError::AlreadyExists`]
+    /// instead of overwriting when it is already present
+    ///
+    /// The check and the creation are a single filesystem operation, so two writers racing for
+    /// the same name never both succeed.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在数据目录中创建新文件；文件已存在时返回 [`AppDataError::AlreadyExists`]，而不是覆盖它。
+    /// 检查与创建是同一个文件系统操作，因此两个写入方争用同一名称时不会同时成功
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::{AppData, AppDataError};
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// match app_data.create_new_file("doc-4f2a.md", "# Untitled") {
+    ///     Ok(()) => println!("created"),
+    ///     Err(AppDataError::AlreadyExists(path)) => println!("{} is taken", path.display()),
+    ///     Err(err) => panic!("{}", err),
+    /// }
+    /// ```
+    pub fn create_new_file(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<(), AppDataError> {
+        self.deny_read_only(file_name)?;
+        let path = self.get_file_path(file_name)?;
+        if let Some(file_system) = self.file_system.custom() {
+            if file_system.exists(&path) {
+                return Err(AppDataError::AlreadyExists(path));
+            }
+            return Ok(file_system.write(&path, contents.as_ref())?);
+        }
+        let mut file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(AppDataError::AlreadyExists(path));
+            }
+            Err(err) => return Err(err.into()),
+        };
+        file.write_all(contents.as_ref())?;
+        if self.durable {
+            file.sync_all()?;
+        }
+        self.audit_event("write", &path)?;
+        self.mirror_write(&path)
+    }
+
+    /// Write a file in the data directory with the Unix permission bits `mode`, such as `0o600`
+    /// for secrets or `0o644` for a shared config
+    ///
+    /// The permissions are applied exactly, regardless of the umask, and before the contents are
+    /// written, so a secret is never readable with looser permissions. With `None` this is the
+    /// same as [`write_file`](AppData::write_file). On Windows `mode` is ignored.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 以 Unix 权限位 `mode` 写入数据目录中的文件，例如敏感信息使用 `0o600`，共享配置使用 `0o644`。
+    /// 权限会被精确设置而不受 umask 影响，并且在写入内容之前生效，因此敏感内容不会以较宽松的权限暴露。
+    /// 传入 `None` 时与 `write_file` 相同。在 Windows 上 `mode` 会被忽略
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data.write_file_with_mode("token", "secret", Some(0o600)).unwrap();
+    /// ```
+    pub fn write_file_with_mode(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+        mode: Option<u32>,
+    ) -> Result<(), AppDataError> {
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+            self.deny_custom_file_system("write_file_with_mode")?;
+            self.deny_read_only(file_name)?;
+            let path = self.get_file_path(file_name)?;
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .mode(mode)
+                .open(&path)?;
+            file.set_permissions(fs::Permissions::from_mode(mode))?;
+            file.write_all(contents.as_ref())?;
+            if self.durable {
+                file.sync_all()?;
+            }
+            self.audit_event("write", &path)?;
+            return self.mirror_write(&path);
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+        self.write_file(file_name, contents)
+    }
+
+    /// Write a file in the data directory atomically
+    ///
+    /// The contents are written to a temporary file which is then renamed over the target, so
+    /// readers see either the old or the new contents, never a partial write.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 原子地写入数据目录中的文件。内容先写入临时文件再重命名覆盖目标，
+    /// 读取方只会看到旧内容或新内容，不会看到写了一半的文件
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data.write_file_atomic("save.dat", [1, 2, 3]).unwrap();
+    /// ```
+    pub fn write_file_atomic(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<(), AppDataError> {
+        self.deny_read_only(file_name)?;
+        let path = self.get_file_path(file_name)?;
+        if let Some(file_system) = self.file_system.custom() {
+            let staged = sibling_path(&path, "tmp");
+            file_system.write(&staged, contents.as_ref())?;
+            if let Err(err) = file_system.rename(&staged, &path) {
+                let _ = file_system.remove(&staged);
+                return Err(err.into());
+            }
+            return Ok(());
+        }
+        let staged = stage_file(&path, contents.as_ref(), self.durable)?;
+        if let Err(err) = fs::rename(&staged, &path) {
+            let _ = fs::remove_file(&staged);
+            return Err(err.into());
+        }
+        if self.durable {
+            sync_parent(&path)?;
+        }
+        self.audit_event("write", &path)?;
+        self.mirror_write(&path)
+    }
+
+    /// Stream `reader` into a file in the data directory atomically, returning the number of
+    /// bytes written
+    ///
+    /// Large downloads or decompressed streams are written to a temporary file as they arrive
+    /// and renamed over the target once complete, so they are never held in memory and readers
+    /// never see a partial file. A failing reader leaves the previous contents in place.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将 `reader` 以流式方式原子地写入数据目录中的文件，返回写入的字节数。
+    /// 大文件下载或解压流在到达时写入临时文件，完成后重命名覆盖目标，因此不会整体保存在内存中，读取方也不会看到不完整的文件。
+    /// 读取失败时保留原有内容
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::net::TcpStream;
+    /// use app_data::AppData;
+    ///
+    /// let mut download = TcpStream::connect("example.com:8080").unwrap();
+    /// let app_data = AppData::new("my_app");
+    /// let size = app_data.write_from_reader("model.bin", &mut download).unwrap();
+    /// println!("downloaded {} bytes", size);
+    /// ```
+    pub fn write_from_reader(
+        &self,
+        file_name: &str,
+        reader: &mut impl Read,
+    ) -> Result<u64, AppDataError> {
+        self.deny_read_only(file_name)?;
+        if self.file_system.custom().is_some() {
+            let mut contents = Vec::new();
+            reader.read_to_end(&mut contents)?;
+            self.write_file_atomic(file_name, &contents)?;
+            return Ok(contents.len() as u64);
+        }
+        let path = self.get_file_path(file_name)?;
+        let staged = sibling_path(&path, "tmp");
+        let streamed = (|| {
+            let mut file = fs::File::create(&staged)?;
+            let written = io::copy(reader, &mut file)?;
+            if self.durable {
+                file.sync_all()?;
+            }
+            drop(file);
+            fs::rename(&staged, &path)?;
+            Ok::<_, io::Error>(written)
+        })();
+        let written = match streamed {
+            Ok(written) => written,
+            Err(err) => {
+                let _ = fs::remove_file(&staged);
+                return Err(AppDataError::IoError(format!(
+                    "Streaming into {} failed: {}",
+                    path.display(),
+                    err
+                )));
+            }
+        };
+        if self.durable {
+            sync_parent(&path)?;
+        }
+        self.audit_event("write", &path)?;
+        self.mirror_write(&path)?;
+        Ok(written)
+    }
+
+    /// Copy the external file `src` into the data directory as `file_name`, e.g. for an
+    /// "Import" action, replacing an existing file
+    ///
+    /// The copy goes through [`write_from_reader`](AppData::write_from_reader), so the target
+    /// never holds a partial import. Missing parent directories inside the data directory are
+    /// created. A missing `src` fails with [`AppDataError::FileNotFound`], and a `file_name`
+    /// that is absolute or contains `..` with [`AppDataError::InvalidPath`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将外部文件 `src` 以 `file_name` 复制到数据目录中，例如用于“导入”操作，已存在的文件会被替换。
+    /// 复制通过 `write_from_reader` 完成，因此目标文件不会只包含一部分导入内容。会创建数据目录内缺失的父目录。
+    /// `src` 不存在时返回 [`AppDataError::FileNotFound`]，`file_name` 为绝对路径或包含 `..` 时返回 [`AppDataError::InvalidPath`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data
+    ///     .import_file(Path::new("/home/me/Downloads/theme.toml"), "themes/imported.toml")
+    ///     .unwrap();
+    /// ```
+    pub fn import_file(&self, src: &Path, file_name: &str) -> Result<(), AppDataError> {
+        self.deny_read_only(file_name)?;
+        let mut source = open_import(src)?;
+        self.ensure_parent(file_name)?;
+        self.write_from_reader(file_name, &mut source)?;
+        Ok(())
+    }
+
+    /// Like [`import_file`](AppData::import_file), but fail with
+    /// [`AppDataError::AlreadyExists`] instead of replacing an existing file
+    ///
+    /// The import is staged next to the target and only linked into place when the name is
+    /// still free, so a concurrent writer is never overwritten.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 与 `import_file` 相同，但目标文件已存在时返回 [`AppDataError::AlreadyExists`]，而不是替换它。
+    /// 导入内容先暂存在目标旁边，只有名称仍然空闲时才链接到位，因此不会覆盖并发写入的文件
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::{AppData, AppDataError};
+    ///
+    /// let app_data = AppData::new("my_game");
+    /// match app_data.import_new_file(Path::new("/tmp/shared.sav"), "slot-3.sav") {
+    ///     Err(AppDataError::AlreadyExists(_)) => println!("slot 3 is taken"),
+    ///     other => other.unwrap(),
+    /// }
+    /// ```
+    pub fn import_new_file(&self, src: &Path, file_name: &str) -> Result<(), AppDataError> {
+        self.deny_read_only(file_name)?;
+        let mut source = open_import(src)?;
+        let path = self.ensure_parent(file_name)?;
+        if let Some(file_system) = self.file_system.custom() {
+            let mut contents = Vec::new();
+            source.read_to_end(&mut contents)?;
+            if file_system.exists(&path) {
+                return Err(AppDataError::AlreadyExists(path));
+            }
+            return Ok(file_system.write(&path, &contents)?);
+        }
+        if path.exists() {
+            return Err(AppDataError::AlreadyExists(path));
+        }
+        let staged = sibling_path(&path, "import");
+        let linked = io::copy(&mut source, &mut fs::File::create(&staged)?).and_then(|_| {
+            match fs::hard_link(&staged, &path) {
+                Err(err) if err.kind() != io::ErrorKind::AlreadyExists => {
+                    // No hard links on this filesystem, claim the name and copy instead
+                    let mut target = fs::OpenOptions::new()
+                        .write(true)
+                        .create_new(true)
+                        .open(&path)?;
+                    io::copy(&mut fs::File::open(&staged)?, &mut target).map(|_| ())
+                }
+                linked => linked,
+            }
+        });
+        let _ = fs::remove_file(&staged);
+        match linked {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                return Err(AppDataError::AlreadyExists(path));
+            }
+            Err(err) => return Err(err.into()),
+        }
+        if self.durable {
+            fs::File::open(&path)?.sync_all()?;
+            sync_parent(&path)?;
+        }
+        self.audit_event("write", &path)?;
+        self.mirror_write(&path)
+    }
+
+    /// Rewrite a JSON-lines file keeping only its last `keep_last` records, returning how many
+    /// records were dropped
+    ///
+    /// Blank lines are discarded. The file is streamed, so only `keep_last` records are held in
+    /// memory, and replaced atomically, so readers never see a half-written file.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 重写 JSON-lines 文件，只保留最后 `keep_last` 条记录，返回丢弃的记录数。
+    /// 空行会被丢弃。文件以流式读取，内存中最多只保留 `keep_last` 条记录，并以原子方式替换，读取方不会看到写了一半的文件
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let dropped = app_data.compact_jsonl("events.jsonl", 1000).unwrap();
+    /// println!("dropped {} old events", dropped);
+    /// ```
+    pub fn compact_jsonl(&self, file_name: &str, keep_last: usize) -> Result<usize, AppDataError> {
+        self.deny_read_only(file_name)?;
+        let mut kept = VecDeque::with_capacity(keep_last);
+        let mut dropped = 0;
+        for line in self.read_lines(file_name)? {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            if kept.len() == keep_last {
+                dropped += 1;
+                // Nothing to make room in when keeping zero records
+                if kept.pop_front().is_none() {
+                    continue;
+                }
+            }
+            kept.push_back(line);
+        }
+        let mut contents = String::new();
+        for line in kept {
+            contents.push_str(&line);
+            contents.push('\n');
+        }
+        self.write_file_atomic(file_name, contents)?;
+        Ok(dropped)
+    }
+
+    /// Atomically write a file only when `contents` differ from what is already stored,
+    /// returning whether a write happened
+    ///
+    /// Unchanged saves leave the file untouched, so its modification time stays put and file
+    /// watchers or sync clients see no spurious change.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 仅当 `contents` 与已存储内容不同时才原子地写入文件，返回是否进行了写入。
+    /// 内容未变化时文件保持不变，修改时间不会更新，文件监视器或同步客户端也不会收到多余的变更
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if app_data.write_file_if_changed("config.toml", "theme = \"dark\"").unwrap() {
+    ///     println!("config saved");
+    /// }
+    /// ```
+    pub fn write_file_if_changed(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<bool, AppDataError> {
+        self.deny_read_only(file_name)?;
+        let contents = contents.as_ref();
+        let path = self.get_file_path(file_name)?;
+        let unchanged = match (self.file_system.custom(), fs::metadata(&path)) {
+            (Some(file_system), _) => file_system
+                .read(&path)
+                .is_ok_and(|stored| stored == contents),
+            // Compare sizes first to skip reading files that obviously differ
+            (None, Ok(metadata))
+                if metadata.is_file() && metadata.len() == contents.len() as u64 =>
+            {
+                fs::read(&path)? == contents
+            }
+            _ => false,
+        };
+        if unchanged {
+            return Ok(false);
+        }
+        self.write_file_atomic(file_name, contents)?;
+        Ok(true)
+    }
+
+    /// Write several files so that either all of them or none of them are replaced
+    ///
+    /// Every file is first staged to a temporary file, then the staged files are renamed into
+    /// place one by one. If a step fails, files already replaced are restored from backups and
+    /// new files are removed. This is all-or-nothing only while the process keeps running: a
+    /// crash in the middle of the rename phase can leave a mix of old and new files behind.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 批量写入多个文件，要么全部替换，要么全部不变。
+    /// 所有文件先写入临时文件，再逐个重命名到目标位置；任一步失败时，已替换的文件会从备份恢复，新建的文件会被删除。
+    /// 这只在进程持续运行时保证全有或全无：若在重命名阶段崩溃，可能留下新旧文件混杂的状态
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data
+    ///     .write_batch(&[("save.dat", b"state"), ("save.idx", b"index")])
+    ///     .unwrap();
+    /// ```
+    pub fn write_batch(&self, files: &[(&str, &[u8])]) -> Result<(), AppDataError> {
+        self.deny_custom_file_system("write_batch")?;
+        for (file_name, _) in files {
+            self.deny_read_only(file_name)?;
+        }
+        let mut staged = Vec::with_capacity(files.len());
+        for (file_name, contents) in files {
+            let result = self
+                .get_file_path(file_name)
+                .and_then(|path| Ok((stage_file(&path, contents, self.durable)?, path)));
+            match result {
+                Ok(entry) => staged.push(entry),
+                Err(err) => {
+                    for (staged_path, _) in &staged {
+                        let _ = fs::remove_file(staged_path);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        // (target, backup of the previous contents) for every file already renamed into place
+        let mut committed: Vec<(PathBuf, Option<PathBuf>)> = Vec::with_capacity(staged.len());
+        for (index, (staged_path, path)) in staged.iter().enumerate() {
+            match Self::commit_staged(staged_path, path) {
+                Ok(backup) => committed.push((path.clone(), backup)),
+                Err(err) => {
+                    for (path, backup) in committed.iter().rev() {
+                        match backup {
+                            Some(backup) => {
+                                let _ = fs::rename(backup, path);
+                            }
+                            None => {
+                                let _ = fs::remove_file(path);
+                            }
+                        }
+                    }
+                    for (staged_path, _) in &staged[index..] {
+                        let _ = fs::remove_file(staged_path);
+                    }
+                    return Err(err.into());
+                }
+            }
+        }
+        if self.durable {
+            for (path, _) in &committed {
+                sync_parent(path)?;
+            }
+        }
+        for (path, backup) in &committed {
+            if let Some(backup) = backup {
+                let _ = fs::remove_file(backup);
+            }
+            self.audit_event("write", path)?;
+        }
+        // Mirror every file even when one copy fails, then report the first failure
+        committed
+            .iter()
+            .map(|(path, _)| self.mirror_write(path))
+            .fold(Ok(()), Result::and)
+    }
+
+    /// Rename a staged file over `path`, keeping the previous file as a backup
+    fn commit_staged(staged: &Path, path: &Path) -> io::Result<Option<PathBuf>> {
+        let backup = if path.is_file() {
+            let backup = sibling_path(path, "bak");
+            fs::rename(path, &backup)?;
+            Some(backup)
+        } else {
+            None
+        };
+        if let Err(err) = fs::rename(staged, path) {
+            if let Some(backup) = &backup {
+                let _ = fs::rename(backup, path);
+            }
+            return Err(err);
+        }
+        Ok(backup)
+    }
+
+    /// Find a file in the data directory by case-insensitive name, returning its path as spelled
+    /// on disk, or `None` when there is no match
+    ///
+    /// Each component of a nested `name` is matched separately. An exact match wins; among
+    /// several case-insensitive matches, which only case-sensitive filesystems can hold, the
+    /// first in sorted order is returned.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按不区分大小写的名称在数据目录中查找文件，返回磁盘上实际拼写的路径，找不到时返回 `None`。
+    /// 嵌套的 `name` 会逐级匹配。完全匹配优先；存在多个不区分大小写的匹配时（只可能出现在区分大小写的文件系统上），返回排序后的第一个
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if let Some(path) = app_data.find_file_ci("config.json").unwrap() {
+    ///     println!("found {}", path.display());
+    /// }
+    /// ```
+    pub fn find_file_ci(&self, name: &str) -> Result<Option<PathBuf>, AppDataError> {
+        let relative = checked_relative(name)?;
+        let mut current = self.data_dir_path()?;
+        if self.file_system.custom().is_some() {
+            let lowercase = |path: &Path| -> Vec<String> {
+                path.components()
+                    .map(|component| component.as_os_str().to_string_lossy().to_lowercase())
+                    .collect()
+            };
+            let wanted = lowercase(relative);
+            let mut matches: Vec<_> = self
+                .stored_files(&current)?
+                .into_iter()
+                .filter(|path| {
+                    path.strip_prefix(&current)
+                        .is_ok_and(|stored| lowercase(stored) == wanted)
+                })
+                .collect();
+            matches.sort_by_key(|path| (!path.ends_with(relative), path.clone()));
+            return Ok(matches.into_iter().next());
+        }
+        let components: Vec<_> = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(part) => Some(part),
+                _ => None,
+            })
+            .collect();
+        for (index, part) in components.iter().enumerate() {
+            let is_last = index + 1 == components.len();
+            if !current.is_dir() {
+                return Ok(None);
+            }
+            // Scan even when `part` exists, as case-insensitive filesystems would accept any
+            // spelling and hide the one on disk
+            let wanted = part.to_string_lossy().to_lowercase();
+            let mut matches = Vec::new();
+            for entry in fs::read_dir(&current)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                let fits = if is_last {
+                    file_type.is_file()
+                } else {
+                    file_type.is_dir()
+                };
+                if fits && entry.file_name().to_string_lossy().to_lowercase() == wanted {
+                    matches.push(entry.path());
+                }
+            }
+            matches.sort_by_key(|path| (path.file_name() != Some(part), path.clone()));
+            match matches.into_iter().next() {
+                Some(found) => current = found,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(current))
+    }
+
+    /// Whether a file exists in the data directory
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 数据目录中是否存在该文件
+    /// </details>
+    pub fn file_exists(&self, file_name: &str) -> Result<bool, AppDataError> {
+        let path = self.read_path(file_name)?;
+        match self.file_system.custom() {
+            Some(file_system) => Ok(file_system.exists(&path)),
+            None => Ok(path.is_file()),
+        }
+    }
+
+    /// Delete a file in the data directory
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 删除数据目录中的文件
+    /// </details>
+    pub fn delete_file(&self, file_name: &str) -> Result<(), AppDataError> {
+        self.deny_read_only(file_name)?;
+        let path = self.get_file_path(file_name)?;
+        match self.file_system.custom() {
+            Some(file_system) => Ok(file_system.remove(&path)?),
+            None => Ok(fs::remove_file(path)?),
+        }
+    }
+
+    /// Delete several files in the data directory, returning the outcome for each name in order
+    ///
+    /// One failing file doesn't stop the others, so a bulk "delete selected saves" can report
+    /// partial success. Missing files fail with [`AppDataError::FileNotFound`], and names that
+    /// are absolute or contain `..` with [`AppDataError::InvalidPath`]. The outer error is only
+    /// returned when nothing can be deleted at all, e.g. in read-only mode.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 删除数据目录中的多个文件，按顺序返回每个名称的结果。
+    /// 单个文件失败不会中断其他文件，因此批量“删除所选存档”可以报告部分成功。
+    /// 文件不存在时返回 [`AppDataError::FileNotFound`]，绝对路径或包含 `..` 的名称返回 [`AppDataError::InvalidPath`]。
+    /// 只有完全无法删除时（例如只读模式）才返回外层错误
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_game");
+    /// for (name, result) in app_data.delete_files(&["slot-1.sav", "slot-2.sav"]).unwrap() {
+    ///     if let Err(err) = result {
+    ///         eprintln!("could not delete {}: {}", name, err);
+    ///     }
+    /// }
+    /// ```
+    pub fn delete_files(&self, names: &[&str]) -> Result<Vec<DeleteOutcome>, AppDataError> {
+        self.deny_read_only(&names.join(", "))?;
+        let data_dir = self.ensure_data_dir()?;
+        let delete = |name: &str| -> Result<(), AppDataError> {
+            let path = long_path(data_dir.join(checked_relative(name)?));
+            match self.remove_stored(&path) {
+                Ok(()) => Ok(()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                    Err(AppDataError::FileNotFound(path))
+                }
+                Err(err) => Err(err.into()),
+            }
+        };
+        Ok(names
+            .iter()
+            .map(|name| (name.to_string(), delete(name)))
+            .collect())
+    }
+
+    /// Bind a single file in the data directory to a handle that can be loaded and saved repeatedly
+    ///
+    /// The value is stored as text: it is parsed with [`FromStr`](std::str::FromStr) on load and
+    /// written with [`Display`](std::fmt::Display) on save.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将数据目录中的单个文件绑定为句柄，之后可反复读取和保存，无需再次传入文件名。
+    /// 值以文本形式存储：读取时通过 `FromStr` 解析，保存时通过 `Display` 写入
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let launches = app_data.file::<u32>("launches.txt");
+    /// let count = if launches.exists().unwrap() { launches.load().unwrap() } else { 0 };
+    /// launches.save(&(count + 1)).unwrap();
+    /// ```
+    pub fn file<T>(&self, name: &str) -> ManagedFile<T> {
+        ManagedFile::new(self.clone(), name)
+    }
+}
+
+impl Default for AppData {
+    /// Default using `CARGO_PKG_NAME` as the application name, if `CARGO_PKG_NAME` is not set,
+    /// then `force_local` is true
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 默认使用 `CARGO_PKG_NAME` 作为应用名称，如果 `CARGO_PKG_NAME` 未设置，则`force_local` 为true
+    /// </details>
+    fn default() -> Self {
+        let app_name = env::var("CARGO_PKG_NAME");
+        if app_name.is_err() {
             return Self::with_force_local("", true);
         }
-        Self::new(&app_name.unwrap())
+        Self::new(&app_name.unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{SysBase, lock_env};
+    use std::fs;
+
+    #[test]
+    fn test_app_data_new() {
+        let app_data = AppData::new("test_app");
+        assert_eq!(app_data.app_name, "test_app");
+        assert!(!app_data.force_local);
+    }
+
+    #[test]
+    fn test_app_data_with_force_local() {
+        let app_data = AppData::with_force_local("test_app", true);
+        assert_eq!(app_data.app_name, "test_app");
+        assert!(app_data.force_local);
+    }
+
+    #[test]
+    fn test_app_data_with_version() {
+        let app_data = AppData::with_version("test_app", "v2");
+        assert_eq!(app_data.app_name, "test_app");
+        assert_eq!(app_data.version_subdir.as_deref(), Some("v2"));
+        assert!(!app_data.force_local);
+    }
+
+    #[test]
+    fn test_ensure_data_dir_versioned() {
+        let base = SysBase::new("ensure_data_dir_versioned");
+        let data_dir = AppData::with_version("test_app", "v2")
+            .ensure_data_dir()
+            .unwrap();
+        assert!(data_dir.is_dir());
+        assert_eq!(
+            data_dir,
+            get_sys_app_data_dir().unwrap().join("test_app").join("v2")
+        );
+        assert!(data_dir.starts_with(base.path()));
+    }
+
+    #[test]
+    fn test_list_versions() {
+        let _base = SysBase::new("list_versions");
+        let v1 = AppData::with_version("test_app", "v1");
+        assert!(v1.list_versions().unwrap().is_empty());
+
+        v1.ensure_data_dir().unwrap();
+        AppData::with_version("test_app", "v2")
+            .ensure_data_dir()
+            .unwrap();
+        AppData::new("test_app")
+            .write_file("shared.txt", "not a version")
+            .unwrap();
+        assert_eq!(v1.list_versions().unwrap(), vec!["v1", "v2"]);
+    }
+
+    #[test]
+    fn test_sanitize_component() {
+        assert_eq!(sanitize_component("my_suite"), "my_suite");
+        assert_eq!(sanitize_component("../etc"), ".._etc");
+        assert_eq!(sanitize_component("a/b\\c:d"), "a_b_c_d");
+        assert_eq!(sanitize_component(".."), "_");
+        assert_eq!(sanitize_component("  "), "_");
+    }
+
+    #[test]
+    fn test_ensure_data_dir_grouped() {
+        let _base = SysBase::new("ensure_data_dir_grouped");
+        let sys_dir = get_sys_app_data_dir().unwrap();
+        let editor = AppData::with_group("editor", "my_suite");
+        let viewer = AppData::with_group("viewer", "my_suite");
+        let editor_dir = editor.ensure_data_dir().unwrap();
+        let viewer_dir = viewer.ensure_data_dir().unwrap();
+        assert_eq!(editor_dir, sys_dir.join("my_suite").join("editor"));
+        assert_eq!(viewer_dir, sys_dir.join("my_suite").join("viewer"));
+
+        fs::remove_dir_all(sys_dir.join("my_suite")).unwrap();
+        assert!(!editor_dir.exists());
+        assert!(!viewer_dir.exists());
+    }
+
+    #[test]
+    fn test_resolved_differs_from() {
+        let base = SysBase::new("resolved_differs_from");
+        let app_data = AppData::new("test_app");
+        assert!(
+            !app_data
+                .resolved_differs_from(&AppData::new("test_app"))
+                .unwrap()
+        );
+        assert!(
+            app_data
+                .resolved_differs_from(&AppData::new("other_app"))
+                .unwrap()
+        );
+        assert!(
+            app_data
+                .resolved_differs_from(&AppData::with_force_local("test_app", true))
+                .unwrap()
+        );
+        assert!(
+            app_data
+                .resolved_differs_from(&AppData::with_version("test_app", "v2"))
+                .unwrap()
+        );
+        // Nothing is created
+        assert_eq!(fs::read_dir(base.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_sandbox_in_ci() {
+        let mut base = SysBase::new("sandbox_in_ci");
+        base.set_var("CI", "1");
+        let sandbox = ci_sandbox_dir();
+        let data_dir = AppData::new("test_app")
+            .sandbox_in_ci(true)
+            .ensure_data_dir()
+            .unwrap();
+        assert_eq!(data_dir, sandbox.join("test_app"));
+        assert!(data_dir.is_dir());
+        // Without the opt-in the real base is used even in CI
+        assert!(
+            AppData::new("test_app")
+                .ensure_data_dir()
+                .unwrap()
+                .starts_with(base.path())
+        );
+
+        base.set_var("CI", "false");
+        base.set_var("GITHUB_ACTIONS", "");
+        assert!(
+            AppData::new("test_app")
+                .sandbox_in_ci(true)
+                .ensure_data_dir()
+                .unwrap()
+                .starts_with(base.path())
+        );
+
+        // 清理
+        let _ = fs::remove_dir_all(sandbox);
+    }
+
+    #[test]
+    fn test_data_dir_url() {
+        let _base = SysBase::new("data dir url");
+        let url = AppData::new("test_app").data_dir_url().unwrap();
+        assert!(url.starts_with("file://"));
+        assert!(url.ends_with("/test_app/"));
+        assert!(url.contains("data%20dir%20url"));
+        assert!(!url.contains(' '));
+    }
+
+    #[test]
+    fn test_data_dir_shell() {
+        let _base = SysBase::new("data dir shell");
+        let quoted = AppData::new("test_app").data_dir_shell().unwrap();
+        assert!(quoted.contains("data dir shell"));
+        assert!(quoted.starts_with(['\'', '"']));
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_shell_quote() {
+        let quote = |path: &str| shell_quote(Path::new(path)).unwrap();
+        assert_eq!(
+            quote("/home/me/.local/share/my_app"),
+            "/home/me/.local/share/my_app"
+        );
+        assert_eq!(quote("/home/me/My App"), "'/home/me/My App'");
+        assert_eq!(quote("/tmp/it's"), "'/tmp/it'\\''s'");
+        assert_eq!(quote("/tmp/$HOME \"x\""), "'/tmp/$HOME \"x\"'");
+        assert_eq!(quote("/tmp/a;b"), "'/tmp/a;b'");
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_shell_quote() {
+        let quote = |path: &str| shell_quote(Path::new(path)).unwrap();
+        assert_eq!(quote(r"C:\Users\me\My App"), r#""C:\Users\me\My App""#);
+        assert_eq!(quote(r"C:\it's"), r#""C:\it's""#);
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_dir_to_file_url() {
+        assert_eq!(
+            dir_to_file_url(Path::new("/home/me/My Data")),
+            "file:///home/me/My%20Data/"
+        );
+        assert_eq!(
+            dir_to_file_url(Path::new("/home/me/日本/100%")),
+            "file:///home/me/%E6%97%A5%E6%9C%AC/100%25/"
+        );
+        assert_eq!(dir_to_file_url(Path::new("/")), "file:///");
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_dir_to_file_url() {
+        assert_eq!(
+            dir_to_file_url(Path::new(r"C:\Users\Me\App Data")),
+            "file:///C:/Users/Me/App%20Data/"
+        );
+        assert_eq!(
+            dir_to_file_url(Path::new(r"\\?\C:\Users\Zoë")),
+            "file:///C:/Users/Zo%C3%AB/"
+        );
+        assert_eq!(
+            dir_to_file_url(Path::new(r"\\server\share\app")),
+            "file://server/share/app/"
+        );
+    }
+
+    #[test]
+    fn test_ensure_data_dir_refuses_empty_app_name() {
+        let _base = SysBase::new("refuses_empty_app_name");
+        let sys_dir = get_sys_app_data_dir().unwrap();
+        match AppData::new("").ensure_data_dir() {
+            Err(AppDataError::InvalidAppName(reason)) => assert_eq!(reason, "empty"),
+            other => panic!("Expected InvalidAppName, got {:?}", other),
+        }
+        assert!(AppData::new("").write_file("oops.txt", "").is_err());
+        assert!(!sys_dir.join("oops.txt").exists());
+        assert!(matches!(
+            AppData::with_group("", "suite").ensure_data_dir(),
+            Err(AppDataError::InvalidAppName(_))
+        ));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_app_name_trailing_space_on_windows() {
+        let base = SysBase::new("app_name_trailing_space");
+        let data_dir = AppData::new("test_app. ").ensure_data_dir().unwrap();
+        assert_eq!(data_dir, base.path().join("test_app"));
+        assert_eq!(
+            AppData::new("test_app").ensure_data_dir().unwrap(),
+            data_dir
+        );
+        assert!(matches!(
+            AppData::new(" . ").ensure_data_dir(),
+            Err(AppDataError::InvalidAppName(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_is_system_location() {
+        assert!(is_system_location(Path::new("/")));
+        assert!(is_system_location(Path::new("/usr")));
+        assert!(is_system_location(Path::new("/usr/local")));
+        assert!(is_system_location(Path::new("/etc")));
+        assert!(!is_system_location(Path::new("/usr/local/share/my_app")));
+        assert!(!is_system_location(Path::new(
+            "/home/me/.local/share/my_app"
+        )));
+        assert!(!is_system_location(Path::new("data")));
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_is_system_location() {
+        assert!(is_system_location(Path::new(r"C:\")));
+        assert!(is_system_location(Path::new(r"C:\Windows")));
+        assert!(is_system_location(Path::new(r"c:\program files")));
+        assert!(!is_system_location(Path::new(
+            r"C:\Users\Me\AppData\Roaming\my_app"
+        )));
+    }
+
+    #[test]
+    fn test_base_dir_env_override() {
+        let mut base = SysBase::new("base_dir_env_override");
+        let custom = base.path().join("custom");
+        base.set_var(BASE_DIR_VAR, &custom);
+        let data_dir = AppData::with_group("test_app", "suite")
+            .ensure_data_dir()
+            .unwrap();
+        assert_eq!(data_dir, custom.join("suite").join("test_app"));
+
+        base.set_var(BASE_DIR_VAR, "");
+        let data_dir = AppData::new("test_app").ensure_data_dir().unwrap();
+        assert_eq!(data_dir, get_sys_app_data_dir().unwrap().join("test_app"));
+    }
+
+    #[test]
+    fn test_try_default() {
+        let mut base = SysBase::new("try_default");
+        base.set_var("CARGO_PKG_NAME", "pkg_app");
+        assert_eq!(AppData::try_default().unwrap(), AppData::new("pkg_app"));
+
+        base.remove_var("CARGO_PKG_NAME");
+        match AppData::try_default() {
+            Err(AppDataError::EnvVarNotFound(var)) => assert_eq!(var, "CARGO_PKG_NAME"),
+            other => panic!("Expected EnvVarNotFound, got {:?}", other),
+        }
+        // `Default` stays infallible
+        assert!(AppData::default().force_local);
+    }
+
+    #[test]
+    fn test_would_collide_with() {
+        let base = SysBase::new("would_collide_with");
+        let app_data = AppData::new("test_app");
+        let v1 = AppData::with_version("test_app", "v1");
+        let v2 = AppData::with_version("test_app", "v2");
+        assert!(
+            app_data
+                .would_collide_with(&AppData::new("test_app"))
+                .unwrap()
+        );
+        assert!(app_data.would_collide_with(&v1).unwrap());
+        assert!(v1.would_collide_with(&app_data).unwrap());
+        assert!(!v1.would_collide_with(&v2).unwrap());
+        assert!(
+            !app_data
+                .would_collide_with(&AppData::new("test_app_2"))
+                .unwrap()
+        );
+        assert!(
+            !app_data
+                .would_collide_with(&AppData::with_group("test_app", "suite"))
+                .unwrap()
+        );
+        assert_eq!(fs::read_dir(base.path()).unwrap().count(), 0);
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_normalize_unicode() {
+        let _base = SysBase::new("normalize_unicode");
+        let composed = AppData::new("Caf\u{00E9}").normalize_unicode(true);
+        let decomposed = AppData::new("Cafe\u{0301}").normalize_unicode(true);
+        let composed_dir = composed.ensure_data_dir().unwrap();
+        assert_eq!(composed_dir, decomposed.ensure_data_dir().unwrap());
+        assert!(composed_dir.ends_with("Caf\u{00E9}"));
+        assert!(!composed.resolved_differs_from(&decomposed).unwrap());
+    }
+
+    #[test]
+    fn test_app_data_debug() {
+        let app_data = AppData::new("test_app");
+        let debug_str = format!("{:?}", app_data);
+        assert!(debug_str.contains("test_app"));
+    }
+
+    #[test]
+    fn test_app_data_clone() {
+        let app_data = AppData::new("test_app");
+        let cloned = app_data.clone();
+        assert_eq!(app_data, cloned);
+    }
+
+    #[test]
+    fn test_app_data_partial_eq() {
+        let app_data1 = AppData::new("test_app");
+        let app_data2 = AppData::new("test_app");
+        let app_data3 = AppData::new("other_app");
+        assert_eq!(app_data1, app_data2);
+        assert_ne!(app_data1, app_data3);
+    }
+
+    #[test]
+    fn test_ensure_data_dir_force_local() {
+        let _guard = lock_env();
+        let app_data = AppData::with_force_local("test_app", true);
+        let result = app_data.ensure_data_dir();
+        assert!(result.is_ok());
+        let data_dir = result.unwrap();
+        assert!(data_dir.exists());
+        assert!(data_dir.is_dir());
+        assert!(data_dir.ends_with("data"));
+
+        // 清理
+        let _ = fs::remove_dir_all(&data_dir);
+    }
+
+    #[test]
+    fn test_ensure_parent() {
+        let base = SysBase::new("ensure_parent");
+        let app_data = AppData::new("test_app");
+        let path = app_data.ensure_parent("a/b/c/d/e/settings.toml").unwrap();
+        assert_eq!(path, base.path().join("test_app/a/b/c/d/e/settings.toml"));
+        assert!(path.parent().unwrap().is_dir());
+        assert!(!path.exists());
+
+        for relative in [
+            "",
+            ".",
+            "../escape.txt",
+            "a/../../escape.txt",
+            "/etc/passwd",
+        ] {
+            match app_data.ensure_parent(relative) {
+                Err(AppDataError::InvalidPath(path)) => assert_eq!(path, relative),
+                other => panic!("Expected InvalidPath for {:?}, got {:?}", relative, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_relativize() {
+        let base = SysBase::new("relativize");
+        let app_data = AppData::new("test_app");
+        let path = app_data.get_file_path("config.json").unwrap();
+        assert_eq!(
+            app_data.relativize(&path).unwrap(),
+            Path::new("config.json")
+        );
+        let nested = base.path().join("test_app/profiles/default.toml");
+        assert_eq!(
+            app_data.relativize(&nested).unwrap(),
+            Path::new("profiles/default.toml")
+        );
+        assert_eq!(
+            app_data.relativize(&base.path().join("test_app")).unwrap(),
+            Path::new("")
+        );
+
+        for outside in [
+            base.path().join("other_app/config.json"),
+            base.path().join("test_app/../other_app/config.json"),
+            base.path().join("test_app_2/config.json"),
+        ] {
+            match app_data.relativize(&outside) {
+                Err(AppDataError::InvalidPath(_)) => {}
+                other => panic!("Expected InvalidPath for {:?}, got {:?}", outside, other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_contains() {
+        let base = SysBase::new("contains");
+        let app_data = AppData::new("test_app");
+        let data_dir = base.path().join("test_app");
+        assert!(
+            app_data
+                .contains(&data_dir.join("not/created/yet.txt"))
+                .unwrap()
+        );
+        assert!(!data_dir.exists());
+
+        app_data.write_file("config.json", "{}").unwrap();
+        assert!(app_data.contains(&data_dir.join("config.json")).unwrap());
+        assert!(app_data.contains(&data_dir).unwrap());
+        assert!(app_data.contains(&data_dir.join("./new.txt")).unwrap());
+
+        for outside in [
+            base.path().join("other_app/config.json"),
+            base.path().join("test_app/../other_app/config.json"),
+            base.path().join("test_app/missing/../../escape.txt"),
+            base.path().join("test_app_2"),
+        ] {
+            assert!(!app_data.contains(&outside).unwrap(), "{:?}", outside);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_contains_symlink_escape() {
+        let base = SysBase::new("contains_symlink");
+        let app_data = AppData::new("test_app");
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        let outside = base.path().join("outside");
+        fs::create_dir(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, data_dir.join("link")).unwrap();
+        std::os::unix::fs::symlink(data_dir.join("config.json"), base.path().join("in")).unwrap();
+
+        assert!(!app_data.contains(&data_dir.join("link")).unwrap());
+        assert!(!app_data.contains(&data_dir.join("link/new.txt")).unwrap());
+        // A link from outside pointing into the data directory is inside once resolved
+        app_data.write_file("config.json", "{}").unwrap();
+        assert!(app_data.contains(&base.path().join("in")).unwrap());
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_long_path() {
+        let base = SysBase::new("long_path");
+        let profile = "p".repeat(120);
+        let app_data = AppData::with_version("test_app", &profile);
+        let file_name = format!("{}/settings.txt", "s".repeat(120));
+        assert!(
+            base.path()
+                .join(&profile)
+                .join(&file_name)
+                .as_os_str()
+                .len()
+                > MAX_PATH
+        );
+
+        let path = app_data.ensure_parent(&file_name).unwrap();
+        assert!(path.to_str().unwrap().starts_with(r"\\?\"));
+        app_data.write_file(&file_name, "long").unwrap();
+        assert_eq!(app_data.read_to_string(&file_name).unwrap(), "long");
+        assert_eq!(fs::read_to_string(path).unwrap(), "long");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_dangling_symlink_data_dir() {
+        let base = SysBase::new("dangling_symlink");
+        let target = base.path().join("moved/away");
+        std::os::unix::fs::symlink(&target, base.path().join("test_app")).unwrap();
+
+        match AppData::new("test_app").ensure_data_dir() {
+            Err(AppDataError::DanglingSymlink(path)) => assert!(path.ends_with("test_app")),
+            other => panic!("Expected DanglingSymlink, got {:?}", other),
+        }
+        assert!(!target.exists());
+
+        let app_data = AppData::new("test_app")
+            .recreate_symlink_target(true)
+            .write_readme(true);
+        let dir = app_data.ensure_data_dir().unwrap();
+        assert!(target.is_dir());
+        assert!(fs::symlink_metadata(&dir).unwrap().file_type().is_symlink());
+        assert!(target.join(README_FILE).is_file());
+        app_data.write_file("a.txt", "a").unwrap();
+        assert_eq!(fs::read_to_string(target.join("a.txt")).unwrap(), "a");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recreated_symlink_target_removed_on_init_failure() {
+        let base = SysBase::new("symlink_init_failure");
+        let target = base.path().join("moved/away");
+        let link = base.path().join("test_app");
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        let app_data = AppData::new("test_app").recreate_symlink_target(true);
+        let result = app_data.ensure_data_dir_with_init(|_| {
+            Err(AppDataError::IoError("seeding failed".to_string()))
+        });
+        assert!(matches!(result, Err(AppDataError::IoError(_))));
+        assert!(!target.exists());
+        assert!(
+            fs::symlink_metadata(&link)
+                .unwrap()
+                .file_type()
+                .is_symlink()
+        );
+
+        let mut runs = 0;
+        app_data
+            .ensure_data_dir_with_init(|_| {
+                runs += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(runs, 1);
+        assert!(target.is_dir());
+    }
+
+    #[test]
+    fn test_reads_dont_create_data_dir() {
+        let base = SysBase::new("reads_dont_create");
+        let app_data = AppData::new("test_app");
+        assert_eq!(
+            app_data.data_dir_path().unwrap(),
+            long_path(base.path().join("test_app"))
+        );
+        assert!(!app_data.file_exists("config.toml").unwrap());
+        assert!(app_data.read_file("config.toml").is_err());
+        assert!(app_data.read_to_string("config.toml").is_err());
+        assert!(matches!(
+            app_data.read_into_writer("config.toml", &mut Vec::new()),
+            Err(AppDataError::FileNotFound(_))
+        ));
+        assert_eq!(app_data.find_file_ci("Config.toml").unwrap(), None);
+        assert_eq!(app_data.file_count(true).unwrap(), 0);
+        assert!(app_data.list_files_by_mtime(true).unwrap().is_empty());
+        assert!(!base.path().join("test_app").exists());
+
+        app_data.write_file("config.toml", "a = 1").unwrap();
+        assert!(app_data.file_exists("config.toml").unwrap());
+    }
+
+    #[test]
+    fn test_file_count() {
+        let _base = SysBase::new("file_count");
+        let app_data = AppData::new("test_app");
+        assert_eq!(app_data.file_count(true).unwrap(), 0);
+
+        for name in [
+            "a.txt",
+            "b.txt",
+            "saves/1.sav",
+            "saves/2.sav",
+            "saves/old/0.sav",
+        ] {
+            app_data.ensure_parent(name).unwrap();
+            app_data.write_file(name, "x").unwrap();
+        }
+        fs::create_dir(app_data.get_file_path("empty").unwrap()).unwrap();
+
+        assert_eq!(app_data.file_count(false).unwrap(), 2);
+        assert_eq!(app_data.file_count(true).unwrap(), 5);
+    }
+
+    #[test]
+    fn test_list_files_by_mtime() {
+        let base = SysBase::new("list_files_by_mtime");
+        let app_data = AppData::new("test_app");
+        let start = std::time::SystemTime::now();
+        for (name, age) in [
+            ("b.txt", 10),
+            ("old.txt", 30),
+            ("a.txt", 10),
+            ("sub/new.txt", 0),
+        ] {
+            let path = app_data.ensure_parent(name).unwrap();
+            let file = fs::File::create(path).unwrap();
+            file.set_modified(start - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+        let dir = base.path().join("test_app");
+
+        assert_eq!(
+            app_data.list_files_by_mtime(true).unwrap(),
+            [
+                dir.join("sub/new.txt"),
+                dir.join("a.txt"),
+                dir.join("b.txt"),
+                dir.join("old.txt")
+            ]
+        );
+        assert_eq!(
+            app_data.list_files_by_mtime(false).unwrap(),
+            [
+                dir.join("old.txt"),
+                dir.join("a.txt"),
+                dir.join("b.txt"),
+                dir.join("sub/new.txt")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discover_plugins() {
+        let base = SysBase::new("discover_plugins");
+        let app_data = AppData::new("test_app");
+        let plugins_dir = app_data.plugins_dir().unwrap();
+        assert_eq!(plugins_dir, base.path().join("test_app/plugins"));
+        assert!(plugins_dir.is_dir());
+        assert!(app_data.discover_plugins("wasm").unwrap().is_empty());
+
+        fs::write(plugins_dir.join("b.wasm"), "").unwrap();
+        fs::write(plugins_dir.join("a.wasm"), "").unwrap();
+        fs::create_dir(plugins_dir.join("bundle.wasm")).unwrap();
+        fs::write(plugins_dir.join("notes.txt"), "").unwrap();
+        fs::write(plugins_dir.join("wasm"), "").unwrap();
+
+        let expected = [
+            plugins_dir.join("a.wasm"),
+            plugins_dir.join("b.wasm"),
+            plugins_dir.join("bundle.wasm"),
+        ];
+        assert_eq!(app_data.discover_plugins("wasm").unwrap(), expected);
+        assert_eq!(app_data.discover_plugins(".wasm").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_read_only() {
+        let base = SysBase::new("read_only");
+        let expected = base.path().join("test_app");
+        let app_data = AppData::new("test_app").read_only(true);
+        assert_eq!(app_data.ensure_data_dir().unwrap(), expected);
+        assert!(!expected.exists());
+        assert!(app_data.list_files_by_mtime(true).unwrap().is_empty());
+        assert!(app_data.discover_plugins("wasm").unwrap().is_empty());
+
+        AppData::new("test_app")
+            .write_file("config.toml", "theme = 1")
+            .unwrap();
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "theme = 1");
+        assert!(app_data.file_exists("config.toml").unwrap());
+
+        let errors = [
+            app_data.write_file("config.toml", "x"),
+            app_data.write_file_atomic("config.toml", "x"),
+            app_data.write_batch(&[("config.toml", b"x")]),
+            app_data.delete_file("config.toml"),
+            app_data.ensure_parent("a/b.txt").map(drop),
+            app_data.check_writable(),
+            app_data.appender("events.log").map(drop),
+        ];
+        for error in errors {
+            match error {
+                Err(AppDataError::ReadOnly(_)) => {}
+                other => panic!("Expected ReadOnly error, got {:?}", other),
+            }
+        }
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "theme = 1");
+        assert!(!expected.join("a").exists());
+    }
+
+    #[test]
+    fn test_list_apps_in_system_dir() {
+        let mut base = SysBase::new("list_apps");
+        assert!(list_apps_in_system_dir().unwrap().is_empty());
+        AppData::new("suite_b").ensure_data_dir().unwrap();
+        AppData::new("suite_a").ensure_data_dir().unwrap();
+        fs::write(base.path().join("stray.txt"), "").unwrap();
+        assert_eq!(list_apps_in_system_dir().unwrap(), ["suite_a", "suite_b"]);
+
+        let overridden = base.path().join("override");
+        base.set_var("APPDATA_BASE_DIR", &overridden);
+        assert!(list_apps_in_system_dir().unwrap().is_empty());
+        AppData::new("suite_c").ensure_data_dir().unwrap();
+        assert_eq!(list_apps_in_system_dir().unwrap(), ["suite_c"]);
+    }
+
+    #[test]
+    fn test_seed_from_dir() {
+        let base = SysBase::new("seed_from_dir");
+        let source = base.path().join("defaults");
+        fs::create_dir_all(source.join("samples/nested")).unwrap();
+        fs::write(source.join("config.toml"), "theme = 1").unwrap();
+        fs::write(source.join("samples/a.txt"), "a").unwrap();
+        fs::write(source.join("samples/nested/b.txt"), "b").unwrap();
+
+        let app_data = AppData::new("test_app");
+        assert_eq!(app_data.seed_from_dir(&source).unwrap(), 3);
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "theme = 1");
+        assert_eq!(
+            app_data.read_to_string("samples/nested/b.txt").unwrap(),
+            "b"
+        );
+
+        // Later runs keep user edits and deletions
+        app_data.write_file("config.toml", "theme = 2").unwrap();
+        app_data.delete_file("samples/a.txt").unwrap();
+        assert_eq!(app_data.seed_from_dir(&source).unwrap(), 0);
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "theme = 2");
+        assert!(!app_data.file_exists("samples/a.txt").unwrap());
+    }
+
+    #[test]
+    fn test_seed_from_dir_with_progress() {
+        let base = SysBase::new("seed_from_dir_progress");
+        let source = base.path().join("defaults");
+        fs::create_dir_all(source.join("nested")).unwrap();
+        fs::write(source.join("a.bin"), [0; 10]).unwrap();
+        fs::write(source.join("nested/b.bin"), [0; 30]).unwrap();
+
+        let app_data = AppData::new("test_app");
+        let mut reports = Vec::new();
+        let copied = app_data
+            .seed_from_dir_with_progress(&source, |progress| reports.push(progress))
+            .unwrap();
+        assert_eq!(copied, 2);
+        assert_eq!(reports.len(), 2);
+        assert_eq!(reports[0].files_done, 1);
+        assert_eq!(reports[0].bytes_done, 10);
+        assert_eq!(
+            reports[1],
+            Progress {
+                files_done: 2,
+                files_total: 2,
+                bytes_done: 40,
+                bytes_total: 40
+            }
+        );
+        assert!(reports[1].is_complete());
+        assert_eq!(reports[1].fraction(), 1.0);
+
+        // Nothing left to copy still reports completion once
+        reports.clear();
+        app_data
+            .seed_from_dir_with_progress(&source, |progress| reports.push(progress))
+            .unwrap();
+        assert_eq!(reports, [Progress::default()]);
+    }
+
+    #[test]
+    fn test_oldest_file() {
+        let base = SysBase::new("oldest_file");
+        let app_data = AppData::new("test_app");
+        assert_eq!(app_data.oldest_file().unwrap(), None);
+
+        let start = std::time::SystemTime::now();
+        for (name, age) in [("new.txt", 0), ("sub/oldest.txt", 60), ("old.txt", 30)] {
+            let file = fs::File::create(app_data.ensure_parent(name).unwrap()).unwrap();
+            file.set_modified(start - std::time::Duration::from_secs(age))
+                .unwrap();
+        }
+        assert_eq!(
+            app_data.oldest_file().unwrap(),
+            Some(base.path().join("test_app/sub/oldest.txt"))
+        );
+    }
+
+    #[test]
+    fn test_migrate_from() {
+        let base = SysBase::new("migrate_from");
+        let legacy = base.path().join(".test_app");
+        fs::create_dir_all(legacy.join("profiles")).unwrap();
+        fs::write(legacy.join("config.toml"), "theme = 1").unwrap();
+        fs::write(legacy.join("profiles/default.toml"), "name = 1").unwrap();
+
+        let app_data = AppData::new("test_app");
+        assert!(app_data.migrate_from(&legacy).unwrap());
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "theme = 1");
+        assert_eq!(
+            app_data.read_to_string("profiles/default.toml").unwrap(),
+            "name = 1"
+        );
+        assert!(!legacy.join("config.toml").exists());
+        assert_eq!(
+            fs::read_to_string(legacy.join(".migrated")).unwrap(),
+            app_data.ensure_data_dir().unwrap().display().to_string()
+        );
+        // Already migrated
+        assert!(!app_data.migrate_from(&legacy).unwrap());
+        assert!(!app_data.migrate_from(&base.path().join("missing")).unwrap());
+    }
+
+    #[test]
+    fn test_migrate_from_skips_populated_target() {
+        let base = SysBase::new("migrate_from_populated");
+        let legacy = base.path().join(".test_app");
+        fs::create_dir_all(&legacy).unwrap();
+        fs::write(legacy.join("config.toml"), "old").unwrap();
+
+        let app_data = AppData::new("test_app");
+        app_data.write_file("config.toml", "new").unwrap();
+        assert!(!app_data.migrate_from(&legacy).unwrap());
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "new");
+        assert_eq!(
+            fs::read_to_string(legacy.join("config.toml")).unwrap(),
+            "old"
+        );
+        assert!(!legacy.join(".migrated").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_file_with_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _base = SysBase::new("write_file_with_mode");
+        let app_data = AppData::new("test_app");
+        let mode_of = |name: &str| {
+            let path = app_data.get_file_path(name).unwrap();
+            fs::metadata(path).unwrap().permissions().mode() & 0o777
+        };
+
+        app_data
+            .write_file_with_mode("token", "secret", Some(0o600))
+            .unwrap();
+        assert_eq!(mode_of("token"), 0o600);
+        assert_eq!(app_data.read_to_string("token").unwrap(), "secret");
+
+        // Existing files get the new mode too, beyond what the umask would allow
+        app_data
+            .write_file_with_mode("token", "shared", Some(0o666))
+            .unwrap();
+        assert_eq!(mode_of("token"), 0o666);
+        assert_eq!(app_data.read_to_string("token").unwrap(), "shared");
+
+        app_data.write_file_with_mode("plain", "x", None).unwrap();
+        assert_eq!(app_data.read_to_string("plain").unwrap(), "x");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_home_symlink() {
+        let mut base = SysBase::new("resolve_home_symlink");
+        let real_home = base.path().join("export/home/user");
+        fs::create_dir_all(&real_home).unwrap();
+        let link_home = base.path().join("home-user");
+        std::os::unix::fs::symlink(&real_home, &link_home).unwrap();
+        base.remove_var("XDG_DATA_HOME");
+        base.set_var("HOME", &link_home);
+
+        let app_data = AppData::new("test_app");
+        assert!(app_data.system_base().unwrap().starts_with(&link_home));
+        let resolved = app_data.resolve_home_symlink(true);
+        let sys_base = resolved.system_base().unwrap();
+        assert!(sys_base.starts_with(fs::canonicalize(&real_home).unwrap()));
+        assert!(resolved.ensure_data_dir().unwrap().starts_with(&sys_base));
+    }
+
+    #[test]
+    fn test_system_base() {
+        let mut base = SysBase::new("system_base");
+        let app_data = AppData::with_group("test_app", "suite");
+        assert_eq!(app_data.system_base().unwrap(), base.path());
+        assert_eq!(
+            app_data.system_base().unwrap(),
+            get_sys_app_data_dir().unwrap()
+        );
+        assert!(!base.path().join("suite").exists());
+
+        let overridden = base.path().join("override");
+        base.set_var("APPDATA_BASE_DIR", &overridden);
+        assert_eq!(app_data.system_base().unwrap(), overridden);
+        assert!(!overridden.exists());
+    }
+
+    #[test]
+    fn test_can_create_system_dir() {
+        let mut base = SysBase::new("can_create_system_dir");
+        let app_data = AppData::new("test_app");
+        assert!(app_data.can_create_system_dir().unwrap());
+        assert!(!base.path().join("test_app").exists());
+
+        let missing = base.path().join("missing/deeper");
+        base.set_var("APPDATA_BASE_DIR", &missing);
+        assert!(app_data.can_create_system_dir().unwrap());
+        assert!(!base.path().join("missing").exists());
+
+        let file = base.path().join("file");
+        fs::write(&file, "").unwrap();
+        base.set_var("APPDATA_BASE_DIR", file.join("below"));
+        assert!(!app_data.can_create_system_dir().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_can_create_system_dir_read_only_base() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let base = SysBase::new("can_create_system_dir_read_only");
+        let app_data = AppData::new("test_app");
+        fs::set_permissions(base.path(), fs::Permissions::from_mode(0o555)).unwrap();
+        // Privileged users bypass permission bits, nothing to assert then
+        if fs::write(base.path().join("root_check"), "").is_err() {
+            assert!(!app_data.can_create_system_dir().unwrap());
+        }
+        fs::set_permissions(base.path(), fs::Permissions::from_mode(0o755)).unwrap();
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs;
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_system_base_convention() {
+        let mut base = SysBase::new("system_base_convention");
+        let home = base.path().join("home");
+        base.remove_var("XDG_DATA_HOME");
+        base.set_var("HOME", &home);
+        assert_eq!(
+            AppData::new("test_app").system_base().unwrap(),
+            home.join(".local/share")
+        );
+    }
 
     #[test]
-    fn test_app_data_new() {
+    fn test_find_file_ci() {
+        let base = SysBase::new("find_file_ci");
         let app_data = AppData::new("test_app");
-        assert_eq!(app_data.app_name, "test_app");
-        assert_eq!(app_data.force_local, false);
+        let dir = base.path().join("test_app");
+        app_data.write_file("Config.JSON", "{}").unwrap();
+        app_data.ensure_parent("Profiles/Default.toml").unwrap();
+        app_data.write_file("Profiles/Default.toml", "").unwrap();
+
+        assert_eq!(
+            app_data.find_file_ci("config.json").unwrap(),
+            Some(dir.join("Config.JSON"))
+        );
+        assert_eq!(
+            app_data.find_file_ci("Config.JSON").unwrap(),
+            Some(dir.join("Config.JSON"))
+        );
+        assert_eq!(
+            app_data.find_file_ci("profiles/DEFAULT.toml").unwrap(),
+            Some(dir.join("Profiles/Default.toml"))
+        );
+        assert_eq!(app_data.find_file_ci("missing.json").unwrap(), None);
+        assert_eq!(app_data.find_file_ci("profiles").unwrap(), None);
+        assert_eq!(app_data.find_file_ci("missing/config.json").unwrap(), None);
+
+        // Where both spellings can exist, an exact match wins
+        let exact = dir.join("config.json");
+        let created = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&exact);
+        if created.is_ok() {
+            assert_eq!(app_data.find_file_ci("config.json").unwrap(), Some(exact));
+            assert_eq!(
+                app_data.find_file_ci("CONFIG.json").unwrap(),
+                Some(dir.join("Config.JSON"))
+            );
+        }
     }
 
     #[test]
-    fn test_app_data_with_force_local() {
-        let app_data = AppData::with_force_local("test_app", true);
-        assert_eq!(app_data.app_name, "test_app");
-        assert_eq!(app_data.force_local, true);
+    fn test_create_new_file() {
+        let _base = SysBase::new("create_new_file");
+        let app_data = AppData::new("test_app");
+        app_data.create_new_file("doc-1.md", "# First").unwrap();
+        assert_eq!(app_data.read_to_string("doc-1.md").unwrap(), "# First");
+
+        match app_data.create_new_file("doc-1.md", "# Clobbered") {
+            Err(AppDataError::AlreadyExists(path)) => assert!(path.ends_with("doc-1.md")),
+            other => panic!("Expected AlreadyExists, got {:?}", other),
+        }
+        assert_eq!(app_data.read_to_string("doc-1.md").unwrap(), "# First");
     }
 
     #[test]
-    fn test_app_data_debug() {
+    fn test_write_from_reader() {
+        let _base = SysBase::new("write_from_reader");
         let app_data = AppData::new("test_app");
-        let debug_str = format!("{:?}", app_data);
-        assert!(debug_str.contains("test_app"));
+        let contents = vec![7u8; 100_000];
+        let written = app_data
+            .write_from_reader("model.bin", &mut contents.as_slice())
+            .unwrap();
+        assert_eq!(written, 100_000);
+        assert_eq!(app_data.read_file("model.bin").unwrap(), contents);
+
+        struct Failing;
+        impl Read for Failing {
+            fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::other("connection reset"))
+            }
+        }
+        match app_data.write_from_reader("model.bin", &mut Failing) {
+            Err(AppDataError::IoError(msg)) => {
+                assert!(msg.contains("model.bin") && msg.contains("connection reset"))
+            }
+            other => panic!("Expected IoError, got {:?}", other),
+        }
+        assert_eq!(app_data.read_file("model.bin").unwrap(), contents);
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        assert_eq!(fs::read_dir(data_dir).unwrap().count(), 1);
     }
 
     #[test]
-    fn test_app_data_clone() {
+    fn test_read_into_writer() {
+        let _base = SysBase::new("read_into_writer");
         let app_data = AppData::new("test_app");
-        let cloned = app_data.clone();
-        assert_eq!(app_data, cloned);
+        app_data.write_file("report.csv", "a,b\n1,2\n").unwrap();
+        let mut exported = Vec::new();
+        assert_eq!(
+            app_data
+                .read_into_writer("report.csv", &mut exported)
+                .unwrap(),
+            8
+        );
+        assert_eq!(exported, b"a,b\n1,2\n");
+
+        match app_data.read_into_writer("missing.csv", &mut exported) {
+            Err(AppDataError::FileNotFound(path)) => assert!(path.ends_with("missing.csv")),
+            other => panic!("Expected FileNotFound, got {:?}", other),
+        }
     }
 
     #[test]
-    fn test_app_data_partial_eq() {
-        let app_data1 = AppData::new("test_app");
-        let app_data2 = AppData::new("test_app");
-        let app_data3 = AppData::new("other_app");
-        assert_eq!(app_data1, app_data2);
-        assert_ne!(app_data1, app_data3);
+    fn test_export_file() {
+        let base = SysBase::new("export_file");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("report.csv", "a,b\n1,2\n").unwrap();
+        let dest = base.path().join("exports/2024/report.csv");
+
+        app_data.export_file("report.csv", &dest).unwrap();
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "a,b\n1,2\n");
+        app_data.write_file("report.csv", "a,b\n3,4\n").unwrap();
+        app_data.export_file("report.csv", &dest).unwrap();
+        assert_eq!(fs::read_to_string(&dest).unwrap(), "a,b\n3,4\n");
+        assert_eq!(fs::read_dir(dest.parent().unwrap()).unwrap().count(), 1);
+
+        match app_data.export_file("missing.csv", &dest) {
+            Err(AppDataError::FileNotFound(path)) => assert!(path.ends_with("missing.csv")),
+            other => panic!("Expected FileNotFound, got {:?}", other),
+        }
+        assert!(matches!(
+            app_data.export_file("../report.csv", &dest),
+            Err(AppDataError::InvalidPath(_))
+        ));
     }
 
     #[test]
-    fn test_ensure_data_dir_force_local() {
-        let app_data = AppData::with_force_local("test_app", true);
-        let result = app_data.ensure_data_dir();
-        assert!(result.is_ok());
-        let data_dir = result.unwrap();
-        assert!(data_dir.exists());
+    fn test_import_file() {
+        let base = SysBase::new("import_file");
+        let app_data = AppData::new("test_app");
+        let src = base.path().join("downloads/theme.toml");
+        fs::create_dir_all(src.parent().unwrap()).unwrap();
+        fs::write(&src, "accent = \"blue\"").unwrap();
+
+        app_data.import_file(&src, "themes/imported.toml").unwrap();
+        assert_eq!(
+            app_data.read_to_string("themes/imported.toml").unwrap(),
+            "accent = \"blue\""
+        );
+        fs::write(&src, "accent = \"red\"").unwrap();
+        app_data.import_file(&src, "themes/imported.toml").unwrap();
+        assert_eq!(
+            app_data.read_to_string("themes/imported.toml").unwrap(),
+            "accent = \"red\""
+        );
+
+        match app_data.import_new_file(&src, "themes/imported.toml") {
+            Err(AppDataError::AlreadyExists(path)) => assert!(path.ends_with("imported.toml")),
+            other => panic!("Expected AlreadyExists, got {:?}", other),
+        }
+        app_data.import_new_file(&src, "themes/copy.toml").unwrap();
+        assert_eq!(
+            app_data.read_to_string("themes/copy.toml").unwrap(),
+            "accent = \"red\""
+        );
+        assert_eq!(app_data.file_count(true).unwrap(), 2);
+
+        assert!(matches!(
+            app_data.import_file(&base.path().join("missing.toml"), "a.toml"),
+            Err(AppDataError::FileNotFound(_))
+        ));
+        assert!(matches!(
+            app_data.import_file(&src, "../escaped.toml"),
+            Err(AppDataError::InvalidPath(_))
+        ));
+        assert!(!base.path().join("escaped.toml").exists());
+    }
+
+    #[test]
+    fn test_write_file_if_changed() {
+        let _base = SysBase::new("write_file_if_changed");
+        let app_data = AppData::new("test_app");
+        assert!(
+            app_data
+                .write_file_if_changed("config.toml", "a = 1")
+                .unwrap()
+        );
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "a = 1");
+
+        let path = app_data.get_file_path("config.toml").unwrap();
+        let past = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(past)
+            .unwrap();
+        assert!(
+            !app_data
+                .write_file_if_changed("config.toml", "a = 1")
+                .unwrap()
+        );
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), past);
+
+        assert!(
+            app_data
+                .write_file_if_changed("config.toml", "a = 2")
+                .unwrap()
+        );
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "a = 2");
+        assert_ne!(fs::metadata(&path).unwrap().modified().unwrap(), past);
+    }
+
+    #[test]
+    fn test_prefer_container_volume() {
+        let mut base = SysBase::new("prefer_container_volume");
+        let volume = base.path().join("volume");
+        base.set_var("DATA_DIR", &volume);
+        let app_data = AppData::new("test_app").prefer_container_volume(true);
+
+        // A missing volume falls back to the platform directory
+        assert_eq!(
+            app_data.resolve_app_dir().unwrap(),
+            base.path().join("test_app")
+        );
+
+        fs::create_dir(&volume).unwrap();
+        assert_eq!(
+            app_data.ensure_data_dir().unwrap(),
+            long_path(volume.join("test_app"))
+        );
+        assert_eq!(
+            app_data.cache_dir().unwrap(),
+            long_path(volume.join("test_app/cache"))
+        );
+        assert_eq!(fs::read_dir(&volume).unwrap().count(), 1);
+        assert_eq!(
+            AppData::new("test_app").resolve_app_dir().unwrap(),
+            base.path().join("test_app")
+        );
+
+        base.set_var("APPDATA_BASE_DIR", base.path().join("explicit"));
+        assert_eq!(
+            app_data.resolve_app_dir().unwrap(),
+            base.path().join("explicit/test_app")
+        );
+    }
+
+    #[test]
+    fn test_with_base_arg() {
+        let mut base = SysBase::new("with_base_arg");
+        base.set_var("APPDATA_BASE_DIR", base.path().join("env"));
+        let mut app_data = AppData::new("test_app");
+        app_data.with_base_arg(None).unwrap();
+        assert_eq!(app_data.base_dir, None);
+
+        let flag = base.path().join("flag");
+        app_data.with_base_arg(flag.to_str()).unwrap();
+        assert_eq!(
+            app_data.ensure_data_dir().unwrap(),
+            long_path(flag.join("test_app"))
+        );
+
+        app_data.with_base_arg(Some("relative/dir")).unwrap();
+        assert_eq!(
+            app_data.base_dir,
+            Some(current_dir().unwrap().join("relative/dir"))
+        );
+        assert!(matches!(
+            app_data.with_base_arg(Some("")),
+            Err(AppDataError::InvalidPath(_))
+        ));
+    }
+
+    #[test]
+    fn test_home_dir() {
+        let _lock = lock_env();
+        let home = AppData::home_dir().unwrap();
+        assert!(!home.as_os_str().is_empty());
+    }
+
+    /// `home_dir_with` reading `vars` only
+    fn home_dir_from(vars: &[(&str, &str)]) -> Result<PathBuf, AppDataError> {
+        let env: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        home_dir_with(&|key| env.get(key).cloned())
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn test_home_dir_with() {
+        assert_eq!(
+            home_dir_from(&[("HOME", "/home/me")]).unwrap(),
+            PathBuf::from("/home/me")
+        );
+        for vars in [&[][..], &[("HOME", "")]] {
+            assert!(matches!(
+                home_dir_from(vars),
+                Err(AppDataError::EnvVarNotFound(var)) if var == "HOME"
+            ));
+        }
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_home_dir_with() {
+        assert_eq!(
+            home_dir_from(&[("USERPROFILE", r"C:\Users\me"), ("HOMEDRIVE", "D:")]).unwrap(),
+            PathBuf::from(r"C:\Users\me")
+        );
+        assert_eq!(
+            home_dir_from(&[("HOMEDRIVE", "D:"), ("HOMEPATH", r"\Users\me")]).unwrap(),
+            PathBuf::from(r"D:\Users\me")
+        );
+        assert!(matches!(
+            home_dir_from(&[("USERPROFILE", ""), ("HOMEDRIVE", "D:")]),
+            Err(AppDataError::EnvVarNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("My Cool App!"), "my-cool-app");
+        assert_eq!(slugify("  Tom's   Editor -- v2.0  "), "toms-editor-v2-0");
+        assert_eq!(slugify("snake_case.name"), "snake-case-name");
+        assert_eq!(slugify("Café Crème"), "café-crème");
+        assert_eq!(slugify("Cafe\u{301}"), "café");
+        assert_eq!(slugify("数据 工具"), "数据-工具");
+        assert_eq!(slugify("ÄÖÜ"), "äöü");
+        assert_eq!(slugify("a/b\\c:d"), "abcd");
+        assert_eq!(slugify("!!!"), "");
+    }
+
+    #[test]
+    fn test_slugify_option() {
+        let base = SysBase::new("slugify_option");
+        let app_data = AppData::new("My Cool App!").slugify(true);
+        assert_eq!(
+            app_data.ensure_data_dir().unwrap(),
+            long_path(base.path().join("my-cool-app"))
+        );
+        assert_eq!(
+            AppData::new("My Cool App!").resolve_app_dir().unwrap(),
+            base.path().join("My Cool App!")
+        );
+        assert!(matches!(
+            AppData::new("???").slugify(true).resolve_app_dir(),
+            Err(AppDataError::InvalidAppName(_))
+        ));
+    }
+
+    #[test]
+    fn test_sibling() {
+        let base = SysBase::new("sibling");
+        let app_data = AppData {
+            base_dir: Some(base.path().join("suite")),
+            ..AppData::with_version("editor", "v2")
+        };
+        let sibling = app_data.sibling("viewer");
+
+        assert_eq!(sibling.app_name, "viewer");
+        assert_eq!(sibling.version_subdir.as_deref(), Some("v2"));
+        assert!(!base.path().join("suite").exists());
+        assert_eq!(
+            sibling.ensure_data_dir().unwrap(),
+            long_path(base.path().join("suite/viewer/v2"))
+        );
+        assert_eq!(
+            app_data.ensure_data_dir().unwrap(),
+            long_path(base.path().join("suite/editor/v2"))
+        );
+    }
+
+    #[test]
+    fn test_kv_path() {
+        let base = SysBase::new("kv_path");
+        let app_data = AppData::new("test_app");
+        let kv = base.path().join("test_app/kv");
+
+        let sessions = app_data.kv_path("sessions").unwrap();
+        let cache = app_data.kv_path("cache").unwrap();
+        assert_eq!(sessions, kv.join("sessions"));
+        assert_eq!(cache.parent(), sessions.parent());
+        assert_ne!(cache, sessions);
+        assert!(kv.is_dir());
+        assert!(!sessions.exists());
+
+        assert_eq!(app_data.kv_path("../escape").unwrap(), kv.join(".._escape"));
+        assert_eq!(app_data.kv_path("..").unwrap(), kv.join("_"));
+    }
+
+    #[test]
+    fn test_resource_path() {
+        let base = SysBase::new("resource_path");
+        let install = base.path().join("install");
+        fs::create_dir_all(install.join("assets")).unwrap();
+        fs::write(install.join("my_app"), "").unwrap();
+        fs::write(install.join("assets/icon.png"), "png").unwrap();
+
+        let app_data = AppData::new("test_app").with_resource_root(&install);
+        assert_eq!(
+            app_data.resource_path("assets/icon.png").unwrap(),
+            install.join("assets/icon.png")
+        );
+        match app_data.resource_path("assets/missing.png") {
+            Err(AppDataError::FileNotFound(path)) => {
+                assert_eq!(path, install.join("assets/missing.png"))
+            }
+            other => panic!("Expected FileNotFound, got {:?}", other),
+        }
+        assert!(matches!(
+            app_data.resource_path("../escape"),
+            Err(AppDataError::InvalidPath(_))
+        ));
+        // Resources are separate from the data directory
+        assert!(!base.path().join("test_app").exists());
+
+        let exe = env::current_exe().unwrap();
+        let exe_name = exe.file_name().unwrap().to_str().unwrap();
+        assert_eq!(
+            AppData::new("test_app").resource_path(exe_name).unwrap(),
+            exe
+        );
+    }
+
+    #[test]
+    fn test_write_readme() {
+        let base = SysBase::new("write_readme");
+        let data_dir = AppData::new("test_app")
+            .write_readme(true)
+            .ensure_data_dir()
+            .unwrap();
+        let readme = fs::read_to_string(data_dir.join(README_FILE)).unwrap();
+        assert!(readme.contains("test_app"));
+
+        let custom = AppData::new("custom_app")
+            .write_readme(true)
+            .readme_text("Delete me to reset.\n");
+        let custom_dir = custom.ensure_data_dir().unwrap();
+        assert_eq!(
+            custom.read_to_string(README_FILE).unwrap(),
+            "Delete me to reset.\n"
+        );
+
+        // Existing directories and READMEs are left as they are
+        fs::write(custom_dir.join(README_FILE), "edited").unwrap();
+        custom.readme_text("other").ensure_data_dir().unwrap();
+        assert_eq!(
+            fs::read_to_string(custom_dir.join(README_FILE)).unwrap(),
+            "edited"
+        );
+
+        AppData::new("plain_app").ensure_data_dir().unwrap();
+        assert!(!base.path().join("plain_app").join(README_FILE).exists());
+    }
+
+    #[test]
+    fn test_ignore_empty_local() {
+        let base = SysBase::new("ignore_empty_local");
+        let local = current_dir().unwrap().join("data");
+        if local.exists() {
+            // A real local `./data` would decide resolution on its own
+            return;
+        }
+        fs::create_dir(&local).unwrap();
+
+        let default = AppData::new("test_app").ensure_data_dir();
+        let ignored = AppData::new("test_app")
+            .ignore_empty_local(true)
+            .ensure_data_dir();
+        fs::write(local.join("settings.txt"), "x").unwrap();
+        let non_empty = AppData::new("test_app")
+            .ignore_empty_local(true)
+            .ensure_data_dir();
+
+        // 清理
+        let _ = fs::remove_dir_all(&local);
+        assert_eq!(default.unwrap(), local);
+        assert_eq!(ignored.unwrap(), base.path().join("test_app"));
+        assert_eq!(non_empty.unwrap(), local);
+    }
+
+    #[test]
+    fn test_skip_local_probe() {
+        let base = SysBase::new("skip_local_probe");
+        let local = current_dir().unwrap().join("data");
+        if local.exists() {
+            // A real local `./data` would decide resolution on its own
+            return;
+        }
+        fs::create_dir(&local).unwrap();
+        fs::write(local.join("settings.txt"), "x").unwrap();
+
+        let default = AppData::new("test_app").ensure_data_dir();
+        let skipped = AppData::new("test_app")
+            .skip_local_probe(true)
+            .ensure_data_dir();
+        let forced = AppData::with_force_local("test_app", true)
+            .skip_local_probe(true)
+            .ensure_data_dir();
+
+        // 清理
+        let _ = fs::remove_dir_all(&local);
+        assert_eq!(default.unwrap(), local);
+        assert_eq!(skipped.unwrap(), base.path().join("test_app"));
+        assert_eq!(forced.unwrap(), local);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_ensure_data_dir_local_hidden() {
+        let _guard = lock_env();
+        let app_data = AppData::with_force_local("app_data_hidden_test", true).local_hidden(true);
+        let data_dir = app_data.ensure_data_dir().unwrap();
         assert!(data_dir.is_dir());
-        assert!(data_dir.ends_with("data"));
+        assert_eq!(data_dir.file_name().unwrap(), ".app_data_hidden_test");
 
         // 清理
         let _ = fs::remove_dir_all(&data_dir);
     }
 
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
+    #[test]
+    fn test_sys_app_data_dir_xdg_data_home() {
+        let mut base = SysBase::new("sys_app_data_dir_xdg_data_home");
+        let home = base.path().join("home");
+        base.set_var("HOME", &home);
+
+        base.set_var("XDG_DATA_HOME", "");
+        assert_eq!(get_sys_app_data_dir().unwrap(), home.join(".local/share"));
+
+        base.set_var("XDG_DATA_HOME", "relative/share");
+        assert_eq!(get_sys_app_data_dir().unwrap(), home.join(".local/share"));
+
+        let xdg = base.path().join("xdg");
+        base.set_var("XDG_DATA_HOME", &xdg);
+        assert_eq!(get_sys_app_data_dir().unwrap(), xdg);
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
+    #[test]
+    fn test_resolve_with_env_xdg() {
+        // Only the `./data` probe still reads process-wide state
+        let _guard = lock_env();
+        let app_data = AppData::new("test_app");
+        let mut env = HashMap::from([("HOME".to_string(), "/home/alice".to_string())]);
+        assert_eq!(
+            app_data.resolve_with_env(&env).unwrap(),
+            Path::new("/home/alice/.local/share/test_app")
+        );
+
+        env.insert("XDG_DATA_HOME".to_string(), "relative/share".to_string());
+        assert_eq!(
+            app_data.resolve_with_env(&env).unwrap(),
+            Path::new("/home/alice/.local/share/test_app")
+        );
+
+        env.insert("XDG_DATA_HOME".to_string(), "/xdg".to_string());
+        assert_eq!(
+            app_data.resolve_with_env(&env).unwrap(),
+            Path::new("/xdg/test_app")
+        );
+
+        env.insert("APPDATA_BASE_DIR".to_string(), "/srv/base".to_string());
+        assert_eq!(
+            AppData::with_version("test_app", "v2")
+                .resolve_with_env(&env)
+                .unwrap(),
+            Path::new("/srv/base/test_app/v2")
+        );
+
+        match app_data.resolve_with_env(&HashMap::new()) {
+            Err(AppDataError::EnvVarNotFound(_)) => {}
+            other => panic!("Expected EnvVarNotFound, got {:?}", other),
+        }
+        match AppData::new("").resolve_with_env(&env) {
+            Err(AppDataError::InvalidAppName(reason)) => assert_eq!(reason, "empty"),
+            other => panic!("Expected InvalidAppName, got {:?}", other),
+        }
+    }
+
+    #[cfg(any(target_os = "dragonfly", target_os = "illumos", target_os = "solaris"))]
+    #[test]
+    fn test_sys_app_data_dir_xdg_fallback() {
+        let mut base = SysBase::new("sys_app_data_dir_xdg_fallback");
+        let home = base.path().to_path_buf();
+        base.remove_var("XDG_DATA_HOME");
+        base.set_var("HOME", &home);
+        assert_eq!(get_sys_app_data_dir().unwrap(), home.join(".local/share"));
+    }
+
     #[test]
     fn test_get_file_path() {
+        let _guard = lock_env();
         let app_data = AppData::with_force_local("test_app", true);
         let result = app_data.get_file_path("test.txt");
         assert!(result.is_ok());
@@ -246,6 +5356,358 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_write_and_read_file() {
+        let base = SysBase::new("write_and_read_file");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("notes.txt", "hello").unwrap();
+        assert!(app_data.file_exists("notes.txt").unwrap());
+        assert_eq!(app_data.read_file("notes.txt").unwrap(), b"hello");
+        assert_eq!(app_data.read_to_string("notes.txt").unwrap(), "hello");
+        assert!(
+            app_data
+                .get_file_path("notes.txt")
+                .unwrap()
+                .starts_with(base.path())
+        );
+
+        app_data.delete_file("notes.txt").unwrap();
+        assert!(!app_data.file_exists("notes.txt").unwrap());
+        assert!(app_data.delete_file("notes.txt").is_err());
+    }
+
+    #[test]
+    fn test_read_to_string_strips_bom() {
+        let _base = SysBase::new("read_to_string_strips_bom");
+        let app_data = AppData::new("test_app");
+        app_data
+            .write_file("config.json", "\u{feff}{\"theme\": \"dark\"}")
+            .unwrap();
+        app_data
+            .write_file("plain.toml", "theme = \"dark\"")
+            .unwrap();
+        assert_eq!(
+            app_data.read_to_string("config.json").unwrap(),
+            "{\"theme\": \"dark\"}"
+        );
+        assert_eq!(
+            app_data.read_to_string("plain.toml").unwrap(),
+            "theme = \"dark\""
+        );
+        // Raw reads keep the bytes untouched
+        assert!(
+            app_data
+                .read_file("config.json")
+                .unwrap()
+                .starts_with(b"\xef\xbb\xbf")
+        );
+    }
+
+    #[test]
+    fn test_read_lines() {
+        let _base = SysBase::new("read_lines");
+        let app_data = AppData::new("test_app");
+        app_data
+            .write_file("events.log", "first\nsecond\r\nthird\n")
+            .unwrap();
+        let lines = app_data
+            .read_lines("events.log")
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(lines, vec!["first", "second", "third"]);
+        assert!(app_data.read_lines("missing.log").is_err());
+    }
+
+    #[test]
+    fn test_write_file_atomic() {
+        let _base = SysBase::new("write_file_atomic");
+        let app_data = AppData::new("test_app");
+        app_data.write_file_atomic("save.dat", "first").unwrap();
+        app_data.write_file_atomic("save.dat", "second").unwrap();
+        assert_eq!(app_data.read_to_string("save.dat").unwrap(), "second");
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        assert_eq!(fs::read_dir(data_dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_delete_files() {
+        let _base = SysBase::new("delete_files");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("a.sav", "a").unwrap();
+        app_data.ensure_parent("old/b.sav").unwrap();
+        app_data.write_file("old/b.sav", "b").unwrap();
+        app_data.write_file("keep.sav", "keep").unwrap();
+
+        let results = app_data
+            .delete_files(&["a.sav", "missing.sav", "../keep.sav", "old/b.sav"])
+            .unwrap();
+        let names: Vec<&str> = results.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["a.sav", "missing.sav", "../keep.sav", "old/b.sav"]);
+        assert!(results[0].1.is_ok());
+        assert!(matches!(results[1].1, Err(AppDataError::FileNotFound(_))));
+        assert!(matches!(results[2].1, Err(AppDataError::InvalidPath(_))));
+        assert!(results[3].1.is_ok());
+
+        assert!(!app_data.file_exists("a.sav").unwrap());
+        assert!(!app_data.file_exists("old/b.sav").unwrap());
+        assert!(app_data.file_exists("keep.sav").unwrap());
+
+        let read_only = app_data.clone().read_only(true);
+        assert!(matches!(
+            read_only.delete_files(&["keep.sav"]),
+            Err(AppDataError::ReadOnly(_))
+        ));
+    }
+
+    #[test]
+    fn test_write_batch() {
+        let _base = SysBase::new("write_batch");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("save.dat", "old").unwrap();
+        app_data
+            .write_batch(&[("save.dat", b"new"), ("save.idx", b"index")])
+            .unwrap();
+        assert_eq!(app_data.read_to_string("save.dat").unwrap(), "new");
+        assert_eq!(app_data.read_to_string("save.idx").unwrap(), "index");
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        assert_eq!(fs::read_dir(data_dir).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_ensure_data_dir_with_init() {
+        let _base = SysBase::new("ensure_data_dir_with_init");
+        let app_data = AppData::new("test_app");
+        let mut runs = 0;
+        let data_dir = app_data
+            .ensure_data_dir_with_init(|dir| {
+                runs += 1;
+                fs::write(dir.join("config.toml"), "theme = 1")?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(runs, 1);
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "theme = 1");
+
+        let again = app_data
+            .ensure_data_dir_with_init(|_| panic!("init ran for an existing directory"))
+            .unwrap();
+        assert_eq!(again, data_dir);
+
+        let failing = AppData::new("failing_app");
+        let result = failing.ensure_data_dir_with_init(|_| {
+            Err(AppDataError::IoError("seeding failed".to_string()))
+        });
+        assert!(matches!(result, Err(AppDataError::IoError(_))));
+        assert!(!failing.resolve_data_dir().unwrap().exists());
+    }
+
+    #[test]
+    fn test_ensure_data_dir_with_init_runs_once_concurrently() {
+        let _base = SysBase::new("ensure_data_dir_with_init_concurrent");
+        let app_data = AppData::new("test_app");
+        let runs = std::sync::atomic::AtomicUsize::new(0);
+        std::thread::scope(|scope| {
+            for _ in 0..16 {
+                scope.spawn(|| {
+                    app_data
+                        .ensure_data_dir_with_init(|_| {
+                            runs.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                            Ok(())
+                        })
+                        .unwrap();
+                });
+            }
+        });
+        assert_eq!(runs.into_inner(), 1);
+    }
+
+    #[test]
+    fn test_ensure_data_dir_concurrent() {
+        let _base = SysBase::new("ensure_data_dir_concurrent");
+        let app_data = AppData::with_version("test_app", "v1");
+        let dirs = std::thread::scope(|scope| {
+            let handles = (0..16)
+                .map(|_| scope.spawn(|| app_data.ensure_data_dir()))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap().unwrap())
+                .collect::<Vec<_>>()
+        });
+        assert!(dirs[0].is_dir());
+        assert!(dirs.iter().all(|dir| *dir == dirs[0]));
+    }
+
+    #[test]
+    fn test_create_dir_race_safe_file_in_the_way() {
+        let base = SysBase::new("create_dir_file_in_the_way");
+        let blocker = base.path().join("test_app");
+        fs::write(&blocker, "not a directory").unwrap();
+        match AppData::new("test_app").ensure_data_dir() {
+            Err(AppDataError::NotADirectory(path)) => assert_eq!(path, blocker),
+            other => panic!("Expected NotADirectory, got {:?}", other),
+        }
+        assert!(create_dir_race_safe(&blocker.join("nested")).is_err());
+    }
+
+    #[test]
+    fn test_write_batch_rolls_back() {
+        let _base = SysBase::new("write_batch_rolls_back");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("save.dat", "old").unwrap();
+        // A non-empty directory in the way makes the last rename fail
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        fs::create_dir_all(data_dir.join("blocker").join("inner")).unwrap();
+
+        let result = app_data.write_batch(&[
+            ("save.dat", b"new"),
+            ("save.idx", b"index"),
+            ("blocker", b"oops"),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(app_data.read_to_string("save.dat").unwrap(), "old");
+        assert!(!app_data.file_exists("save.idx").unwrap());
+        let mut names = fs::read_dir(&data_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["blocker", "save.dat"]);
+    }
+
+    #[test]
+    fn test_durable_writes() {
+        let _base = SysBase::new("durable_writes");
+        let app_data = AppData::new("test_app").durable(true);
+        assert!(app_data.durable);
+        app_data.write_file("plain.txt", "plain").unwrap();
+        app_data.write_file_atomic("atomic.txt", "atomic").unwrap();
+        app_data
+            .write_batch(&[("a.txt", b"a"), ("b.txt", b"b")])
+            .unwrap();
+        assert_eq!(app_data.read_to_string("plain.txt").unwrap(), "plain");
+        assert_eq!(app_data.read_to_string("atomic.txt").unwrap(), "atomic");
+        assert_eq!(app_data.read_to_string("a.txt").unwrap(), "a");
+        assert_eq!(app_data.read_to_string("b.txt").unwrap(), "b");
+    }
+
+    #[cfg(any(unix, target_os = "windows"))]
+    #[test]
+    fn test_clear_stale_locks() {
+        let _base = SysBase::new("clear_stale_locks");
+        let app_data = AppData::new("test_app");
+        app_data
+            .write_file("dead.lock", crate::test_util::exited_pid().to_string())
+            .unwrap();
+        app_data
+            .write_file("live.lock", process::id().to_string())
+            .unwrap();
+        app_data.write_file("other.lock", "not a pid").unwrap();
+
+        assert_eq!(app_data.clear_stale_locks().unwrap(), 1);
+        assert!(!app_data.file_exists("dead.lock").unwrap());
+        assert!(app_data.file_exists("live.lock").unwrap());
+        assert!(app_data.file_exists("other.lock").unwrap());
+        assert_eq!(app_data.clear_stale_locks().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_compact_jsonl() {
+        let _base = SysBase::new("compact_jsonl");
+        let app_data = AppData::new("test_app");
+        let mut log = String::new();
+        for i in 0..100 {
+            log.push_str(&format!("{{\"event\": {}}}\n", i));
+            if i % 10 == 0 {
+                log.push('\n');
+            }
+        }
+        app_data.write_file("events.jsonl", log).unwrap();
+
+        assert_eq!(app_data.compact_jsonl("events.jsonl", 3).unwrap(), 97);
+        assert_eq!(
+            app_data.read_to_string("events.jsonl").unwrap(),
+            "{\"event\": 97}\n{\"event\": 98}\n{\"event\": 99}\n"
+        );
+        assert_eq!(app_data.compact_jsonl("events.jsonl", 10).unwrap(), 0);
+        assert_eq!(app_data.compact_jsonl("events.jsonl", 0).unwrap(), 3);
+        assert_eq!(app_data.read_to_string("events.jsonl").unwrap(), "");
+    }
+
+    #[test]
+    fn test_check_writable() {
+        let _base = SysBase::new("check_writable");
+        let app_data = AppData::new("test_app");
+        app_data.check_writable().unwrap();
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        assert_eq!(fs::read_dir(&data_dir).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_installed_at() {
+        let _base = SysBase::new("installed_at");
+        let app_data = AppData::new("test_app");
+        assert!(matches!(
+            app_data.installed_at(),
+            Err(AppDataError::NotADirectory(_))
+        ));
+
+        let before = SystemTime::now() - std::time::Duration::from_secs(5);
+        app_data.ensure_data_dir().unwrap();
+        let installed = app_data.installed_at().unwrap();
+        assert!(installed >= before);
+        assert!(installed <= SystemTime::now());
+
+        // With a recorded creation time, later writes don't move the date forward
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        if fs::metadata(&data_dir).unwrap().created().is_ok() {
+            std::thread::sleep(std::time::Duration::from_millis(20));
+            app_data.write_file("a.txt", "a").unwrap();
+            assert_eq!(app_data.installed_at().unwrap(), installed);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_effective_dir_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _base = SysBase::new("effective_dir_mode");
+        let app_data = AppData::new("test_app");
+        assert_eq!(app_data.effective_dir_mode(), None);
+
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        let mode = app_data.effective_dir_mode().unwrap();
+        // Created as 0o777 minus the umask, the owner always keeps full access in practice
+        assert_eq!(mode & 0o700, 0o700);
+        assert_eq!(mode & !0o777, 0);
+
+        fs::set_permissions(&data_dir, fs::Permissions::from_mode(0o750)).unwrap();
+        assert_eq!(app_data.effective_dir_mode(), Some(0o750));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_writable_read_only_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _base = SysBase::new("check_writable_read_only");
+        let app_data = AppData::new("test_app");
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        fs::set_permissions(&data_dir, fs::Permissions::from_mode(0o555)).unwrap();
+        // Privileged users bypass permission bits, nothing to assert then
+        if fs::write(data_dir.join("root_check"), "").is_err() {
+            match app_data.check_writable() {
+                Err(AppDataError::PermissionDenied(path)) => {
+                    assert_eq!(path, data_dir.display().to_string())
+                }
+                other => panic!("Expected PermissionDenied, got {:?}", other),
+            }
+        }
+        fs::set_permissions(&data_dir, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
     #[test]
     fn test_app_data_error_display() {
         let error = AppDataError::EnvVarNotFound("TEST_VAR".to_string());
@@ -0,0 +1,175 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{AppData, AppDataError};
+
+/// A buffered, append-only writer for a file in the data directory, created by
+/// [`AppData::appender`]
+///
+/// The file is opened on the first write and then kept open, so frequent small appends such as
+/// log lines don't pay for an open and close each time. Buffered data is written out by
+/// [`flush`](Write::flush) and, on a best-effort basis, when the appender is dropped.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 数据目录中文件的带缓冲追加写入器，由 [`AppData::appender`] 创建。
+/// 文件在首次写入时打开并保持打开，频繁的小量追加（如日志行）无需每次打开和关闭文件。
+/// 缓冲的数据在调用 `flush` 时写出，丢弃时也会尽力写出
+/// </details>
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::io::Write;
+/// use app_data::AppData;
+///
+/// let mut log = AppData::new("my_app").appender("events.log").unwrap();
+/// writeln!(log, "started").unwrap();
+/// log.flush().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct FileAppender {
+    path: PathBuf,
+    durable: bool,
+    writer: Option<BufWriter<File>>,
+    /// The owning instance when auditing is enabled, to record the first open
+    audit: Option<AppData>,
+    /// The owning instance when mirroring is enabled, to copy the file on every flush
+    mirror: Option<AppData>,
+}
+
+impl FileAppender {
+    /// The file this appender writes to
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 写入器写入的文件路径
+    /// </details>
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn writer(&mut self) -> io::Result<&mut BufWriter<File>> {
+        match self.writer {
+            Some(ref mut writer) => Ok(writer),
+            None => {
+                let file = OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(&self.path)?;
+                if let Some(app_data) = &self.audit {
+                    app_data
+                        .audit_event("append", &self.path)
+                        .map_err(|err| io::Error::other(err.to_string()))?;
+                }
+                Ok(self.writer.insert(BufWriter::new(file)))
+            }
+        }
+    }
+}
+
+impl Write for FileAppender {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer()?.write(buf)
+    }
+
+    /// Write out buffered data, and with [`AppData::durable`] sync it to disk
+    fn flush(&mut self) -> io::Result<()> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        writer.flush()?;
+        if self.durable {
+            writer.get_ref().sync_data()?;
+        }
+        if let Some(app_data) = &self.mirror {
+            app_data
+                .mirror_write(&self.path)
+                .map_err(|err| io::Error::other(err.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for FileAppender {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+impl AppData {
+    /// Open a buffered append-only writer for a file in the data directory
+    ///
+    /// The file is created on the first write if it doesn't exist. Prefer this over repeated
+    /// whole-file writes for high-frequency appends such as logs.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 为数据目录中的文件打开带缓冲的追加写入器。文件不存在时在首次写入时创建。
+    /// 对于日志等高频追加，应优先使用它而不是反复写入整个文件
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::io::Write;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let mut log = app_data.appender("events.log").unwrap();
+    /// for i in 0..3 {
+    ///     writeln!(log, "event {}", i).unwrap();
+    /// }
+    /// ```
+    pub fn appender(&self, file_name: &str) -> Result<FileAppender, AppDataError> {
+        self.deny_custom_file_system("appender")?;
+        self.deny_read_only(file_name)?;
+        Ok(FileAppender {
+            path: self.get_file_path(file_name)?,
+            durable: self.durable,
+            writer: None,
+            audit: self.audit.then(|| self.clone()),
+            mirror: self.mirror_dir.is_some().then(|| self.clone()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_appender_many_lines() {
+        let _base = SysBase::new("appender");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("events.log", "existing\n").unwrap();
+
+        let mut log = app_data.appender("events.log").unwrap();
+        for i in 0..10_000 {
+            writeln!(log, "line {}", i).unwrap();
+        }
+        log.flush().unwrap();
+
+        let text = app_data.read_to_string("events.log").unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 10_001);
+        assert_eq!(lines[0], "existing");
+        assert_eq!(lines[10_000], "line 9999");
+    }
+
+    #[test]
+    fn test_appender_lazy_open_and_flush_on_drop() {
+        let _base = SysBase::new("appender_lazy");
+        let app_data = AppData::new("test_app");
+        let log = app_data.appender("events.log").unwrap();
+        assert!(!log.path().exists());
+        drop(log);
+        assert!(!app_data.file_exists("events.log").unwrap());
+
+        let mut log = app_data.appender("events.log").unwrap();
+        log.write_all(b"unflushed").unwrap();
+        drop(log);
+        assert_eq!(app_data.read_to_string("events.log").unwrap(), "unflushed");
+    }
+}
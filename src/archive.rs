@@ -0,0 +1,558 @@
+//! Packing the whole data directory into a single archive file and back
+//!
+//! The archive is a plain POSIX ustar file named after the data directory, e.g.
+//! `~/.local/share/my_app.tar`, so any `tar` tool can open it. File contents are stored as is,
+//! without compression.
+
+use std::{
+    env::var,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{
+    AppData, AppDataError,
+    app_data::{checked_relative, sibling_path, sync_parent},
+};
+
+/// Size of a tar header and of the blocks contents are padded to
+const BLOCK: usize = 512;
+
+/// Write `value` as a NUL-terminated octal number filling `field`
+fn put_octal(field: &mut [u8], value: u64) -> io::Result<()> {
+    let width = field.len() - 1;
+    let digits = format!("{:0width$o}", value, width = width);
+    if digits.len() > width {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} too large for the archive", value),
+        ));
+    }
+    field[..width].copy_from_slice(digits.as_bytes());
+    Ok(())
+}
+
+/// Read the octal number in `field`, which may be padded with spaces or NULs
+fn get_octal(field: &[u8]) -> io::Result<u64> {
+    let text = String::from_utf8_lossy(field);
+    let text = text.trim_matches(|c: char| c == '\0' || c == ' ');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| corrupt("invalid number in header"))
+}
+
+fn corrupt(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("corrupt archive: {}", reason),
+    )
+}
+
+/// ustar entry types this crate writes
+const REGULAR: u8 = b'0';
+const SYMLINK: u8 = b'2';
+const DIRECTORY: u8 = b'5';
+
+fn unsupported(reason: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, reason)
+}
+
+/// The ustar header for an entry of type `kind` named `name`. `link` is the target of a
+/// symlink and empty otherwise
+fn header(name: &str, kind: u8, size: u64, mtime: u64, link: &str) -> io::Result<[u8; BLOCK]> {
+    let mut header = [0u8; BLOCK];
+    // Names longer than the name field are split at a `/` into the prefix field
+    let (prefix, name) = if name.len() <= 100 {
+        ("", name)
+    } else {
+        name.char_indices()
+            .filter(|(index, c)| *c == '/' && *index <= 155 && name.len() - index - 1 <= 100)
+            .map(|(index, _)| (&name[..index], &name[index + 1..]))
+            .next()
+            .ok_or_else(|| unsupported(format!("name too long for the archive: {}", name)))?
+    };
+    if link.len() > 100 {
+        return Err(unsupported(format!(
+            "symlink target too long for the archive: {}",
+            link
+        )));
+    }
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    let mode = match kind {
+        DIRECTORY => 0o755,
+        SYMLINK => 0o777,
+        _ => 0o644,
+    };
+    put_octal(&mut header[100..108], mode)?;
+    put_octal(&mut header[108..116], 0)?;
+    put_octal(&mut header[116..124], 0)?;
+    put_octal(&mut header[124..136], size)?;
+    put_octal(&mut header[136..148], mtime)?;
+    header[156] = kind;
+    header[157..157 + link.len()].copy_from_slice(link.as_bytes());
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+    header[345..345 + prefix.len()].copy_from_slice(prefix.as_bytes());
+    // The checksum is computed with its own field filled with spaces
+    header[148..156].fill(b' ');
+    let checksum: u64 = header.iter().map(|byte| u64::from(*byte)).sum();
+    put_octal(&mut header[148..155], checksum)?;
+    Ok(header)
+}
+
+/// Modification time of `metadata` in seconds since the Unix epoch
+fn mtime(metadata: &fs::Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |age| age.as_secs())
+}
+
+/// Append every entry below `dir` to `out`, with names relative to `root`. Entries the archive
+/// can't represent, such as FIFOs or sockets, fail rather than being left out
+fn write_tree(out: &mut impl Write, root: &Path, dir: &Path) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = path
+            .strip_prefix(root)
+            .ok()
+            .and_then(Path::to_str)
+            .ok_or_else(|| unsupported(format!("name not valid UTF-8: {}", path.display())))?
+            .replace('\\', "/");
+        let file_type = entry.file_type()?;
+        let metadata = entry.metadata()?;
+        if file_type.is_symlink() {
+            let target = fs::read_link(&path)?;
+            let target = target.to_str().ok_or_else(|| {
+                unsupported(format!(
+                    "symlink target not valid UTF-8: {}",
+                    path.display()
+                ))
+            })?;
+            out.write_all(&header(&name, SYMLINK, 0, mtime(&metadata), target)?)?;
+        } else if file_type.is_dir() {
+            let name = format!("{}/", name);
+            out.write_all(&header(&name, DIRECTORY, 0, mtime(&metadata), "")?)?;
+            write_tree(out, root, &path)?;
+        } else if file_type.is_file() {
+            out.write_all(&header(
+                &name,
+                REGULAR,
+                metadata.len(),
+                mtime(&metadata),
+                "",
+            )?)?;
+            let copied = io::copy(&mut fs::File::open(&path)?, out)?;
+            if copied != metadata.len() {
+                return Err(io::Error::other(format!(
+                    "{} changed while archiving",
+                    path.display()
+                )));
+            }
+            let padding = (BLOCK - copied as usize % BLOCK) % BLOCK;
+            out.write_all(&[0; BLOCK][..padding])?;
+        } else {
+            return Err(unsupported(format!(
+                "can't archive special file {}",
+                path.display()
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Create the symlink `path` pointing at `target`
+#[cfg(unix)]
+fn create_symlink(target: &Path, path: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+/// Create the symlink `path` pointing at `target`, which Windows needs to know is a directory
+#[cfg(target_os = "windows")]
+fn create_symlink(target: &Path, path: &Path) -> io::Result<()> {
+    let resolved = path
+        .parent()
+        .map_or(target.to_path_buf(), |dir| dir.join(target));
+    if resolved.is_dir() {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+/// Symlinks can't be created on this target
+#[cfg(not(any(unix, target_os = "windows")))]
+fn create_symlink(_target: &Path, path: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("can't create symlink {}", path.display()),
+    ))
+}
+
+/// Extract the archive read from `input` into the existing directory `dest`
+fn read_tree(input: &mut impl Read, dest: &Path) -> Result<(), AppDataError> {
+    let mut header = [0u8; BLOCK];
+    // Symlinks are created last, so no later entry can be extracted through one
+    let mut symlinks = Vec::new();
+    loop {
+        input
+            .read_exact(&mut header)
+            .map_err(|_| corrupt("truncated"))?;
+        if header.iter().all(|byte| *byte == 0) {
+            break;
+        }
+        let stored = get_octal(&header[148..156])?;
+        let mut unsummed = header;
+        unsummed[148..156].fill(b' ');
+        if unsummed.iter().map(|byte| u64::from(*byte)).sum::<u64>() != stored {
+            return Err(corrupt("header checksum mismatch").into());
+        }
+        let field = |range: std::ops::Range<usize>| {
+            let bytes = &header[range];
+            let end = bytes
+                .iter()
+                .position(|byte| *byte == 0)
+                .unwrap_or(bytes.len());
+            String::from_utf8_lossy(&bytes[..end]).into_owned()
+        };
+        let (prefix, name) = (field(345..500), field(0..100));
+        let name = if prefix.is_empty() {
+            name
+        } else {
+            format!("{}/{}", prefix, name)
+        };
+        let size = get_octal(&header[124..136])?;
+        let target = dest.join(checked_relative(name.trim_end_matches('/'))?);
+        match header[156] {
+            DIRECTORY => fs::create_dir_all(&target)?,
+            SYMLINK => symlinks.push((PathBuf::from(field(157..257)), target)),
+            REGULAR | 0 => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let mut file = fs::File::create(&target)?;
+                let copied = io::copy(&mut input.by_ref().take(size), &mut file)?;
+                if copied != size {
+                    return Err(corrupt("truncated").into());
+                }
+                let modified = UNIX_EPOCH + Duration::from_secs(get_octal(&header[136..148])?);
+                file.set_modified(modified)?;
+            }
+            _ => return Err(corrupt("unsupported entry type").into()),
+        }
+        // Contents are padded to whole blocks, and only a regular file's contents were read
+        let consumed = if matches!(header[156], REGULAR | 0) {
+            size
+        } else {
+            0
+        };
+        let skip = size.next_multiple_of(BLOCK as u64) - consumed;
+        io::copy(&mut input.by_ref().take(skip), &mut io::sink())?;
+    }
+    for (link, path) in symlinks {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        create_symlink(&link, &path)?;
+    }
+    Ok(())
+}
+
+impl AppData {
+    /// Path of the single-file archive written by [`deflate`](AppData::deflate): the data
+    /// directory's path with `.tar` appended
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// `deflate` 写入的单文件归档路径：数据目录路径加上 `.tar`
+    /// </details>
+    pub fn archive_path(&self) -> Result<PathBuf, AppDataError> {
+        let mut archive = self.data_dir_path()?.into_os_string();
+        archive.push(".tar");
+        Ok(PathBuf::from(archive))
+    }
+
+    /// Pack the whole data directory into the single file [`archive_path`](AppData::archive_path)
+    /// and remove the loose files, e.g. on shutdown, returning the archive's path
+    ///
+    /// The archive is a plain ustar file, so the data keeps a single-file footprint that is
+    /// easy to sync or back up and opens with any `tar` tool. Contents are stored without
+    /// compression, symlinks as links. Special files such as FIFOs or sockets can't be archived
+    /// and fail with [`AppDataError::IoError`] before anything is removed. The archive is
+    /// written under a temporary name and renamed into place before anything is removed, so a
+    /// crash never loses data. Call [`inflate`](AppData::inflate) on the next start.
+    ///
+    /// A local `./data` directory that is only used because it exists fails with
+    /// [`AppDataError::InvalidPath`]: once removed, the next start would resolve to the system
+    /// directory and miss the archive. Use [`force_local`](AppData::with_force_local) instead.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将整个数据目录打包为单个文件 `archive_path` 并删除散落的文件（例如在退出时），返回归档路径。
+    /// 归档是普通的 ustar 文件，数据因此只占用单个文件，便于同步或备份，并可用任何 `tar` 工具打开。内容不经压缩直接存储，符号链接按链接存储。
+    /// FIFO、套接字等特殊文件无法归档，会在删除任何文件之前返回 [`AppDataError::IoError`]。
+    /// 归档先以临时名称写入，重命名到位后才删除任何文件，因此崩溃不会丢失数据。下次启动时调用 `inflate`。
+    /// 仅因存在而被使用的本地 `./data` 目录会返回 [`AppDataError::InvalidPath`]：删除后下次启动会解析到系统目录而找不到归档。请改用 `force_local`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data.inflate().unwrap();
+    /// // ... run the app ...
+    /// app_data.deflate().unwrap();
+    /// ```
+    pub fn deflate(&self) -> Result<PathBuf, AppDataError> {
+        self.deny_custom_file_system("deflate")?;
+        self.deny_read_only("the data directory")?;
+        if !self.force_local
+            && let Some(local) = self.local_app_dir(&|key| var(key).ok())?
+        {
+            return Err(AppDataError::InvalidPath(local.display().to_string()));
+        }
+        let data_dir = self.ensure_data_dir()?;
+        let archive = self.archive_path()?;
+        let staged = sibling_path(&archive, "tmp");
+        let written = (|| {
+            let mut out = io::BufWriter::new(fs::File::create(&staged)?);
+            write_tree(&mut out, &data_dir, &data_dir)?;
+            out.write_all(&[0; 2 * BLOCK])?;
+            let file = out.into_inner().map_err(io::IntoInnerError::into_error)?;
+            if self.durable {
+                file.sync_all()?;
+            }
+            drop(file);
+            fs::rename(&staged, &archive)
+        })();
+        if let Err(err) = written {
+            let _ = fs::remove_file(&staged);
+            return Err(err.into());
+        }
+        if self.durable {
+            sync_parent(&archive)?;
+        }
+        fs::remove_dir_all(&data_dir)?;
+        Ok(archive)
+    }
+
+    /// Unpack the archive written by [`deflate`](AppData::deflate) back into the data
+    /// directory and remove it, returning whether there was an archive
+    ///
+    /// The archive is extracted next to the data directory first and only moved into place
+    /// once complete. A data directory that already holds files fails with
+    /// [`AppDataError::AlreadyExists`] rather than mixing two versions, and a damaged archive
+    /// with [`AppDataError::IoError`], leaving the archive in place.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将 `deflate` 写入的归档解包回数据目录并删除归档，返回是否存在归档。
+    /// 归档先解压到数据目录旁边，完成后才移动到位。数据目录中已有文件时返回 [`AppDataError::AlreadyExists`]，而不是混合两个版本；
+    /// 归档损坏时返回 [`AppDataError::IoError`]，归档保持不变
+    /// </details>
+    pub fn inflate(&self) -> Result<bool, AppDataError> {
+        self.deny_custom_file_system("inflate")?;
+        self.deny_read_only("the data directory")?;
+        let archive = self.archive_path()?;
+        if !archive.is_file() {
+            return Ok(false);
+        }
+        let data_dir = self.data_dir_path()?;
+        if fs::read_dir(&data_dir).is_ok_and(|mut entries| entries.next().is_some()) {
+            return Err(AppDataError::AlreadyExists(data_dir));
+        }
+        let staged = sibling_path(&data_dir, "inflate");
+        let _ = fs::remove_dir_all(&staged);
+        fs::create_dir_all(&staged)?;
+        let extracted = fs::File::open(&archive)
+            .map_err(AppDataError::from)
+            .and_then(|file| read_tree(&mut io::BufReader::new(file), &staged));
+        if let Err(err) = extracted {
+            let _ = fs::remove_dir_all(&staged);
+            return Err(err);
+        }
+        // An empty data directory may have been created in the meantime
+        let _ = fs::remove_dir(&data_dir);
+        fs::rename(&staged, &data_dir)?;
+        fs::remove_file(&archive)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_deflate_inflate_round_trip() {
+        let base = SysBase::new("deflate_inflate");
+        let app_data = AppData::new("test_app");
+        let long_name = format!("{}/{}.txt", "nested".repeat(12), "n".repeat(60));
+        let files: Vec<(String, Vec<u8>)> = vec![
+            ("config.toml".to_string(), b"a = 1".to_vec()),
+            ("saves/slot-1.sav".to_string(), vec![7; 513]),
+            ("saves/old/slot-0.sav".to_string(), vec![0; 512]),
+            ("empty.txt".to_string(), Vec::new()),
+            (long_name, b"deep".to_vec()),
+        ];
+        for (name, contents) in &files {
+            app_data.ensure_parent(name).unwrap();
+            app_data.write_file(name, contents).unwrap();
+        }
+        fs::create_dir(app_data.get_file_path("empty_dir").unwrap()).unwrap();
+        let modified = fs::metadata(app_data.get_file_path("config.toml").unwrap())
+            .unwrap()
+            .modified()
+            .unwrap();
+
+        let archive = app_data.deflate().unwrap();
+        assert_eq!(archive, base.path().join("test_app.tar"));
+        assert!(!base.path().join("test_app").exists());
+        assert_eq!(fs::read_dir(base.path()).unwrap().count(), 1);
+
+        assert!(app_data.inflate().unwrap());
+        assert!(!archive.exists());
+        for (name, contents) in &files {
+            assert_eq!(&app_data.read_file(name).unwrap(), contents);
+        }
+        assert_eq!(app_data.file_count(true).unwrap(), files.len());
+        assert!(app_data.get_file_path("empty_dir").unwrap().is_dir());
+        let restored = fs::metadata(app_data.get_file_path("config.toml").unwrap())
+            .unwrap()
+            .modified()
+            .unwrap();
+        let drift = modified.duration_since(restored).unwrap_or_default();
+        assert!(drift < Duration::from_secs(1));
+
+        assert!(!app_data.inflate().unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_deflate_inflate_symlinks() {
+        let base = SysBase::new("deflate_symlinks");
+        let app_data = AppData::new("test_app");
+        app_data.ensure_parent("saves/slot-1.sav").unwrap();
+        app_data.write_file("saves/slot-1.sav", "save").unwrap();
+        let data_dir = base.path().join("test_app");
+        std::os::unix::fs::symlink("saves/slot-1.sav", data_dir.join("latest.sav")).unwrap();
+        std::os::unix::fs::symlink("saves", data_dir.join("current")).unwrap();
+        std::os::unix::fs::symlink("missing", data_dir.join("dangling")).unwrap();
+
+        app_data.deflate().unwrap();
+        assert!(!data_dir.exists());
+        assert!(app_data.inflate().unwrap());
+        for (link, target) in [
+            ("latest.sav", "saves/slot-1.sav"),
+            ("current", "saves"),
+            ("dangling", "missing"),
+        ] {
+            let path = data_dir.join(link);
+            assert!(fs::symlink_metadata(&path).unwrap().is_symlink());
+            assert_eq!(fs::read_link(&path).unwrap(), Path::new(target));
+        }
+        assert_eq!(app_data.read_to_string("latest.sav").unwrap(), "save");
+        assert_eq!(
+            app_data.read_to_string("current/slot-1.sav").unwrap(),
+            "save"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_deflate_refuses_special_files() {
+        let base = SysBase::new("deflate_special");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("a.txt", "kept").unwrap();
+        let fifo = base.path().join("test_app/pipe");
+        let made = std::process::Command::new("mkfifo").arg(&fifo).status();
+        if !made.is_ok_and(|status| status.success()) {
+            return;
+        }
+
+        assert!(matches!(app_data.deflate(), Err(AppDataError::IoError(_))));
+        assert_eq!(app_data.read_to_string("a.txt").unwrap(), "kept");
+        assert!(fs::symlink_metadata(&fifo).is_ok());
+        assert!(!app_data.archive_path().unwrap().exists());
+        assert_eq!(fs::read_dir(base.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_deflate_refuses_probed_local_dir() {
+        let _base = SysBase::new("deflate_local");
+        let local = std::env::current_dir().unwrap().join("data");
+        if local.exists() {
+            // A real local `./data` would decide resolution on its own
+            return;
+        }
+        fs::create_dir(&local).unwrap();
+        fs::write(local.join("settings.txt"), "x").unwrap();
+
+        let probed = AppData::new("test_app").deflate();
+        let still_there = local.join("settings.txt").is_file();
+        let forced = AppData::with_force_local("test_app", true).deflate();
+        let local_archive = local.with_extension("tar");
+        let restored = AppData::with_force_local("test_app", true).inflate();
+        let restored_file = fs::read_to_string(local.join("settings.txt"));
+
+        // 清理
+        let _ = fs::remove_dir_all(&local);
+        let _ = fs::remove_file(&local_archive);
+        match probed {
+            Err(AppDataError::InvalidPath(path)) => assert_eq!(path, local.display().to_string()),
+            other => panic!("Expected InvalidPath, got {:?}", other),
+        }
+        assert!(still_there);
+        assert_eq!(forced.unwrap(), local_archive);
+        assert!(restored.unwrap());
+        assert_eq!(restored_file.unwrap(), "x");
+    }
+
+    #[test]
+    fn test_inflate_refuses_populated_dir_and_corrupt_archive() {
+        let base = SysBase::new("inflate_refuses");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("a.txt", "archived").unwrap();
+        app_data.deflate().unwrap();
+        app_data.write_file("a.txt", "new").unwrap();
+
+        assert!(matches!(
+            app_data.inflate(),
+            Err(AppDataError::AlreadyExists(_))
+        ));
+        assert_eq!(app_data.read_to_string("a.txt").unwrap(), "new");
+
+        fs::remove_dir_all(base.path().join("test_app")).unwrap();
+        let archive = app_data.archive_path().unwrap();
+        let mut bytes = fs::read(&archive).unwrap();
+        bytes[0] ^= 1;
+        fs::write(&archive, bytes).unwrap();
+        assert!(matches!(app_data.inflate(), Err(AppDataError::IoError(_))));
+        assert!(archive.exists());
+        assert!(!base.path().join("test_app").exists());
+    }
+
+    #[test]
+    fn test_inflate_rejects_escaping_names() {
+        let base = SysBase::new("inflate_escaping");
+        let app_data = AppData::new("test_app");
+        let mut archive = header("../escaped.txt", REGULAR, 1, 0, "")
+            .unwrap()
+            .to_vec();
+        archive.extend_from_slice(b"x");
+        archive.resize(archive.len() + BLOCK - 1 + 2 * BLOCK, 0);
+        fs::write(app_data.archive_path().unwrap(), archive).unwrap();
+
+        assert!(matches!(
+            app_data.inflate(),
+            Err(AppDataError::InvalidPath(_))
+        ));
+        assert!(!base.path().join("escaped.txt").exists());
+    }
+}
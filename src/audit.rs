@@ -0,0 +1,109 @@
+//! Audit trail of the directories created and files written by the crate
+
+use std::{
+    fs::OpenOptions,
+    io::Write,
+    path::Path,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use crate::{AppData, AppDataError};
+
+/// Audit log kept in the data directory. It is appended to directly, so it is never audited itself
+pub(crate) const AUDIT_LOG: &str = ".appdata-audit.log";
+
+impl AppData {
+    /// Append `<unix millis>\t<action>\t<path>` to the audit log when auditing is enabled
+    pub(crate) fn audit_event(&self, action: &str, path: &Path) -> Result<(), AppDataError> {
+        if !self.audit {
+            return Ok(());
+        }
+        self.audit_event_in(&self.resolve_data_dir()?, action, path)
+    }
+
+    /// [`audit_event`](Self::audit_event) for an already resolved `data_dir`
+    pub(crate) fn audit_event_in(
+        &self,
+        data_dir: &Path,
+        action: &str,
+        path: &Path,
+    ) -> Result<(), AppDataError> {
+        if !self.audit {
+            return Ok(());
+        }
+        let millis = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_millis();
+        let log = data_dir.join(AUDIT_LOG);
+        let mut file = OpenOptions::new().create(true).append(true).open(log)?;
+        // One write per entry, so concurrent writers don't interleave within a line
+        file.write_all(format!("{}\t{}\t{}\n", millis, action, path.display()).as_bytes())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+    use std::{io::Write, sync::Arc};
+
+    use crate::FakeClock;
+
+    fn entries(app_data: &AppData) -> Vec<Vec<String>> {
+        app_data
+            .read_to_string(AUDIT_LOG)
+            .unwrap()
+            .lines()
+            .map(|line| line.split('\t').map(str::to_string).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_audit_write() {
+        let _base = SysBase::new("audit");
+        let clock = Arc::new(FakeClock::new(UNIX_EPOCH + Duration::from_millis(1234)));
+        let app_data = AppData::new("test_app").audit(true).with_clock(clock);
+        app_data.write_file("settings.txt", "x").unwrap();
+        let data_dir = app_data.ensure_data_dir().unwrap();
+
+        let logged = entries(&app_data);
+        assert_eq!(logged.len(), 2);
+        assert_eq!(
+            logged[0],
+            ["1234", "create_dir", &data_dir.display().to_string()]
+        );
+        assert_eq!(
+            logged[1],
+            [
+                "1234",
+                "write",
+                &data_dir.join("settings.txt").display().to_string()
+            ]
+        );
+
+        app_data.write_file_atomic("save.dat", "y").unwrap();
+        app_data.ensure_parent("a/b.txt").unwrap();
+        let mut log = app_data.appender("events.log").unwrap();
+        writeln!(log, "event").unwrap();
+        let actions: Vec<String> = entries(&app_data)
+            .into_iter()
+            .map(|entry| entry[1].clone())
+            .collect();
+        assert_eq!(
+            actions,
+            ["create_dir", "write", "write", "create_dir", "append"]
+        );
+    }
+
+    #[test]
+    fn test_audit_off_by_default() {
+        let _base = SysBase::new("audit_off");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("settings.txt", "x").unwrap();
+        assert!(!app_data.file_exists(AUDIT_LOG).unwrap());
+    }
+}
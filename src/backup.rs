@@ -0,0 +1,207 @@
+//! Incremental backups of the whole data directory
+//!
+//! Every backup is a directory `<dest>/<millis>` holding a full copy of the data directory,
+//! where `<millis>` is the time it was taken in milliseconds since the Unix epoch. Files that
+//! didn't change since the previous backup are hard links to it, so each backup only costs the
+//! space of what changed.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::{AppData, AppDataError, app_data::walk_files};
+
+/// The most recent complete backup in `dest`
+fn latest_backup(dest: &Path) -> io::Result<Option<PathBuf>> {
+    if !dest.is_dir() {
+        return Ok(None);
+    }
+    let mut latest: Option<(u128, PathBuf)> = None;
+    for entry in fs::read_dir(dest)? {
+        let entry = entry?;
+        let taken_at = entry
+            .file_name()
+            .to_str()
+            .and_then(|name| name.parse::<u128>().ok());
+        if let Some(taken_at) = taken_at.filter(|_| entry.path().is_dir())
+            && latest.as_ref().is_none_or(|(latest, _)| taken_at > *latest)
+        {
+            latest = Some((taken_at, entry.path()));
+        }
+    }
+    Ok(latest.map(|(_, path)| path))
+}
+
+/// Whether `previous` has the same size and modification time as `source`
+fn is_unchanged(source: &fs::Metadata, previous: &Path) -> bool {
+    let Ok(previous) = fs::symlink_metadata(previous) else {
+        return false;
+    };
+    previous.is_file()
+        && previous.len() == source.len()
+        && previous.modified().ok() == source.modified().ok()
+}
+
+/// Copy `source` to `target` and give the copy the modification time of `source`, so the next
+/// backup can tell whether it changed
+fn copy_with_mtime(source: &Path, target: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+    fs::copy(source, target)?;
+    fs::File::options()
+        .write(true)
+        .open(target)?
+        .set_modified(metadata.modified()?)
+}
+
+impl AppData {
+    /// Back up the whole data directory into a new timestamped directory in `dest`, returning
+    /// its path
+    ///
+    /// Files unchanged since the previous backup in `dest`, judged by size and modification
+    /// time, are hard-linked to it like `rsync --link-dest` does, and changed files are copied.
+    /// Where hard links can't be created, e.g. on filesystems without them, the file is copied
+    /// instead. The backup is assembled under a temporary name and only renamed into place once
+    /// complete, so an interrupted backup is never used as base. `dest` must not lie inside the
+    /// data directory.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将整个数据目录备份到 `dest` 中新的带时间戳目录，并返回其路径。
+    /// 与 `dest` 中上一次备份相比未变化的文件（按大小和修改时间判断）会像 `rsync --link-dest` 一样硬链接到上一次备份，变化的文件则重新复制。
+    /// 无法创建硬链接时（例如文件系统不支持）改为复制。备份先以临时名称组装，完成后才重命名到位，因此中断的备份不会被用作基准。
+    /// `dest` 不能位于数据目录内
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::path::Path;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let backup = app_data.backup_incremental(Path::new("/mnt/backup/my_app")).unwrap();
+    /// println!("backed up to {}", backup.display());
+    /// ```
+    pub fn backup_incremental(&self, dest: &Path) -> Result<PathBuf, AppDataError> {
+        self.deny_custom_file_system("backup_incremental")?;
+        let data_dir = self.ensure_data_dir()?;
+        if self.contains(dest)? {
+            return Err(AppDataError::InvalidPath(dest.display().to_string()));
+        }
+        let previous = latest_backup(dest)?;
+        let mut millis = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // Keep backups taken within the same millisecond apart
+        while dest.join(millis.to_string()).exists() {
+            millis += 1;
+        }
+        let backup = dest.join(millis.to_string());
+        let partial = dest.join(format!("{}.partial", millis));
+        fs::create_dir_all(&partial)?;
+
+        let copied = walk_files(&data_dir).and_then(|files| {
+            for source in files {
+                let relative = source.strip_prefix(&data_dir).unwrap_or(&source);
+                let target = partial.join(relative);
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                let metadata = fs::metadata(&source)?;
+                let linked = previous
+                    .as_ref()
+                    .map(|previous| previous.join(relative))
+                    .filter(|previous| is_unchanged(&metadata, previous))
+                    .is_some_and(|previous| fs::hard_link(previous, &target).is_ok());
+                if !linked {
+                    copy_with_mtime(&source, &target, &metadata)?;
+                }
+            }
+            fs::rename(&partial, &backup)
+        });
+        if let Err(err) = copied {
+            let _ = fs::remove_dir_all(&partial);
+            return Err(err.into());
+        }
+        Ok(backup)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FakeClock, test_util::SysBase};
+    use std::{
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    #[cfg(unix)]
+    #[test]
+    fn test_backup_incremental() {
+        use std::os::unix::fs::MetadataExt;
+
+        let base = SysBase::new("backup_incremental");
+        let dest = base.path().join("backups");
+        let clock = Arc::new(FakeClock::new(UNIX_EPOCH + Duration::from_millis(1000)));
+        let app_data = AppData::new("test_app").with_clock(clock.clone());
+        app_data.write_file("big.bin", [7; 4096]).unwrap();
+        app_data.ensure_parent("saves/slot.sav").unwrap();
+        app_data.write_file("saves/slot.sav", "v1").unwrap();
+
+        let first = app_data.backup_incremental(&dest).unwrap();
+        assert_eq!(first, dest.join("1000"));
+        assert_eq!(
+            fs::read_to_string(first.join("saves/slot.sav")).unwrap(),
+            "v1"
+        );
+        assert_eq!(fs::metadata(first.join("big.bin")).unwrap().nlink(), 1);
+
+        app_data.write_file("saves/slot.sav", "v2").unwrap();
+        let slot = app_data.get_file_path("saves/slot.sav").unwrap();
+        // Make the change visible even on filesystems with coarse timestamps
+        fs::File::options()
+            .write(true)
+            .open(&slot)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(10))
+            .unwrap();
+        clock.advance(Duration::from_secs(1));
+        let second = app_data.backup_incremental(&dest).unwrap();
+        assert_eq!(second, dest.join("2000"));
+
+        assert_eq!(fs::metadata(second.join("big.bin")).unwrap().nlink(), 2);
+        assert_eq!(
+            fs::metadata(second.join("big.bin")).unwrap().ino(),
+            fs::metadata(first.join("big.bin")).unwrap().ino()
+        );
+        assert_eq!(
+            fs::metadata(second.join("saves/slot.sav")).unwrap().nlink(),
+            1
+        );
+        assert_eq!(
+            fs::read_to_string(second.join("saves/slot.sav")).unwrap(),
+            "v2"
+        );
+        assert_eq!(
+            fs::read_to_string(first.join("saves/slot.sav")).unwrap(),
+            "v1"
+        );
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn test_backup_incremental_inside_data_dir() {
+        let _base = SysBase::new("backup_incremental_inside");
+        let app_data = AppData::new("test_app");
+        let inside = app_data.ensure_data_dir().unwrap().join("backups");
+        assert!(matches!(
+            app_data.backup_incremental(&inside),
+            Err(AppDataError::InvalidPath(_))
+        ));
+        assert!(!inside.exists());
+    }
+}
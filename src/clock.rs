@@ -0,0 +1,169 @@
+use std::{
+    fmt,
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+/// Source of the current time for TTL and timestamp features
+///
+/// <details><summary><b>中文说明</b></summary>
+/// TTL 与时间戳相关功能使用的当前时间来源
+/// </details>
+pub trait Clock: Send + Sync {
+    /// The current time
+    fn now(&self) -> SystemTime;
+}
+
+/// The real clock, backed by [`SystemTime::now`]
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 真实时钟，基于 [`SystemTime::now`]
+/// </details>
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to, for deterministic tests of time-dependent behavior
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 只有在显式推进时才会变化的时钟，用于对时间相关行为进行确定性测试
+/// </details>
+///
+/// # Examples
+///
+/// ```rust
+/// use std::{sync::Arc, time::{Duration, SystemTime}};
+/// use app_data::{AppData, Clock, FakeClock};
+///
+/// let clock = Arc::new(FakeClock::new(SystemTime::UNIX_EPOCH));
+/// let app_data = AppData::new("my_app").with_clock(clock.clone());
+/// clock.advance(Duration::from_secs(60));
+/// assert_eq!(clock.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(60));
+/// ```
+#[derive(Debug)]
+pub struct FakeClock {
+    now: Mutex<SystemTime>,
+}
+
+impl FakeClock {
+    /// Create a clock stopped at `start`
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 创建停在 `start` 时刻的时钟
+    /// </details>
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    /// Move the clock forward by `duration`
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将时钟向前推进 `duration`
+    /// </details>
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) += duration;
+    }
+
+    /// Set the clock to `now`
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将时钟设置为 `now`
+    /// </details>
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// The clock an [`AppData`](crate::AppData) reads time from, the [`SystemClock`] by default
+///
+/// Two handles are equal when both use the system clock or share the same custom clock.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// [`AppData`](crate::AppData) 读取时间所用的时钟，默认为 [`SystemClock`]。
+/// 两个句柄都使用系统时钟或共享同一个自定义时钟时视为相等
+/// </details>
+#[derive(Clone, Default)]
+pub struct SharedClock(Option<Arc<dyn Clock>>);
+
+impl SharedClock {
+    /// Wrap a custom clock
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 包装自定义时钟
+    /// </details>
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self(Some(clock))
+    }
+
+    /// The current time according to the wrapped clock
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按所包装时钟给出的当前时间
+    /// </details>
+    pub fn now(&self) -> SystemTime {
+        match &self.0 {
+            Some(clock) => clock.now(),
+            None => SystemClock.now(),
+        }
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(_) => f.write_str("SharedClock(custom)"),
+            None => f.write_str("SharedClock(system)"),
+        }
+    }
+}
+
+impl PartialEq for SharedClock {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for SharedClock {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock() {
+        let clock = FakeClock::new(SystemTime::UNIX_EPOCH);
+        clock.advance(Duration::from_secs(5));
+        assert_eq!(clock.now(), SystemTime::UNIX_EPOCH + Duration::from_secs(5));
+        clock.set(SystemTime::UNIX_EPOCH);
+        assert_eq!(clock.now(), SystemTime::UNIX_EPOCH);
+    }
+
+    #[test]
+    fn test_shared_clock_eq() {
+        let fake: Arc<dyn Clock> = Arc::new(FakeClock::new(SystemTime::UNIX_EPOCH));
+        assert_eq!(SharedClock::default(), SharedClock::default());
+        assert_eq!(
+            SharedClock::new(fake.clone()),
+            SharedClock::new(fake.clone())
+        );
+        assert_ne!(SharedClock::new(fake.clone()), SharedClock::default());
+        assert_eq!(SharedClock::new(fake).now(), SystemTime::UNIX_EPOCH);
+    }
+}
@@ -0,0 +1,448 @@
+//! Companion directories kept apart from the data directory: config, cache, state and runtime
+//!
+//! When the data directory lives in the platform directory, each companion directory lives in
+//! its own platform base, e.g. `~/.cache/<app_name>`. When the data directory is placed
+//! explicitly (local mode, `base_dir`, `APPDATA_BASE_DIR`, `for_user`, the CI sandbox or a
+//! container volume), or the platform has no separate base for it, it is a subfolder of the data
+//! directory instead, so everything stays in the chosen place. The runtime directory only exists
+//! where the platform provides one.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    AppData, AppDataError,
+    app_data::{EnvLookup, create_dir_race_safe},
+};
+
+/// Subfolder of the data directory holding the cache when it has no base of its own
+const CACHE_SUBDIR: &str = "cache";
+/// Subfolder of the data directory holding the configuration when it has no base of its own
+const CONFIG_SUBDIR: &str = "config";
+/// Subfolder of the data directory holding the state when it has no base of its own
+const STATE_SUBDIR: &str = "state";
+
+#[cfg(target_os = "windows")]
+fn sys_cache_dir_with(env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    env("LOCALAPPDATA")
+        .map(|dir| Some(PathBuf::from(dir)))
+        .ok_or_else(|| AppDataError::EnvVarNotFound("LOCALAPPDATA".to_string()))
+}
+
+#[cfg(target_os = "macos")]
+fn sys_cache_dir_with(env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    env("HOME")
+        .map(|home| Some(PathBuf::from(home).join("Library/Caches")))
+        .ok_or_else(|| AppDataError::EnvVarNotFound("HOME".to_string()))
+}
+
+/// Configuration and state share the data base on Windows and macOS
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn sys_config_dir_with(_env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    Ok(None)
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn sys_state_dir_with(_env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    Ok(None)
+}
+
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+fn sys_runtime_dir_with(_env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    Ok(None)
+}
+
+/// `$<var>`, or `$HOME/<home_relative>` when it is empty or relative, as the XDG base
+/// directory specification requires
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn xdg_dir_with(
+    env: EnvLookup,
+    var: &str,
+    home_relative: &str,
+) -> Result<Option<PathBuf>, AppDataError> {
+    if let Some(xdg) = env(var).filter(|xdg| Path::new(xdg).is_absolute()) {
+        Ok(Some(PathBuf::from(xdg)))
+    } else if let Some(home) = env("HOME") {
+        Ok(Some(PathBuf::from(home).join(home_relative)))
+    } else {
+        Err(AppDataError::EnvVarNotFound(format!("{} and HOME", var)))
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn sys_cache_dir_with(env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    xdg_dir_with(env, "XDG_CACHE_HOME", ".cache")
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn sys_config_dir_with(env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    xdg_dir_with(env, "XDG_CONFIG_HOME", ".config")
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn sys_state_dir_with(env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    xdg_dir_with(env, "XDG_STATE_HOME", ".local/state")
+}
+
+/// `XDG_RUNTIME_DIR` has no fallback, it is only used when the session provides it
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn sys_runtime_dir_with(env: EnvLookup) -> Result<Option<PathBuf>, AppDataError> {
+    Ok(env("XDG_RUNTIME_DIR")
+        .filter(|dir| Path::new(dir).is_absolute())
+        .map(PathBuf::from))
+}
+
+/// Every storage location of an application, returned by [`AppData::paths`]
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 应用的所有存储位置，由 [`AppData::paths`] 返回
+/// </details>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppPaths {
+    /// The data directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 数据目录
+    /// </details>
+    pub data: PathBuf,
+    /// The configuration directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 配置目录
+    /// </details>
+    pub config: PathBuf,
+    /// The cache directory
+    /// <details><summary><b>中文说明</b></summary>
+    /// 缓存目录
+    /// </details>
+    pub cache: PathBuf,
+    /// The state directory, for logs and history that are not worth backing up
+    /// <details><summary><b>中文说明</b></summary>
+    /// 状态目录，用于不值得备份的日志和历史记录
+    /// </details>
+    pub state: PathBuf,
+    /// The runtime directory for sockets and pid files, `None` where the platform has none
+    /// <details><summary><b>中文说明</b></summary>
+    /// 用于套接字和 pid 文件的运行时目录，平台不提供时为 `None`
+    /// </details>
+    pub runtime: Option<PathBuf>,
+}
+
+/// Remove everything inside `dir`, keeping `dir` itself, and return the size of the removed files
+fn empty_dir(dir: &Path) -> io::Result<u64> {
+    let mut freed = 0;
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            freed += empty_dir(&path)?;
+            fs::remove_dir(&path)?;
+        } else {
+            if file_type.is_file() {
+                freed += entry.metadata()?.len();
+            }
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(freed)
+}
+
+impl AppData {
+    /// Get the cache directory, creating it if it doesn't exist
+    ///
+    /// This is `$XDG_CACHE_HOME/<app_name>` (or `~/.cache/<app_name>`) on Linux,
+    /// `~/Library/Caches/<app_name>` on macOS and `%LOCALAPPDATA%\<app_name>` on Windows, with
+    /// the group and version folders of the data directory. When the data directory is placed
+    /// explicitly, the cache is its `cache` subfolder.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取缓存目录，不存在时创建。Linux 上为 `$XDG_CACHE_HOME/<app_name>`（或 `~/.cache/<app_name>`），
+    /// macOS 上为 `~/Library/Caches/<app_name>`，Windows 上为 `%LOCALAPPDATA%\<app_name>`，并带有与数据目录相同的分组和版本目录。
+    /// 数据目录被显式指定位置时，缓存目录为其 `cache` 子目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let thumbnails = AppData::new("my_app").cache_dir().unwrap().join("thumbnails");
+    /// ```
+    pub fn cache_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.ensure_companion_dir(self.resolve_cache_dir()?)
+    }
+
+    /// Get the configuration directory, creating it if it doesn't exist
+    ///
+    /// This is `$XDG_CONFIG_HOME/<app_name>` (or `~/.config/<app_name>`) on Linux and the
+    /// `config` subfolder of the data directory elsewhere or when it is placed explicitly.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取配置目录，不存在时创建。Linux 上为 `$XDG_CONFIG_HOME/<app_name>`（或 `~/.config/<app_name>`），
+    /// 其他平台或数据目录被显式指定位置时为数据目录的 `config` 子目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let settings = AppData::new("my_app").config_dir().unwrap().join("settings.toml");
+    /// ```
+    pub fn config_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.ensure_companion_dir(self.resolve_config_dir()?)
+    }
+
+    /// Get the state directory, creating it if it doesn't exist
+    ///
+    /// This is `$XDG_STATE_HOME/<app_name>` (or `~/.local/state/<app_name>`) on Linux and the
+    /// `state` subfolder of the data directory elsewhere or when it is placed explicitly.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取状态目录，不存在时创建。Linux 上为 `$XDG_STATE_HOME/<app_name>`（或 `~/.local/state/<app_name>`），
+    /// 其他平台或数据目录被显式指定位置时为数据目录的 `state` 子目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let history = AppData::new("my_app").state_dir().unwrap().join("history.log");
+    /// ```
+    pub fn state_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.ensure_companion_dir(self.resolve_state_dir()?)
+    }
+
+    /// Get the runtime directory for sockets and pid files, creating it if it doesn't exist
+    ///
+    /// This is `$XDG_RUNTIME_DIR/<app_name>` when the session provides `XDG_RUNTIME_DIR` and
+    /// `None` otherwise, including on Windows and macOS and when the data directory is placed
+    /// explicitly.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取用于套接字和 pid 文件的运行时目录，不存在时创建。会话提供 `XDG_RUNTIME_DIR` 时为
+    /// `$XDG_RUNTIME_DIR/<app_name>`，否则（包括 Windows、macOS 以及数据目录被显式指定位置时）为 `None`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// if let Some(runtime) = AppData::new("my_app").runtime_dir().unwrap() {
+    ///     println!("socket at {}", runtime.join("app.sock").display());
+    /// }
+    /// ```
+    pub fn runtime_dir(&self) -> Result<Option<PathBuf>, AppDataError> {
+        self.resolve_runtime_dir()?
+            .map(|dir| self.ensure_companion_dir(dir))
+            .transpose()
+    }
+
+    /// Get every storage location at once, for example for a "Storage locations" panel
+    ///
+    /// Nothing is created, the directories may not exist yet.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 一次获取所有存储位置，例如用于“存储位置”面板。不会创建任何目录，这些目录可能尚不存在
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let paths = AppData::new("my_app").paths().unwrap();
+    /// println!("data: {}", paths.data.display());
+    /// println!("cache: {}", paths.cache.display());
+    /// ```
+    pub fn paths(&self) -> Result<AppPaths, AppDataError> {
+        let data = self.resolve_data_dir()?;
+        self.check_safe_location(&data)?;
+        Ok(AppPaths {
+            data,
+            config: self.resolve_config_dir()?,
+            cache: self.resolve_cache_dir()?,
+            state: self.resolve_state_dir()?,
+            runtime: self.resolve_runtime_dir()?,
+        })
+    }
+
+    /// Create the companion directory `dir` unless it exists or in read-only mode
+    fn ensure_companion_dir(&self, dir: PathBuf) -> Result<PathBuf, AppDataError> {
+        self.check_safe_location(&dir)?;
+        if !dir.is_dir() && !self.read_only {
+            create_dir_race_safe(&dir)?;
+            self.audit_event("create_dir", &dir)?;
+        }
+        Ok(dir)
+    }
+
+    /// The platform companion directory of `platform_base`, or the `subdir` folder of the data
+    /// directory
+    fn resolve_companion_dir(
+        &self,
+        platform_base: fn(EnvLookup) -> Result<Option<PathBuf>, AppDataError>,
+        subdir: &str,
+    ) -> Result<PathBuf, AppDataError> {
+        match self.platform_companion_dir(platform_base)? {
+            Some(dir) => Ok(dir),
+            None => Ok(self.resolve_data_dir()?.join(subdir)),
+        }
+    }
+
+    /// Resolve the cache directory without creating it
+    pub(crate) fn resolve_cache_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.resolve_companion_dir(sys_cache_dir_with, CACHE_SUBDIR)
+    }
+
+    /// Resolve the configuration directory without creating it
+    pub(crate) fn resolve_config_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.resolve_companion_dir(sys_config_dir_with, CONFIG_SUBDIR)
+    }
+
+    /// Resolve the state directory without creating it
+    pub(crate) fn resolve_state_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.resolve_companion_dir(sys_state_dir_with, STATE_SUBDIR)
+    }
+
+    /// Resolve the runtime directory without creating it
+    pub(crate) fn resolve_runtime_dir(&self) -> Result<Option<PathBuf>, AppDataError> {
+        self.platform_companion_dir(sys_runtime_dir_with)
+    }
+
+    /// Empty the [cache directory](AppData::cache_dir) and return the number of bytes freed
+    ///
+    /// The cache directory itself is kept. Nothing outside it is touched, and a cache directory
+    /// that would contain the data or configuration directory is refused.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 清空缓存目录并返回释放的字节数。缓存目录本身会保留，其外的内容不会被触及；若缓存目录包含数据目录或配置目录则拒绝执行
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let freed = AppData::new("my_app").clear_cache().unwrap();
+    /// println!("freed {} bytes", freed);
+    /// ```
+    pub fn clear_cache(&self) -> Result<u64, AppDataError> {
+        self.deny_read_only("the cache directory")?;
+        let dir = self.resolve_cache_dir()?;
+        self.check_safe_location(&dir)?;
+        if self.resolve_data_dir()?.starts_with(&dir)
+            || self.resolve_config_dir()?.starts_with(&dir)
+        {
+            return Err(AppDataError::RefusedUnsafeCreate(dir));
+        }
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+        Ok(empty_dir(&dir)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_clear_cache_keeps_data() {
+        let base = SysBase::new("clear_cache");
+        let app_data = AppData {
+            base_dir: Some(base.path().to_path_buf()),
+            ..AppData::new("test_app")
+        };
+        app_data.write_file("settings.toml", "theme = 1").unwrap();
+        let cache = app_data.cache_dir().unwrap();
+        assert_eq!(cache, base.path().join("test_app").join("cache"));
+        fs::write(cache.join("a.bin"), [0u8; 10]).unwrap();
+        fs::create_dir(cache.join("thumbs")).unwrap();
+        fs::write(cache.join("thumbs/b.png"), [0u8; 5]).unwrap();
+
+        assert_eq!(app_data.clear_cache().unwrap(), 15);
+        assert!(cache.is_dir());
+        assert_eq!(fs::read_dir(&cache).unwrap().count(), 0);
+        assert_eq!(
+            app_data.read_to_string("settings.toml").unwrap(),
+            "theme = 1"
+        );
+        assert_eq!(app_data.clear_cache().unwrap(), 0);
+
+        assert!(matches!(
+            app_data.read_only(true).clear_cache(),
+            Err(AppDataError::ReadOnly(_))
+        ));
+    }
+
+    #[test]
+    fn test_paths_explicit_base() {
+        let base = SysBase::new("paths_explicit");
+        let app_data = AppData {
+            base_dir: Some(base.path().to_path_buf()),
+            ..AppData::new("test_app")
+        };
+        let data = base.path().join("test_app");
+        assert_eq!(
+            app_data.paths().unwrap(),
+            AppPaths {
+                data: data.clone(),
+                config: data.join("config"),
+                cache: data.join("cache"),
+                state: data.join("state"),
+                runtime: None,
+            }
+        );
+        assert!(!data.exists());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_paths_xdg() {
+        let mut base = SysBase::new("paths_xdg");
+        for (var, dir) in [
+            ("XDG_CONFIG_HOME", "config"),
+            ("XDG_CACHE_HOME", "cache"),
+            ("XDG_STATE_HOME", "state"),
+            ("XDG_RUNTIME_DIR", "runtime"),
+        ] {
+            let dir = base.path().join(dir);
+            base.set_var(var, dir);
+        }
+        let paths = AppData::new("test_app").paths().unwrap();
+        assert_eq!(paths.data, base.path().join("test_app"));
+        assert_eq!(paths.config, base.path().join("config/test_app"));
+        assert_eq!(paths.cache, base.path().join("cache/test_app"));
+        assert_eq!(paths.state, base.path().join("state/test_app"));
+        assert_eq!(paths.runtime, Some(base.path().join("runtime/test_app")));
+        assert!(!paths.data.exists() && !paths.config.exists());
+
+        base.remove_var("XDG_RUNTIME_DIR");
+        base.remove_var("XDG_STATE_HOME");
+        base.set_var("HOME", base.path().join("home"));
+        let paths = AppData::new("test_app").paths().unwrap();
+        assert_eq!(paths.state, base.path().join("home/.local/state/test_app"));
+        assert_eq!(paths.runtime, None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_cache_dir_xdg_cache_home() {
+        let mut base = SysBase::new("cache_dir_xdg");
+        let xdg = base.path().join("xdg-cache");
+        base.set_var("XDG_CACHE_HOME", &xdg);
+        let app_data = AppData::with_group("test_app", "suite");
+        app_data.write_file("data.txt", "x").unwrap();
+
+        let cache = app_data.cache_dir().unwrap();
+        assert_eq!(cache, xdg.join("suite").join("test_app"));
+        fs::write(cache.join("c.tmp"), "abc").unwrap();
+        assert_eq!(app_data.clear_cache().unwrap(), 3);
+        assert_eq!(app_data.read_to_string("data.txt").unwrap(), "x");
+    }
+}
@@ -0,0 +1,83 @@
+//! Minimal `.env` file support for the `dotenv` feature
+
+use std::{env, fs};
+
+/// Look up `key` in the `.env` file of the current directory
+pub(crate) fn lookup(key: &str) -> Option<String> {
+    let contents = fs::read_to_string(env::current_dir().ok()?.join(".env")).ok()?;
+    parse(&contents)
+        .into_iter()
+        .rev()
+        .find(|(name, _)| name == key)
+        .map(|(_, value)| value)
+}
+
+/// Parse `KEY=VALUE` lines, skipping blanks and `#` comments and accepting an `export ` prefix
+/// and single or double quotes around the value
+fn parse(contents: &str) -> Vec<(String, String)> {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let (key, value) = line.split_once('=')?;
+            let value = value.trim();
+            let value = ['"', '\'']
+                .iter()
+                .find_map(|quote| value.strip_prefix(*quote)?.strip_suffix(*quote))
+                .unwrap_or(value);
+            Some((key.trim().to_string(), value.to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppData;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_parse() {
+        let entries = parse(
+            "# comment\n\nAPPDATA_BASE_DIR=/tmp/base\nexport QUOTED=\"a b\"\nSINGLE = 'c'\nbroken\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ("APPDATA_BASE_DIR".to_string(), "/tmp/base".to_string()),
+                ("QUOTED".to_string(), "a b".to_string()),
+                ("SINGLE".to_string(), "c".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dotenv_base_dir_override() {
+        let mut base = SysBase::new("dotenv_base_dir_override");
+        let from_file = base.path().join("from_dotenv");
+        let dotenv = env::current_dir().unwrap().join(".env");
+        assert!(!dotenv.exists(), "refusing to overwrite an existing .env");
+        fs::write(
+            &dotenv,
+            format!("APPDATA_BASE_DIR={}\n", from_file.display()),
+        )
+        .unwrap();
+
+        let enabled = AppData::new("test_app").load_dotenv(true);
+        let disabled = AppData::new("test_app");
+        let enabled_dir = enabled.ensure_data_dir();
+        let disabled_dir = disabled.ensure_data_dir();
+
+        // The real environment wins over `.env`
+        let from_env = base.path().join("from_env");
+        base.set_var("APPDATA_BASE_DIR", &from_env);
+        let env_dir = enabled.ensure_data_dir();
+        fs::remove_file(&dotenv).unwrap();
+
+        assert_eq!(enabled_dir.unwrap(), from_file.join("test_app"));
+        assert!(!disabled_dir.unwrap().starts_with(&from_file));
+        assert_eq!(env_dir.unwrap(), from_env.join("test_app"));
+    }
+}
@@ -0,0 +1,567 @@
+//! Pluggable storage backend for the data directory, so the crate can run over in-memory,
+//! encrypted or virtual filesystems
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use crate::{
+    AppData, AppDataError,
+    app_data::{walk_files, write_contents},
+};
+
+/// Storage operations [`AppData`](crate::AppData) performs in the data directory
+///
+/// A custom backend serves creating the data directory and every helper that reads, writes or
+/// deletes whole files, such as [`read_file`](crate::AppData::read_file),
+/// [`read_lines`](crate::AppData::read_lines), [`write_file_atomic`](crate::AppData::write_file_atomic),
+/// [`import_file`](crate::AppData::import_file) or the TTL helpers. Backends that can list their
+/// files also serve [`file_count`](crate::AppData::file_count),
+/// [`find_file_ci`](crate::AppData::find_file_ci),
+/// [`prune_expired`](crate::AppData::prune_expired) and [`persist`](crate::AppData::persist).
+/// Operations that need the real filesystem, such as locks, appenders, snapshots, backups,
+/// archives, file modes or modification times, fail with
+/// [`AppDataError::Unsupported`](crate::AppDataError::Unsupported).
+/// [`create_new_file`](crate::AppData::create_new_file) checks and writes in two steps, so it
+/// doesn't guard against concurrent writers.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// [`AppData`](crate::AppData) 在数据目录中执行的存储操作。
+/// 自定义后端负责创建数据目录以及所有读取、写入或删除整个文件的辅助方法，例如 `read_file`、`read_lines`、`write_file_atomic`、`import_file` 或 TTL 相关方法。
+/// 能够列出其文件的后端还支持 `file_count`、`find_file_ci`、`prune_expired` 和 `persist`。
+/// 需要真实文件系统的操作（如锁、追加写入器、快照、备份、归档、文件权限或修改时间）返回 [`AppDataError::Unsupported`]。
+/// `create_new_file` 分两步检查并写入，因此无法防范并发写入方
+/// </details>
+pub trait FileSystem: Send + Sync {
+    /// Whether a file or directory exists at `path`
+    fn exists(&self, path: &Path) -> bool;
+    /// Create the directory `path` and all of its parents
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Read the whole file at `path`
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// Create or replace the file at `path` with `contents`
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()>;
+    /// Move the file at `from` to `to`, replacing `to`
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Remove the file at `path`
+    fn remove(&self, path: &Path) -> io::Result<()>;
+    /// Remove the directory `path` with everything below it, used to undo a data directory whose
+    /// [`ensure_data_dir_with_init`](crate::AppData::ensure_data_dir_with_init) callback failed
+    /// so the next call runs it again. Backends that can't remove directories keep the
+    /// default, which fails
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("can't remove {}", path.display()),
+        ))
+    }
+    /// Every file stored below `dir`. Backends that can't list their files keep the default,
+    /// which lists nothing
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let _ = dir;
+        Ok(Vec::new())
+    }
+}
+
+/// The real filesystem, backed by [`std::fs`]
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 真实文件系统，基于 [`std::fs`]
+/// </details>
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdFileSystem;
+
+impl FileSystem for StdFileSystem {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        fs::write(path, contents)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::remove_dir_all(path)
+    }
+}
+
+/// Files and directories kept in memory, e.g. for tests or to write many files cheaply and
+/// [`persist`](crate::AppData::persist) them once
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 保存在内存中的文件和目录，例如用于测试，或先低成本地写入大量文件再一次性 `persist`
+/// </details>
+///
+/// # Examples
+///
+/// ```rust
+/// use std::sync::Arc;
+/// use app_data::{AppData, MemoryFileSystem};
+///
+/// let app_data = AppData::new("my_app").with_file_system(Arc::new(MemoryFileSystem::default()));
+/// app_data.write_file("config.toml", "a = 1").unwrap();
+/// assert_eq!(app_data.read_to_string("config.toml").unwrap(), "a = 1");
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryFileSystem {
+    dirs: Mutex<BTreeSet<PathBuf>>,
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+fn not_found(path: &Path) -> io::Error {
+    io::Error::new(io::ErrorKind::NotFound, path.display().to_string())
+}
+
+impl MemoryFileSystem {
+    fn dirs(&self) -> MutexGuard<'_, BTreeSet<PathBuf>> {
+        self.dirs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn stored(&self) -> MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        self.files.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl FileSystem for MemoryFileSystem {
+    fn exists(&self, path: &Path) -> bool {
+        self.dirs().contains(path) || self.stored().contains_key(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        self.dirs().extend(path.ancestors().map(Path::to_path_buf));
+        Ok(())
+    }
+
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.stored()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> io::Result<()> {
+        let parent = path.parent().ok_or_else(|| not_found(path))?;
+        if !self.dirs().contains(parent) {
+            return Err(not_found(parent));
+        }
+        self.stored().insert(path.to_path_buf(), contents.to_vec());
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.stored();
+        let contents = files.remove(from).ok_or_else(|| not_found(from))?;
+        files.insert(to.to_path_buf(), contents);
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        self.stored()
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn remove_dir_all(&self, path: &Path) -> io::Result<()> {
+        if !self.dirs().remove(path) {
+            return Err(not_found(path));
+        }
+        self.dirs().retain(|dir| !dir.starts_with(path));
+        self.stored().retain(|file, _| !file.starts_with(path));
+        Ok(())
+    }
+
+    fn files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        let files = self.stored();
+        Ok(files
+            .keys()
+            .filter(|path| path.starts_with(dir))
+            .cloned()
+            .collect())
+    }
+}
+
+/// The backend an [`AppData`](crate::AppData) stores files with, the real filesystem by default
+///
+/// Two handles are equal when both use the real filesystem or share the same custom backend.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// [`AppData`](crate::AppData) 存储文件所用的后端，默认为真实文件系统。
+/// 两个句柄都使用真实文件系统或共享同一个自定义后端时视为相等
+/// </details>
+#[derive(Clone, Default)]
+pub struct SharedFileSystem(Option<Arc<dyn FileSystem>>);
+
+impl SharedFileSystem {
+    /// Wrap a custom backend
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 包装自定义后端
+    /// </details>
+    pub fn new(file_system: Arc<dyn FileSystem>) -> Self {
+        Self(Some(file_system))
+    }
+
+    /// The custom backend, `None` for the real filesystem, which keeps the crate's own handling
+    /// of durable writes, locks and permissions
+    pub(crate) fn custom(&self) -> Option<&dyn FileSystem> {
+        self.0.as_deref()
+    }
+}
+
+impl fmt::Debug for SharedFileSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(_) => f.write_str("SharedFileSystem(custom)"),
+            None => f.write_str("SharedFileSystem(std)"),
+        }
+    }
+}
+
+impl PartialEq for SharedFileSystem {
+    fn eq(&self, other: &Self) -> bool {
+        match (&self.0, &other.0) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for SharedFileSystem {}
+
+impl AppData {
+    /// Fail with [`AppDataError::Unsupported`] when a custom backend is set, for operations that
+    /// only work on the real filesystem
+    pub(crate) fn deny_custom_file_system(&self, operation: &str) -> Result<(), AppDataError> {
+        match self.file_system.custom() {
+            Some(_) => Err(AppDataError::Unsupported(operation.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Read the file at `path` through the custom backend, or from the real filesystem
+    pub(crate) fn read_stored(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.file_system.custom() {
+            Some(file_system) => file_system.read(path),
+            None => fs::read(path),
+        }
+    }
+
+    /// Whether a file is stored at `path`, in the custom backend or on the real filesystem
+    pub(crate) fn stored_is_file(&self, path: &Path) -> bool {
+        match self.file_system.custom() {
+            Some(file_system) => file_system.exists(path) && file_system.read(path).is_ok(),
+            None => path.is_file(),
+        }
+    }
+
+    /// Remove the file at `path` through the custom backend, or from the real filesystem
+    pub(crate) fn remove_stored(&self, path: &Path) -> io::Result<()> {
+        match self.file_system.custom() {
+            Some(file_system) => file_system.remove(path),
+            None => fs::remove_file(path),
+        }
+    }
+
+    /// Every file stored below `dir`, sorted, in the custom backend or on the real filesystem
+    pub(crate) fn stored_files(&self, dir: &Path) -> io::Result<Vec<PathBuf>> {
+        match self.file_system.custom() {
+            Some(file_system) => {
+                let mut files = file_system.files(dir)?;
+                files.sort();
+                Ok(files)
+            }
+            None if dir.is_dir() => walk_files(dir),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Write every file the custom [`FileSystem`] backend holds for the data directory to the
+    /// real disk at the resolved location, e.g. to inspect the state a test built in memory
+    ///
+    /// The real data directory is created like [`ensure_data_dir`](AppData::ensure_data_dir)
+    /// without a custom backend would, with its safety checks, README and ownership marker.
+    /// Files are written in place and overwrite what is on disk, files only on disk are kept.
+    /// Does nothing with the real filesystem, or when the backend can't list its files.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将自定义 [`FileSystem`] 后端中数据目录的所有文件写入真实磁盘上解析出的位置，例如用于检查测试在内存中构建的状态。
+    /// 真实数据目录的创建方式与不使用自定义后端时的 `ensure_data_dir` 相同，包括其安全检查、README 和所有者标记。
+    /// 文件原地写入并覆盖磁盘上的内容，仅存在于磁盘上的文件会保留。使用真实文件系统或后端无法列出其文件时不做任何事
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::sync::Arc;
+    /// use app_data::{AppData, MemoryFileSystem};
+    ///
+    /// let app_data = AppData::new("my_app").with_file_system(Arc::new(MemoryFileSystem::default()));
+    /// for index in 0..1000 {
+    ///     app_data.write_file(&format!("item-{}.txt", index), "cached").unwrap();
+    /// }
+    /// app_data.persist().unwrap();
+    /// ```
+    pub fn persist(&self) -> Result<(), AppDataError> {
+        let Some(file_system) = self.file_system.custom() else {
+            return Ok(());
+        };
+        self.deny_read_only("the data directory")?;
+        let disk = AppData {
+            file_system: SharedFileSystem::default(),
+            ..self.clone()
+        };
+        let data_dir = disk.ensure_data_dir()?;
+        for path in file_system.files(&data_dir)? {
+            let contents = file_system.read(&path)?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            write_contents(&path, &contents, disk.durable)?;
+            disk.audit_event("write", &path)?;
+            disk.mirror_write(&path)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+    use std::time::Duration;
+
+    #[test]
+    fn test_memory_backend() {
+        let base = SysBase::new("memory_backend");
+        let backend = Arc::new(MemoryFileSystem::default());
+        let app_data = AppData::new("test_app").with_file_system(backend.clone());
+
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        assert!(backend.exists(&data_dir));
+        app_data.write_file("config.toml", "a = 1").unwrap();
+        app_data.write_file_atomic("save.dat", [1, 2, 3]).unwrap();
+        assert_eq!(app_data.read_to_string("config.toml").unwrap(), "a = 1");
+        assert_eq!(app_data.read_file("save.dat").unwrap(), [1, 2, 3]);
+        assert!(app_data.file_exists("save.dat").unwrap());
+        app_data.delete_file("save.dat").unwrap();
+        assert!(!app_data.file_exists("save.dat").unwrap());
+        assert!(matches!(
+            app_data.read_file("save.dat"),
+            Err(AppDataError::IoError(_))
+        ));
+
+        // Nothing touched the real filesystem
+        assert!(!base.path().join("test_app").exists());
+        let files = backend.files.lock().unwrap();
+        assert_eq!(
+            files.keys().collect::<Vec<_>>(),
+            [&data_dir.join("config.toml")]
+        );
+    }
+
+    #[test]
+    fn test_memory_backend_init_failure_retries() {
+        let base = SysBase::new("memory_init_failure");
+        let backend = Arc::new(MemoryFileSystem::default());
+        let app_data = AppData::new("test_app").with_file_system(backend.clone());
+        let result = app_data.ensure_data_dir_with_init(|dir| {
+            backend.write(&dir.join("partial.toml"), b"x")?;
+            Err(AppDataError::IoError("seeding failed".to_string()))
+        });
+        assert!(matches!(result, Err(AppDataError::IoError(_))));
+        let data_dir = app_data.data_dir_path().unwrap();
+        assert!(!backend.exists(&data_dir));
+        assert!(!backend.exists(&data_dir.join("partial.toml")));
+
+        let mut runs = 0;
+        app_data
+            .ensure_data_dir_with_init(|_| {
+                runs += 1;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(runs, 1);
+        assert!(backend.exists(&data_dir));
+        assert!(!base.path().join("test_app").exists());
+    }
+
+    #[test]
+    fn test_memory_backend_compact_jsonl() {
+        let base = SysBase::new("memory_compact_jsonl");
+        let app_data =
+            AppData::new("test_app").with_file_system(Arc::new(MemoryFileSystem::default()));
+        app_data.write_file("events.jsonl", "1\n2\n\n3\n").unwrap();
+
+        assert_eq!(app_data.compact_jsonl("events.jsonl", 2).unwrap(), 1);
+        assert_eq!(app_data.read_to_string("events.jsonl").unwrap(), "2\n3\n");
+        let lines: Vec<_> = app_data
+            .read_lines("events.jsonl")
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(lines, ["2", "3"]);
+        assert!(!base.path().join("test_app").exists());
+    }
+
+    #[test]
+    fn test_memory_backend_ttl_round_trip() {
+        let base = SysBase::new("memory_ttl");
+        let clock = Arc::new(crate::FakeClock::new(std::time::SystemTime::now()));
+        let app_data = AppData::new("test_app")
+            .with_clock(clock.clone())
+            .with_file_system(Arc::new(MemoryFileSystem::default()));
+        app_data
+            .write_file_with_ttl("token.json", "token", Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(
+            app_data.read_file_if_fresh("token.json").unwrap().unwrap(),
+            b"token"
+        );
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(app_data.read_file_if_fresh("token.json").unwrap(), None);
+        assert_eq!(app_data.prune_expired().unwrap(), 1);
+        assert!(!app_data.file_exists("token.json").unwrap());
+        assert!(!app_data.file_exists("token.json.ttl").unwrap());
+        assert!(!base.path().join("test_app").exists());
+    }
+
+    #[test]
+    fn test_memory_backend_file_helpers() {
+        let base = SysBase::new("memory_file_helpers");
+        let app_data =
+            AppData::new("test_app").with_file_system(Arc::new(MemoryFileSystem::default()));
+        let source = base.path().join("import.txt");
+        fs::write(&source, "imported").unwrap();
+
+        app_data.import_file(&source, "docs/Imported.txt").unwrap();
+        assert!(matches!(
+            app_data.import_new_file(&source, "docs/Imported.txt"),
+            Err(AppDataError::AlreadyExists(_))
+        ));
+        app_data.create_new_file("new.txt", "new").unwrap();
+        assert!(matches!(
+            app_data.create_new_file("new.txt", "again"),
+            Err(AppDataError::AlreadyExists(_))
+        ));
+        assert!(!app_data.write_file_if_changed("new.txt", "new").unwrap());
+        let mut copied = Vec::new();
+        app_data.read_into_writer("new.txt", &mut copied).unwrap();
+        assert_eq!(copied, b"new");
+
+        let data_dir = app_data.data_dir_path().unwrap();
+        assert_eq!(
+            app_data.find_file_ci("DOCS/imported.TXT").unwrap(),
+            Some(data_dir.join("docs/Imported.txt"))
+        );
+        assert_eq!(app_data.file_count(false).unwrap(), 1);
+        assert_eq!(app_data.file_count(true).unwrap(), 2);
+
+        let exported = base.path().join("export/new.txt");
+        app_data.export_file("new.txt", &exported).unwrap();
+        assert_eq!(fs::read_to_string(&exported).unwrap(), "new");
+
+        let outcomes = app_data.delete_files(&["new.txt", "missing.txt"]).unwrap();
+        assert!(outcomes[0].1.is_ok());
+        assert!(matches!(outcomes[1].1, Err(AppDataError::FileNotFound(_))));
+
+        // Operations needing the real filesystem refuse rather than use a second store
+        assert!(matches!(
+            app_data.snapshot_file("docs/Imported.txt"),
+            Err(AppDataError::Unsupported(_))
+        ));
+        assert!(matches!(
+            app_data.appender("events.log"),
+            Err(AppDataError::Unsupported(_))
+        ));
+        assert!(!base.path().join("test_app").exists());
+    }
+
+    #[test]
+    fn test_persist_writes_memory_files_to_disk() {
+        let base = SysBase::new("persist");
+        let backend = Arc::new(MemoryFileSystem::default());
+        let app_data = AppData::new("test_app")
+            .verify_owner(true)
+            .write_readme(true)
+            .with_file_system(backend.clone());
+        app_data.write_file("config.toml", "a = 1").unwrap();
+        let data_dir = app_data.ensure_data_dir().unwrap();
+        backend.create_dir_all(&data_dir.join("saves")).unwrap();
+        backend
+            .write(&data_dir.join("saves/slot-1.sav"), &[1, 2])
+            .unwrap();
+        assert!(!base.path().join("test_app").exists());
+
+        app_data.persist().unwrap();
+        let dir = base.path().join("test_app");
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml")).unwrap(),
+            "a = 1"
+        );
+        assert_eq!(fs::read(dir.join("saves/slot-1.sav")).unwrap(), [1, 2]);
+        assert!(dir.join(crate::app_data::README_FILE).is_file());
+        assert!(dir.join(crate::owner::OWNER_FILE).is_file());
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 4);
+        // The backend still holds the files and stays in charge
+        app_data.write_file("config.toml", "a = 2").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml")).unwrap(),
+            "a = 1"
+        );
+
+        // The real filesystem has nothing to persist
+        AppData::new("std_app").persist().unwrap();
+        assert!(!base.path().join("std_app").exists());
+    }
+
+    #[test]
+    fn test_persist_checks_owner_on_disk() {
+        let base = SysBase::new("persist_owner");
+        let dir = base.path().join("test_app");
+        fs::create_dir(&dir).unwrap();
+        fs::write(dir.join(crate::owner::OWNER_FILE), "app=other_tool\n").unwrap();
+        let app_data = AppData::new("test_app")
+            .verify_owner(true)
+            .with_file_system(Arc::new(MemoryFileSystem::default()));
+        app_data.write_file("config.toml", "a = 1").unwrap();
+
+        assert!(matches!(
+            app_data.persist(),
+            Err(AppDataError::OwnershipConflict(_))
+        ));
+        assert!(!dir.join("config.toml").exists());
+    }
+
+    #[test]
+    fn test_shared_file_system_eq() {
+        let backend: Arc<dyn FileSystem> = Arc::new(MemoryFileSystem::default());
+        assert_eq!(SharedFileSystem::default(), SharedFileSystem::default());
+        assert_eq!(
+            SharedFileSystem::new(backend.clone()),
+            SharedFileSystem::new(backend.clone())
+        );
+        assert_ne!(SharedFileSystem::new(backend), SharedFileSystem::default());
+    }
+}
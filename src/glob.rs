@@ -0,0 +1,261 @@
+//! Shell glob matching of files in the data directory
+//!
+//! Patterns are matched one path component at a time: `*` matches any run of characters and
+//! `?` a single one within a component, `[abc]`, `[a-z]` and `[!abc]` match character classes,
+//! and a `**` component matches any number of directories. As in the shell, a leading `.` must
+//! be matched explicitly, so `*` skips hidden files such as the audit log.
+
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{AppData, AppDataError, app_data::checked_relative};
+
+/// Match the character class starting after `[` at `pattern[start..]` against `c`. Returns
+/// whether it matched and the index after the closing `]`, or `None` when the class isn't closed
+fn match_class(pattern: &[char], start: usize, c: char) -> Option<(bool, usize)> {
+    let mut i = start;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let &current = pattern.get(i)?;
+        // A `]` right after the opening bracket is a literal
+        if current == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if pattern.get(i + 1) == Some(&'-') && pattern.get(i + 2).is_some_and(|end| *end != ']') {
+            matched |= (current..=pattern[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            matched |= current == c;
+            i += 1;
+        }
+    }
+}
+
+/// Whether `name` matches the single-component `pattern`
+fn matches_component(pattern: &[char], name: &[char]) -> bool {
+    if name.first() == Some(&'.') && pattern.first() != Some(&'.') {
+        return false;
+    }
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it is currently matched up to
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+                continue;
+            }
+            Some('?') => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            Some('[') => {
+                if let Some((true, next)) = match_class(pattern, p + 1, name[n]) {
+                    p = next;
+                    n += 1;
+                    continue;
+                }
+                // An unclosed `[` is a literal
+                if match_class(pattern, p + 1, name[n]).is_none() && name[n] == '[' {
+                    p += 1;
+                    n += 1;
+                    continue;
+                }
+            }
+            Some(&literal) if literal == name[n] => {
+                p += 1;
+                n += 1;
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((star_p, star_n)) => {
+                p = star_p;
+                n = star_n + 1;
+                backtrack = Some((star_p, star_n + 1));
+            }
+            None => return false,
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Collect the files below `dir` matching the remaining pattern `components`
+fn collect_matches(
+    dir: &Path,
+    components: &[Vec<char>],
+    matches: &mut Vec<PathBuf>,
+) -> std::io::Result<()> {
+    let Some((component, rest)) = components.split_first() else {
+        return Ok(());
+    };
+    let recursive = component.iter().collect::<String>() == "**";
+    if recursive {
+        if rest.is_empty() {
+            // A trailing `**` matches every file below
+            matches.extend(crate::app_data::walk_files(dir)?);
+            return Ok(());
+        }
+        collect_matches(dir, rest, matches)?;
+    }
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let name: Vec<char> = entry.file_name().to_string_lossy().chars().collect();
+        if recursive {
+            if file_type.is_dir() && name.first() != Some(&'.') {
+                collect_matches(&entry.path(), components, matches)?;
+            }
+        } else if matches_component(component, &name) {
+            if rest.is_empty() {
+                if file_type.is_file() {
+                    matches.push(entry.path());
+                }
+            } else if file_type.is_dir() {
+                collect_matches(&entry.path(), rest, matches)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+impl AppData {
+    /// Find the files in the data directory matching the shell glob `pattern`, such as
+    /// `*.json`, `saves/*.sav` or `**/*.png`, in sorted order
+    ///
+    /// The pattern is relative to the data directory and can't reach outside it: absolute
+    /// patterns and `..` components fail with [`AppDataError::InvalidPath`]. Only regular files
+    /// match, symlinks are not followed.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按排序查找数据目录中匹配 shell 通配模式 `pattern`（例如 `*.json`、`saves/*.sav` 或 `**/*.png`）的文件。
+    /// 模式相对于数据目录，不能指向目录之外：绝对路径模式和 `..` 组件返回 [`AppDataError::InvalidPath`]。
+    /// 只匹配普通文件，不跟随符号链接
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// for save in AppData::new("my_game").glob("saves/slot-?.sav").unwrap() {
+    ///     println!("found {}", save.display());
+    /// }
+    /// ```
+    pub fn glob(&self, pattern: &str) -> Result<Vec<PathBuf>, AppDataError> {
+        self.deny_custom_file_system("glob")?;
+        let components = checked_relative(pattern)?
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(name) => Some(name.to_string_lossy().chars().collect()),
+                _ => None,
+            })
+            .collect::<Vec<Vec<char>>>();
+        let dir = self.data_dir_path()?;
+        let mut matches = Vec::new();
+        if dir.is_dir() {
+            collect_matches(&dir, &components, &mut matches)?;
+        }
+        matches.sort();
+        matches.dedup();
+        Ok(matches)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    fn matches(pattern: &str, name: &str) -> bool {
+        let pattern: Vec<char> = pattern.chars().collect();
+        let name: Vec<char> = name.chars().collect();
+        matches_component(&pattern, &name)
+    }
+
+    #[test]
+    fn test_matches_component() {
+        assert!(matches("*.json", "config.json"));
+        assert!(!matches("*.json", "config.json.bak"));
+        assert!(matches("*", "anything"));
+        assert!(matches("a*b*c", "aXXbYYc"));
+        assert!(!matches("a*b*c", "aXXbYY"));
+        assert!(matches("slot-?.sav", "slot-3.sav"));
+        assert!(!matches("slot-?.sav", "slot-10.sav"));
+        assert!(matches("slot-[0-9].sav", "slot-7.sav"));
+        assert!(!matches("slot-[!0-9].sav", "slot-7.sav"));
+        assert!(matches("[]x]", "]"));
+        assert!(matches("a[b", "a[b"));
+        assert!(!matches("*", ".hidden"));
+        assert!(matches(".*", ".hidden"));
+    }
+
+    #[test]
+    fn test_glob() {
+        let base = SysBase::new("glob");
+        let app_data = AppData::new("test_app");
+        for name in [
+            "config.json",
+            "state.json",
+            "notes.md",
+            ".hidden.json",
+            "saves/slot-1.sav",
+            "saves/slot-2.sav",
+            "saves/old/slot-0.sav",
+            "images/a.png",
+            "images/icons/b.png",
+        ] {
+            app_data.ensure_parent(name).unwrap();
+            app_data.write_file(name, "x").unwrap();
+        }
+        let data_dir = base.path().join("test_app");
+        let found = |pattern: &str| -> Vec<String> {
+            app_data
+                .glob(pattern)
+                .unwrap()
+                .iter()
+                .map(|path| {
+                    let relative = path.strip_prefix(&data_dir).unwrap();
+                    relative.to_string_lossy().replace('\\', "/")
+                })
+                .collect()
+        };
+
+        assert_eq!(found("*.json"), ["config.json", "state.json"]);
+        assert_eq!(
+            found("saves/*.sav"),
+            ["saves/slot-1.sav", "saves/slot-2.sav"]
+        );
+        assert_eq!(found("*/*/*.sav"), ["saves/old/slot-0.sav"]);
+        assert_eq!(found("**/*.png"), ["images/a.png", "images/icons/b.png"]);
+        assert_eq!(
+            found("saves/**"),
+            [
+                "saves/old/slot-0.sav",
+                "saves/slot-1.sav",
+                "saves/slot-2.sav"
+            ]
+        );
+        assert_eq!(found("notes.md"), ["notes.md"]);
+        assert!(found("saves").is_empty());
+        assert!(found("missing/*.txt").is_empty());
+
+        for pattern in ["../*", "saves/../../*", "/etc/*", ""] {
+            match app_data.glob(pattern) {
+                Err(AppDataError::InvalidPath(_)) => {}
+                other => panic!("Expected InvalidPath for {:?}, got {:?}", pattern, other),
+            }
+        }
+    }
+}
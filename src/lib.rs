@@ -19,5 +19,51 @@
 "#]
 
 mod app_data;
+mod appender;
+mod archive;
+mod audit;
+mod backup;
+mod clock;
+mod dirs;
+#[cfg(feature = "dotenv")]
+mod dotenv;
+mod file_system;
+#[cfg(feature = "glob")]
+mod glob;
+mod managed_file;
+mod mirror;
+mod owner;
+mod pid;
+mod platform;
+mod progress;
+mod removable;
+mod resolved;
+mod rw_lock;
+mod session;
+mod snapshot;
+mod swap;
+#[cfg(test)]
+mod test_util;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+#[cfg(feature = "trash")]
+mod trash;
+mod ttl;
+mod unicode;
+mod user;
+mod volatile;
+mod watch;
 
-pub use app_data::{AppData, AppDataError};
+pub use app_data::{AppData, AppDataError, list_apps_in_system_dir};
+pub use appender::FileAppender;
+pub use clock::{Clock, FakeClock, SharedClock, SystemClock};
+pub use dirs::AppPaths;
+pub use file_system::{FileSystem, MemoryFileSystem, SharedFileSystem, StdFileSystem};
+pub use managed_file::ManagedFile;
+pub use platform::Platform;
+pub use progress::Progress;
+pub use resolved::ResolvedAppData;
+pub use rw_lock::{RwFileLock, RwFileLockGuard};
+#[cfg(any(test, feature = "testing"))]
+pub use testing::TestAppData;
+pub use watch::FileWatcher;
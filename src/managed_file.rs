@@ -0,0 +1,150 @@
+use std::{fmt, marker::PhantomData, str::FromStr};
+
+use crate::{AppData, AppDataError};
+
+/// A handle to a single file in the data directory, created by [`AppData::file`]
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 数据目录中单个文件的句柄，由 [`AppData::file`] 创建
+/// </details>
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use app_data::AppData;
+///
+/// let settings = AppData::new("my_app").file::<String>("settings.txt");
+/// settings.save(&"volume=7".to_string()).unwrap();
+/// assert_eq!(settings.load().unwrap(), "volume=7");
+/// ```
+pub struct ManagedFile<T> {
+    app_data: AppData,
+    name: String,
+    _value: PhantomData<fn() -> T>,
+}
+
+impl<T> ManagedFile<T> {
+    pub(crate) fn new(app_data: AppData, name: &str) -> Self {
+        Self {
+            app_data,
+            name: name.to_string(),
+            _value: PhantomData,
+        }
+    }
+
+    /// The file name this handle is bound to
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 句柄绑定的文件名
+    /// </details>
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the file currently exists
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 文件当前是否存在
+    /// </details>
+    pub fn exists(&self) -> Result<bool, AppDataError> {
+        self.app_data.file_exists(&self.name)
+    }
+
+    /// Delete the file
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 删除文件
+    /// </details>
+    pub fn delete(&self) -> Result<(), AppDataError> {
+        self.app_data.delete_file(&self.name)
+    }
+}
+
+impl<T> ManagedFile<T>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    /// Read the file and parse its contents into a value
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 读取文件并将内容解析为值
+    /// </details>
+    pub fn load(&self) -> Result<T, AppDataError> {
+        let text = self.app_data.read_to_string(&self.name)?;
+        text.parse()
+            .map_err(|e: T::Err| AppDataError::Serialization(format!("{}: {}", self.name, e)))
+    }
+}
+
+impl<T: fmt::Display> ManagedFile<T> {
+    /// Write a value to the file, replacing any existing contents
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将值写入文件，覆盖已有内容
+    /// </details>
+    pub fn save(&self, value: &T) -> Result<(), AppDataError> {
+        self.app_data.write_file(&self.name, value.to_string())
+    }
+}
+
+impl<T> Clone for ManagedFile<T> {
+    fn clone(&self) -> Self {
+        Self::new(self.app_data.clone(), &self.name)
+    }
+}
+
+impl<T> fmt::Debug for ManagedFile<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ManagedFile")
+            .field("app_data", &self.app_data)
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_managed_file_matches_free_functions() {
+        let _base = SysBase::new("managed_file");
+        let app_data = AppData::new("test_app");
+        let file = app_data.file::<u32>("count.txt");
+        assert_eq!(file.name(), "count.txt");
+        assert!(!file.exists().unwrap());
+
+        file.save(&42).unwrap();
+        assert!(file.exists().unwrap());
+        assert!(app_data.file_exists("count.txt").unwrap());
+        assert_eq!(app_data.read_to_string("count.txt").unwrap(), "42");
+
+        app_data.write_file("count.txt", "7").unwrap();
+        assert_eq!(file.load().unwrap(), 7);
+
+        file.delete().unwrap();
+        assert!(!file.exists().unwrap());
+        assert!(!app_data.file_exists("count.txt").unwrap());
+    }
+
+    #[test]
+    fn test_managed_file_load_with_bom() {
+        let _base = SysBase::new("managed_file_bom");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("count.txt", "\u{feff}42").unwrap();
+        assert_eq!(app_data.file::<u32>("count.txt").load().unwrap(), 42);
+    }
+
+    #[test]
+    fn test_managed_file_parse_error() {
+        let _base = SysBase::new("managed_file_parse_error");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("count.txt", "not a number").unwrap();
+        match app_data.file::<u32>("count.txt").load() {
+            Err(AppDataError::Serialization(msg)) => assert!(msg.contains("count.txt")),
+            other => panic!("Expected Serialization error, got {:?}", other),
+        }
+    }
+}
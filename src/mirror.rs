@@ -0,0 +1,141 @@
+//! Copies of every write in a secondary directory
+
+use std::{fs, path::Path};
+
+use crate::{AppData, AppDataError};
+
+impl AppData {
+    /// Copy the just written `path` to the same relative path in the mirror directory, when one
+    /// is configured
+    pub(crate) fn mirror_write(&self, path: &Path) -> Result<(), AppDataError> {
+        if self.mirror_dir.is_none() {
+            return Ok(());
+        }
+        let relative = self
+            .relativize(path)
+            .map_err(|err| AppDataError::MirrorFailed(err.to_string()))?;
+        self.mirror_relative(path, &relative)
+    }
+
+    /// [`mirror_write`](Self::mirror_write) for a `path` in the already resolved `data_dir`
+    pub(crate) fn mirror_write_in(&self, data_dir: &Path, path: &Path) -> Result<(), AppDataError> {
+        if self.mirror_dir.is_none() {
+            return Ok(());
+        }
+        let relative = path
+            .strip_prefix(data_dir)
+            .map_err(|_| AppDataError::MirrorFailed(path.display().to_string()))?;
+        self.mirror_relative(path, relative)
+    }
+
+    /// Mirror the just written `path`, or every file below it when it is a directory
+    pub(crate) fn mirror_tree(&self, path: &Path) -> Result<(), AppDataError> {
+        if self.mirror_dir.is_none() || !path.is_dir() {
+            return self.mirror_write(path);
+        }
+        for file in crate::app_data::walk_files(path)? {
+            self.mirror_write(&file)?;
+        }
+        Ok(())
+    }
+
+    fn mirror_relative(&self, path: &Path, relative: &Path) -> Result<(), AppDataError> {
+        let Some(mirror_dir) = &self.mirror_dir else {
+            return Ok(());
+        };
+        let target = mirror_dir.join(relative);
+        let copied = target
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::copy(path, &target));
+        copied
+            .map(|_| ())
+            .map_err(|err| AppDataError::MirrorFailed(format!("{}: {}", target.display(), err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+    use std::io::Write;
+
+    #[test]
+    fn test_mirror_writes() {
+        let base = SysBase::new("mirror");
+        let mirror = base.path().join("backup");
+        let app_data = AppData::new("test_app").mirror_to(Some(mirror.clone()));
+        app_data.write_file("notes.md", "# Notes").unwrap();
+        app_data.ensure_parent("profiles/default.toml").unwrap();
+        app_data
+            .write_file_atomic("profiles/default.toml", "x = 1")
+            .unwrap();
+        app_data
+            .write_batch(&[("a.txt", b"a"), ("b.txt", b"b")])
+            .unwrap();
+
+        for (name, contents) in [
+            ("notes.md", "# Notes"),
+            ("profiles/default.toml", "x = 1"),
+            ("a.txt", "a"),
+            ("b.txt", "b"),
+        ] {
+            assert_eq!(app_data.read_to_string(name).unwrap(), contents);
+            assert_eq!(fs::read_to_string(mirror.join(name)).unwrap(), contents);
+        }
+    }
+
+    #[test]
+    fn test_mirror_other_writers() {
+        let base = SysBase::new("mirror_other_writers");
+        let mirror = base.path().join("backup");
+        let defaults = base.path().join("defaults");
+        fs::create_dir_all(defaults.join("themes")).unwrap();
+        fs::write(defaults.join("themes/dark.toml"), "dark").unwrap();
+        let legacy = base.path().join("legacy");
+        fs::create_dir_all(legacy.join("saves")).unwrap();
+        fs::write(legacy.join("saves/slot-1.sav"), "save").unwrap();
+
+        let seeded = AppData::new("seeded_app").mirror_to(Some(mirror.join("seeded")));
+        assert_eq!(seeded.seed_from_dir(&defaults).unwrap(), 1);
+        let migrated = AppData::new("migrated_app").mirror_to(Some(mirror.join("migrated")));
+        assert!(migrated.migrate_from(&legacy).unwrap());
+
+        let app_data = AppData::new("test_app").mirror_to(Some(mirror.join("app")));
+        let resolved = app_data.clone().resolved().unwrap();
+        resolved.write_file("pinned.txt", "pinned").unwrap();
+        app_data.write_file("notes.md", "# Notes").unwrap();
+        let snapshot = app_data.snapshot_file("notes.md").unwrap();
+        let mut log = app_data.appender("events.log").unwrap();
+        log.write_all(b"started\n").unwrap();
+        log.flush().unwrap();
+
+        for (path, contents) in [
+            (mirror.join("seeded/themes/dark.toml"), "dark"),
+            (mirror.join("migrated/saves/slot-1.sav"), "save"),
+            (mirror.join("app/pinned.txt"), "pinned"),
+            (mirror.join("app/events.log"), "started\n"),
+        ] {
+            assert_eq!(fs::read_to_string(path).unwrap(), contents);
+        }
+        let relative = app_data.relativize(&snapshot).unwrap();
+        assert_eq!(
+            fs::read_to_string(mirror.join("app").join(relative)).unwrap(),
+            "# Notes"
+        );
+    }
+
+    #[test]
+    fn test_mirror_failure_keeps_primary() {
+        let base = SysBase::new("mirror_failure");
+        // A file where the mirror directory should be makes every copy fail
+        let mirror = base.path().join("backup");
+        fs::write(&mirror, "in the way").unwrap();
+        let app_data = AppData::new("test_app").mirror_to(Some(mirror));
+        match app_data.write_file("notes.md", "# Notes") {
+            Err(AppDataError::MirrorFailed(_)) => {}
+            other => panic!("Expected MirrorFailed, got {:?}", other),
+        }
+        assert_eq!(app_data.read_to_string("notes.md").unwrap(), "# Notes");
+    }
+}
@@ -0,0 +1,142 @@
+//! Ownership marker detecting different applications sharing one data directory
+
+use std::{fs, io, path::Path};
+
+use crate::{AppData, AppDataError, app_data::write_contents};
+
+/// Marker recording which application owns the data directory
+pub(crate) const OWNER_FILE: &str = ".appdata-owner";
+
+impl AppData {
+    /// Contents of the ownership marker for this instance: the app name and, when set, the
+    /// version subfolder
+    fn owner_identity(&self) -> String {
+        match &self.version_subdir {
+            Some(version) => format!("app={}\nversion={}\n", self.app_name, version),
+            None => format!("app={}\n", self.app_name),
+        }
+    }
+
+    /// Record this instance as the owner of the data directory in `.appdata-owner`
+    ///
+    /// With [`verify_owner`](AppData::verify_owner), later calls to
+    /// [`ensure_data_dir`](AppData::ensure_data_dir) fail with
+    /// [`AppDataError::OwnershipConflict`] when a different app claimed the directory, e.g.
+    /// two tools of a suite with similar names. Claiming replaces a previous owner, so an app
+    /// can take over its directory after a rename.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在 `.appdata-owner` 中记录当前实例为数据目录的所有者。
+    /// 启用 `verify_owner` 后，如果目录已被其他应用占用（例如套件中名称相近的两个工具），之后调用 `ensure_data_dir`
+    /// 会返回 [`AppDataError::OwnershipConflict`]。重新占用会替换之前的所有者，因此应用改名后可以接管自己的目录
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").verify_owner(true);
+    /// app_data.claim().unwrap();
+    /// ```
+    pub fn claim(&self) -> Result<(), AppDataError> {
+        self.deny_read_only(OWNER_FILE)?;
+        let data_dir = self.ensure_data_dir()?;
+        self.write_owner(&data_dir)
+    }
+
+    /// Write the ownership marker into `data_dir`
+    pub(crate) fn write_owner(&self, data_dir: &Path) -> Result<(), AppDataError> {
+        let path = data_dir.join(OWNER_FILE);
+        write_contents(&path, self.owner_identity().as_bytes(), self.durable)?;
+        self.audit_event_in(data_dir, "write", &path)?;
+        self.mirror_write(&path)
+    }
+
+    /// Fail when `data_dir` was claimed by a different app. Unclaimed directories pass
+    pub(crate) fn check_owner(&self, data_dir: &Path) -> Result<(), AppDataError> {
+        let recorded = match fs::read_to_string(data_dir.join(OWNER_FILE)) {
+            Ok(recorded) => recorded,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(err) => return Err(err.into()),
+        };
+        let lines = |text: &str| -> Vec<String> {
+            text.lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty())
+                .map(str::to_string)
+                .collect()
+        };
+        let recorded = lines(&recorded);
+        if recorded == lines(&self.owner_identity()) {
+            return Ok(());
+        }
+        Err(AppDataError::OwnershipConflict(format!(
+            "{} is owned by {}",
+            data_dir.display(),
+            recorded.join(", ")
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_claim_matching_owner() {
+        let _base = SysBase::new("claim_matching");
+        let app_data = AppData::with_version("editor", "v2").verify_owner(true);
+        app_data.claim().unwrap();
+        assert_eq!(
+            app_data.read_to_string(OWNER_FILE).unwrap(),
+            "app=editor\nversion=v2\n"
+        );
+        app_data.ensure_data_dir().unwrap();
+    }
+
+    #[test]
+    fn test_verify_owner_claims_new_and_accepts_unclaimed_dirs() {
+        let base = SysBase::new("claim_missing");
+        // An existing directory without marker is accepted as is
+        fs::create_dir_all(base.path().join("legacy")).unwrap();
+        let legacy = AppData::new("legacy").verify_owner(true);
+        legacy.ensure_data_dir().unwrap();
+        assert!(!legacy.file_exists(OWNER_FILE).unwrap());
+
+        // A freshly created directory is claimed right away
+        let fresh = AppData::new("fresh").verify_owner(true);
+        fresh.ensure_data_dir().unwrap();
+        assert_eq!(fresh.read_to_string(OWNER_FILE).unwrap(), "app=fresh\n");
+    }
+
+    #[test]
+    fn test_ownership_conflict() {
+        let _base = SysBase::new("claim_conflict");
+        let app_data = AppData::new("editor");
+        app_data.ensure_data_dir().unwrap();
+        app_data.write_file(OWNER_FILE, "app=other_tool\n").unwrap();
+
+        // Without verification the marker is ignored
+        app_data.ensure_data_dir().unwrap();
+        let verified = app_data.clone().verify_owner(true);
+        match verified.ensure_data_dir() {
+            Err(AppDataError::OwnershipConflict(msg)) => assert!(msg.contains("app=other_tool")),
+            other => panic!("Expected OwnershipConflict, got {:?}", other),
+        }
+
+        // A different version of the same app is a different owner too
+        app_data
+            .write_file(OWNER_FILE, "app=editor\nversion=v1\n")
+            .unwrap();
+        assert!(matches!(
+            verified.ensure_data_dir(),
+            Err(AppDataError::OwnershipConflict(_))
+        ));
+
+        // Claiming takes the directory over
+        app_data.claim().unwrap();
+        verified.ensure_data_dir().unwrap();
+    }
+}
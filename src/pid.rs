@@ -0,0 +1,56 @@
+//! Process liveness checks used to detect stale lock files
+
+/// Whether a process with `pid` is currently running
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn is_alive(pid: u32) -> bool {
+    std::path::Path::new("/proc").join(pid.to_string()).exists()
+}
+
+/// Whether a process with `pid` is currently running.
+/// `ps` works for processes of other users too, unlike `kill -0`
+#[cfg(all(unix, not(any(target_os = "linux", target_os = "android"))))]
+pub(crate) fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("ps")
+        .args(["-p", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        // Assume alive when we can't tell, so live locks are never removed
+        .map_or(true, |status| status.success())
+}
+
+/// Whether a process with `pid` is currently running
+#[cfg(target_os = "windows")]
+pub(crate) fn is_alive(pid: u32) -> bool {
+    let output = std::process::Command::new("tasklist")
+        .args(["/FI", &format!("PID eq {}", pid), "/FO", "CSV", "/NH"])
+        .output();
+    match output {
+        Ok(output) => String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)),
+        // Assume alive when we can't tell, so live locks are never removed
+        Err(_) => true,
+    }
+}
+
+/// Process liveness can't be queried on this target, treat every process as alive
+#[cfg(not(any(unix, target_os = "windows")))]
+pub(crate) fn is_alive(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    #[test]
+    fn test_current_process_is_alive() {
+        assert!(is_alive(process::id()));
+    }
+
+    #[cfg(any(unix, target_os = "windows"))]
+    #[test]
+    fn test_exited_process_is_not_alive() {
+        assert!(!is_alive(crate::test_util::exited_pid()));
+    }
+}
@@ -0,0 +1,309 @@
+//! Data paths of other target platforms, computed from an explicit environment
+//!
+//! Host resolution is selected with `cfg` at compile time. Build tools and installers running
+//! on one platform sometimes need the data path of another, so the platform conventions are
+//! also available here as plain string rules using the target's path separator.
+
+use std::{collections::HashMap, fmt, path::PathBuf};
+
+use crate::{
+    AppData, AppDataError,
+    app_data::{check_app_dir_name, sanitize_component, slugify, windows_dir_name},
+};
+
+/// A target platform for [`AppData::resolve_for_platform`]
+///
+/// <details><summary><b>中文说明</b></summary>
+/// [`AppData::resolve_for_platform`] 的目标平台
+/// </details>
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    /// `%APPDATA%\<app_name>`
+    Windows,
+    /// `~/Library/Application Support/<app_name>`
+    MacOS,
+    /// `$XDG_DATA_HOME/<app_name>` or `~/.local/share/<app_name>`
+    Linux,
+}
+
+impl Platform {
+    /// The platform this crate was compiled for, `None` on targets other than Windows, macOS
+    /// and Linux
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 本库编译的目标平台，Windows、macOS 和 Linux 之外的目标返回 `None`
+    /// </details>
+    pub fn current() -> Option<Self> {
+        if cfg!(target_os = "windows") {
+            Some(Platform::Windows)
+        } else if cfg!(target_os = "macos") {
+            Some(Platform::MacOS)
+        } else if cfg!(target_os = "linux") {
+            Some(Platform::Linux)
+        } else {
+            None
+        }
+    }
+
+    fn separator(self) -> char {
+        match self {
+            Platform::Windows => '\\',
+            Platform::MacOS | Platform::Linux => '/',
+        }
+    }
+
+    /// The platform application directory, read from `env`
+    fn app_data_base(self, env: &HashMap<String, String>) -> Result<String, AppDataError> {
+        let home = || {
+            env.get("HOME")
+                .ok_or_else(|| AppDataError::EnvVarNotFound("HOME".to_string()))
+        };
+        match self {
+            Platform::Windows => env
+                .get("APPDATA")
+                .cloned()
+                .ok_or_else(|| AppDataError::EnvVarNotFound("APPDATA".to_string())),
+            Platform::MacOS => Ok(format!("{}/Library/Application Support", home()?)),
+            Platform::Linux => match env.get("XDG_DATA_HOME") {
+                Some(xdg) if xdg.starts_with('/') => Ok(xdg.clone()),
+                _ => home()
+                    .map(|home| format!("{}/.local/share", home))
+                    .map_err(|_| {
+                        AppDataError::EnvVarNotFound("XDG_DATA_HOME and HOME".to_string())
+                    }),
+            },
+        }
+    }
+}
+
+impl fmt::Display for Platform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Platform::Windows => f.write_str("Windows"),
+            Platform::MacOS => f.write_str("macOS"),
+            Platform::Linux => f.write_str("Linux"),
+        }
+    }
+}
+
+impl AppData {
+    /// The platform whose conventions this crate applies on the host, for platform-specific
+    /// decisions without `cfg` or `std::env::consts::OS`
+    ///
+    /// Unlike [`Platform::current`], every target is classified: the BSDs, illumos and other
+    /// targets use the XDG style layout and count as [`Platform::Linux`].
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 本库在主机上采用其约定的平台，便于在不使用 `cfg` 或 `std::env::consts::OS` 的情况下做平台相关的判断。
+    /// 与 `Platform::current` 不同，所有目标都会被归类：BSD、illumos 及其他目标使用 XDG 风格的布局，归为 [`Platform::Linux`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::{AppData, Platform};
+    ///
+    /// if AppData::current_platform() == Platform::MacOS {
+    ///     println!("look for the data in Finder under ~/Library");
+    /// }
+    /// println!("running on {}", AppData::current_platform());
+    /// ```
+    pub fn current_platform() -> Platform {
+        Platform::current().unwrap_or(Platform::Linux)
+    }
+
+    /// Compute the data directory this instance would use on `platform`, reading every
+    /// environment variable from `env`, without touching the filesystem
+    ///
+    /// Lets build tools and installers compute a target's path from another build host. The
+    /// path uses the target's separator. `base_dir`, `APPDATA_BASE_DIR` in `env`, the group and
+    /// the version subfolder apply as usual, while run-time host state (the local `./data`
+    /// directory, `force_local`, `for_user` and the CI sandbox) is not considered. On the host
+    /// itself use [`resolve_with_env`](AppData::resolve_with_env).
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 计算本实例在 `platform` 上使用的数据目录，所有环境变量都从 `env` 读取，不访问文件系统。
+    /// 便于构建工具和安装程序在其他构建主机上计算目标平台的路径，路径使用目标平台的分隔符。
+    /// `base_dir`、`env` 中的 `APPDATA_BASE_DIR`、分组和版本子目录照常生效，而运行时的主机状态
+    /// （本地 `./data` 目录、`force_local`、`for_user` 和 CI 沙箱）不会被考虑。在主机本身上请使用 `resolve_with_env`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use std::{collections::HashMap, path::Path};
+    /// use app_data::{AppData, Platform};
+    ///
+    /// let env = HashMap::from([("APPDATA".to_string(), r"C:\Users\me\AppData\Roaming".to_string())]);
+    /// let dir = AppData::new("my_app").resolve_for_platform(Platform::Windows, &env).unwrap();
+    /// assert_eq!(dir, Path::new(r"C:\Users\me\AppData\Roaming\my_app"));
+    /// ```
+    pub fn resolve_for_platform(
+        &self,
+        platform: Platform,
+        env: &HashMap<String, String>,
+    ) -> Result<PathBuf, AppDataError> {
+        let base = if let Some(dir) = &self.base_dir {
+            dir.to_string_lossy().into_owned()
+        } else if let Some(dir) = env.get("APPDATA_BASE_DIR").filter(|dir| !dir.is_empty()) {
+            dir.clone()
+        } else {
+            platform.app_data_base(env)?
+        };
+        let separator = platform.separator();
+        let mut path = base.trim_end_matches([separator, '/']).to_string();
+        let app_name = match platform {
+            _ if self.slugify => slugify(&self.app_name),
+            Platform::MacOS if self.normalize_unicode => crate::unicode::compose(&self.app_name),
+            Platform::Windows => windows_dir_name(&self.app_name).to_string(),
+            _ => self.app_name.clone(),
+        };
+        check_app_dir_name(&self.app_name, &app_name)?;
+        let group = self.group.as_deref().map(sanitize_component);
+        for part in group
+            .iter()
+            .chain([&app_name])
+            .chain(self.version_subdir.iter())
+        {
+            path.push(separator);
+            path.push_str(part);
+        }
+        Ok(PathBuf::from(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    fn env(vars: &[(&str, &str)]) -> HashMap<String, String> {
+        vars.iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_current_platform() {
+        let expected = match std::env::consts::OS {
+            "windows" => Platform::Windows,
+            "macos" => Platform::MacOS,
+            _ => Platform::Linux,
+        };
+        assert_eq!(AppData::current_platform(), expected);
+        assert_eq!(Platform::Windows.to_string(), "Windows");
+        assert_eq!(Platform::MacOS.to_string(), "macOS");
+        assert_eq!(Platform::Linux.to_string(), "Linux");
+    }
+
+    #[test]
+    fn test_resolve_for_platform() {
+        let app_data = AppData::new("test_app");
+        let windows = env(&[("APPDATA", r"C:\Users\me\AppData\Roaming\")]);
+        assert_eq!(
+            app_data
+                .resolve_for_platform(Platform::Windows, &windows)
+                .unwrap(),
+            Path::new(r"C:\Users\me\AppData\Roaming\test_app")
+        );
+        let home = env(&[("HOME", "/Users/me")]);
+        assert_eq!(
+            app_data
+                .resolve_for_platform(Platform::MacOS, &home)
+                .unwrap(),
+            Path::new("/Users/me/Library/Application Support/test_app")
+        );
+        assert_eq!(
+            app_data
+                .resolve_for_platform(Platform::Linux, &home)
+                .unwrap(),
+            Path::new("/Users/me/.local/share/test_app")
+        );
+        let xdg = env(&[("HOME", "/home/me"), ("XDG_DATA_HOME", "/data/xdg")]);
+        assert_eq!(
+            app_data
+                .resolve_for_platform(Platform::Linux, &xdg)
+                .unwrap(),
+            Path::new("/data/xdg/test_app")
+        );
+        let relative_xdg = env(&[("HOME", "/home/me"), ("XDG_DATA_HOME", "xdg")]);
+        assert_eq!(
+            app_data
+                .resolve_for_platform(Platform::Linux, &relative_xdg)
+                .unwrap(),
+            Path::new("/home/me/.local/share/test_app")
+        );
+
+        for platform in [Platform::Windows, Platform::MacOS, Platform::Linux] {
+            match app_data.resolve_for_platform(platform, &HashMap::new()) {
+                Err(AppDataError::EnvVarNotFound(_)) => {}
+                other => panic!("Expected EnvVarNotFound, got {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn test_resolve_for_platform_group_version_and_override() {
+        let mut app_data = AppData::with_version("test_app", "v2");
+        app_data.group = Some("suite".to_string());
+        let windows = env(&[("APPDATA", r"C:\Roaming")]);
+        assert_eq!(
+            app_data
+                .resolve_for_platform(Platform::Windows, &windows)
+                .unwrap(),
+            Path::new(r"C:\Roaming\suite\test_app\v2")
+        );
+
+        let overridden = env(&[("APPDATA", r"C:\Roaming"), ("APPDATA_BASE_DIR", r"D:\Data")]);
+        assert_eq!(
+            app_data
+                .resolve_for_platform(Platform::Windows, &overridden)
+                .unwrap(),
+            Path::new(r"D:\Data\suite\test_app\v2")
+        );
+        assert!(matches!(
+            AppData::new("").resolve_for_platform(Platform::Linux, &overridden),
+            Err(AppDataError::InvalidAppName(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_for_platform_trims_windows_names() {
+        let windows = env(&[("APPDATA", r"C:\Roaming")]);
+        assert_eq!(
+            AppData::new("test_app. ")
+                .resolve_for_platform(Platform::Windows, &windows)
+                .unwrap(),
+            Path::new(r"C:\Roaming\test_app")
+        );
+        assert!(matches!(
+            AppData::new("..").resolve_for_platform(Platform::Windows, &windows),
+            Err(AppDataError::InvalidAppName(_))
+        ));
+        let home = env(&[("HOME", "/home/me")]);
+        assert_eq!(
+            AppData::new("test_app ")
+                .resolve_for_platform(Platform::Linux, &home)
+                .unwrap(),
+            Path::new("/home/me/.local/share/test_app ")
+        );
+    }
+
+    #[test]
+    fn test_resolve_for_platform_normalizes_on_macos() {
+        let home = env(&[("HOME", "/Users/me")]);
+        let decomposed = AppData::new("Cafe\u{0301}").normalize_unicode(true);
+        assert_eq!(
+            decomposed
+                .resolve_for_platform(Platform::MacOS, &home)
+                .unwrap(),
+            Path::new("/Users/me/Library/Application Support/Caf\u{00E9}")
+        );
+        assert_eq!(
+            decomposed
+                .resolve_for_platform(Platform::Linux, &home)
+                .unwrap(),
+            Path::new("/Users/me/.local/share/Cafe\u{0301}")
+        );
+    }
+}
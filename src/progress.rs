@@ -0,0 +1,60 @@
+/// Progress of a long-running operation, reported after each file
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 耗时操作的进度，每处理完一个文件报告一次
+/// </details>
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Progress {
+    /// Files processed so far
+    /// <details><summary><b>中文说明</b></summary>
+    /// 已处理的文件数
+    /// </details>
+    pub files_done: usize,
+    /// Files the operation will process in total
+    /// <details><summary><b>中文说明</b></summary>
+    /// 操作总共要处理的文件数
+    /// </details>
+    pub files_total: usize,
+    /// Bytes processed so far
+    /// <details><summary><b>中文说明</b></summary>
+    /// 已处理的字节数
+    /// </details>
+    pub bytes_done: u64,
+    /// Bytes the operation will process in total
+    /// <details><summary><b>中文说明</b></summary>
+    /// 操作总共要处理的字节数
+    /// </details>
+    pub bytes_total: u64,
+}
+
+impl Progress {
+    /// Completed fraction of the bytes from `0.0` to `1.0`, `1.0` when there is nothing to do
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 按字节计算的完成比例，范围 `0.0` 到 `1.0`；无需处理任何内容时为 `1.0`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use app_data::Progress;
+    ///
+    /// let progress = Progress { files_done: 1, files_total: 4, bytes_done: 50, bytes_total: 200 };
+    /// assert_eq!(progress.fraction(), 0.25);
+    /// ```
+    pub fn fraction(&self) -> f64 {
+        if self.bytes_total == 0 {
+            return 1.0;
+        }
+        self.bytes_done as f64 / self.bytes_total as f64
+    }
+
+    /// Whether every file has been processed
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 是否已处理完所有文件
+    /// </details>
+    pub fn is_complete(&self) -> bool {
+        self.files_done == self.files_total
+    }
+}
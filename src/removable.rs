@@ -0,0 +1,128 @@
+//! Detection of data directories on removable media such as USB sticks
+
+use std::path::Path;
+
+use crate::{AppData, AppDataError};
+
+/// Whether the block device holding `path` is flagged removable in sysfs
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn is_on_removable(path: &Path) -> Result<bool, AppDataError> {
+    use std::os::unix::fs::MetadataExt;
+
+    let dev = std::fs::metadata(path)?.dev();
+    // glibc's `major`/`minor` encoding of `dev_t`
+    let major = ((dev >> 8) & 0xfff) | ((dev >> 32) & !0xfff);
+    let minor = (dev & 0xff) | ((dev >> 12) & !0xff);
+    let Ok(device) = Path::new("/sys/dev/block")
+        .join(format!("{}:{}", major, minor))
+        .canonicalize()
+    else {
+        // Not backed by a block device, e.g. tmpfs or a network filesystem
+        return Ok(false);
+    };
+    // Partitions don't carry the flag themselves, their disk one level up does
+    for dir in device.ancestors().take(2) {
+        if let Ok(flag) = std::fs::read_to_string(dir.join("removable")) {
+            return Ok(flag.trim() == "1");
+        }
+    }
+    Ok(false)
+}
+
+/// Whether `diskutil` reports the volume holding `path` as removable or external
+#[cfg(target_os = "macos")]
+fn is_on_removable(path: &Path) -> Result<bool, AppDataError> {
+    let output = std::process::Command::new("diskutil")
+        .arg("info")
+        .arg(path)
+        .output()?;
+    if !output.status.success() {
+        return Err(AppDataError::IoError(format!(
+            "diskutil info failed for {}",
+            path.display()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().any(|line| {
+        let Some((key, value)) = line.split_once(':') else {
+            return false;
+        };
+        let value = value.trim();
+        match key.trim() {
+            "Removable Media" => value == "Removable",
+            "Device Location" => value == "External",
+            _ => false,
+        }
+    }))
+}
+
+/// Whether `GetDriveTypeW` reports the drive holding `path` as removable
+#[cfg(target_os = "windows")]
+fn is_on_removable(path: &Path) -> Result<bool, AppDataError> {
+    use std::{os::windows::ffi::OsStrExt, path::Component};
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetDriveTypeW(root_path_name: *const u16) -> u32;
+    }
+    const DRIVE_REMOVABLE: u32 = 2;
+
+    let path = std::path::absolute(path)?;
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return Ok(false);
+    };
+    let mut root: Vec<u16> = prefix.as_os_str().encode_wide().collect();
+    root.extend("\\\0".encode_utf16());
+    // SAFETY: `root` is a NUL-terminated UTF-16 string that outlives the call
+    let drive_type = unsafe { GetDriveTypeW(root.as_ptr()) };
+    Ok(drive_type == DRIVE_REMOVABLE)
+}
+
+/// Removable media can't be detected on this target
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "windows"
+)))]
+fn is_on_removable(_path: &Path) -> Result<bool, AppDataError> {
+    Ok(false)
+}
+
+impl AppData {
+    /// Whether the data directory is on removable media such as a USB stick
+    ///
+    /// Detection uses sysfs on Linux, `diskutil` on macOS and `GetDriveTypeW` on Windows; other
+    /// platforms always report `false`.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 数据目录是否位于 U 盘等可移动介质上。
+    /// Linux 使用 sysfs，macOS 使用 `diskutil`，Windows 使用 `GetDriveTypeW`；其他平台始终返回 `false`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::with_force_local("my_app", true);
+    /// if app_data.is_removable().unwrap() {
+    ///     println!("running from a removable drive, don't unplug it while saving");
+    /// }
+    /// ```
+    pub fn is_removable(&self) -> Result<bool, AppDataError> {
+        is_on_removable(&self.ensure_data_dir()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_is_removable() {
+        let _base = SysBase::new("is_removable");
+        // CI machines have no removable drives, only check that detection works
+        assert!(AppData::new("test_app").is_removable().is_ok());
+    }
+}
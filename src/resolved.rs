@@ -0,0 +1,168 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    AppData, AppDataError,
+    app_data::{UTF8_BOM, long_path, write_contents},
+};
+
+/// An [`AppData`] whose data directory has been resolved and created once, created by
+/// [`AppData::resolved`]
+///
+/// File helpers reuse the pinned directory instead of resolving it again on every call, so the
+/// location can't change underneath a long-running app when the environment or the current
+/// directory changes.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 已完成一次解析并创建数据目录的 [`AppData`]，由 [`AppData::resolved`] 创建。
+/// 文件辅助方法复用固定的目录，而不是每次调用都重新解析，因此即使环境变量或当前目录发生变化，长期运行的应用的数据位置也不会改变
+/// </details>
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use app_data::AppData;
+///
+/// let data = AppData::new("my_app").resolved().unwrap();
+/// data.write_file("config.toml", "theme = \"dark\"").unwrap();
+/// println!("{}", data.read_to_string("config.toml").unwrap());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedAppData {
+    app_data: AppData,
+    data_dir: PathBuf,
+}
+
+impl ResolvedAppData {
+    /// The settings the directory was resolved with
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 解析目录时使用的配置
+    /// </details>
+    pub fn app_data(&self) -> &AppData {
+        &self.app_data
+    }
+
+    /// The pinned data directory
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 固定的数据目录
+    /// </details>
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Get the path of a file in the pinned data directory
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取固定数据目录中文件的路径
+    /// </details>
+    pub fn get_file_path(&self, file_name: &str) -> PathBuf {
+        long_path(self.data_dir.join(file_name))
+    }
+
+    /// Read the whole contents of a file in the pinned data directory
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 读取固定数据目录中文件的全部内容
+    /// </details>
+    pub fn read_file(&self, file_name: &str) -> Result<Vec<u8>, AppDataError> {
+        Ok(self.app_data.read_stored(&self.get_file_path(file_name))?)
+    }
+
+    /// Read a file in the pinned data directory as UTF-8 text, stripping a leading byte order mark
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 以 UTF-8 文本读取固定数据目录中的文件，并去除开头的 BOM
+    /// </details>
+    pub fn read_to_string(&self, file_name: &str) -> Result<String, AppDataError> {
+        let mut text = String::from_utf8(self.read_file(file_name)?)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        if text.starts_with(UTF8_BOM) {
+            text.drain(..UTF8_BOM.len_utf8());
+        }
+        Ok(text)
+    }
+
+    /// Write a file in the pinned data directory, replacing any existing contents
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 写入固定数据目录中的文件，覆盖已有内容
+    /// </details>
+    pub fn write_file(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+    ) -> Result<(), AppDataError> {
+        self.app_data.deny_read_only(file_name)?;
+        let path = self.get_file_path(file_name);
+        if let Some(file_system) = self.app_data.file_system.custom() {
+            return Ok(file_system.write(&path, contents.as_ref())?);
+        }
+        write_contents(&path, contents.as_ref(), self.app_data.durable)?;
+        self.app_data
+            .audit_event_in(&self.data_dir, "write", &path)?;
+        self.app_data.mirror_write_in(&self.data_dir, &path)
+    }
+}
+
+impl AppData {
+    /// Resolve and create the data directory once, returning a handle that keeps using it
+    ///
+    /// Use this for long-lived apps that should decide where their data lives at startup. The
+    /// lazy `AppData` helpers resolve the directory again on every call.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 一次性解析并创建数据目录，返回始终使用该目录的句柄。
+    /// 适用于在启动时确定数据位置的长期运行应用；惰性的 `AppData` 辅助方法每次调用都会重新解析
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let data = AppData::new("my_app").resolved().unwrap();
+    /// println!("data lives in {}", data.data_dir().display());
+    /// ```
+    pub fn resolved(self) -> Result<ResolvedAppData, AppDataError> {
+        let data_dir = self.ensure_data_dir()?;
+        Ok(ResolvedAppData {
+            app_data: self,
+            data_dir,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+    use std::fs;
+
+    #[test]
+    fn test_resolved_path_is_stable() {
+        let mut base = SysBase::new("resolved");
+        let data = AppData::new("test_app").resolved().unwrap();
+        let data_dir = base.path().join("test_app");
+        assert_eq!(data.data_dir(), data_dir);
+        assert!(data_dir.is_dir());
+
+        // Changing the environment after resolution doesn't move the data
+        base.set_var("APPDATA_BASE_DIR", base.path().join("elsewhere"));
+        assert_eq!(data.data_dir(), data_dir);
+        data.write_file("config.toml", "\u{feff}theme = 1").unwrap();
+        assert_eq!(
+            data.get_file_path("config.toml"),
+            data_dir.join("config.toml")
+        );
+        assert_eq!(data.read_to_string("config.toml").unwrap(), "theme = 1");
+        assert_eq!(
+            fs::read(data_dir.join("config.toml")).unwrap(),
+            data.read_file("config.toml").unwrap()
+        );
+        assert!(!AppData::new("test_app").file_exists("config.toml").unwrap());
+    }
+}
@@ -0,0 +1,212 @@
+//! Advisory reader/writer locks between processes
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    path::{Path, PathBuf},
+};
+
+use crate::{AppData, AppDataError};
+
+/// Extension of the sidecar file that is locked in place of the file itself, so that mandatory
+/// locking on Windows never blocks plain reads and writes of the contents
+const RW_LOCK_EXTENSION: &str = "rwlock";
+
+/// Shared (read) and exclusive (write) advisory locks for a file in the data directory, created
+/// by [`AppData::rw_lock_file`]
+///
+/// Any number of readers can hold the lock at the same time, while a writer excludes everyone
+/// else, across threads and processes. Locks are advisory: they only coordinate code that
+/// takes them. Each guard releases its lock when dropped.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 数据目录中文件的共享（读）与独占（写）建议锁，由 [`AppData::rw_lock_file`] 创建。
+/// 任意数量的读者可以同时持有锁，写者则排斥其他所有持有者，跨线程和进程均有效。
+/// 锁是建议性的，只对获取锁的代码起作用。每个守卫在丢弃时释放锁
+/// </details>
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use app_data::AppData;
+///
+/// let app_data = AppData::new("my_app");
+/// let lock = app_data.rw_lock_file("config.toml").unwrap();
+/// {
+///     let _read = lock.read().unwrap();
+///     let config = app_data.read_to_string("config.toml").unwrap();
+/// }
+/// let _write = lock.write().unwrap();
+/// app_data.write_file("config.toml", "theme = \"dark\"").unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct RwFileLock {
+    path: PathBuf,
+}
+
+/// A held lock from [`RwFileLock`], released when dropped
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 从 [`RwFileLock`] 获得的锁，丢弃时释放
+/// </details>
+#[derive(Debug)]
+pub struct RwFileLockGuard {
+    file: File,
+}
+
+impl Drop for RwFileLockGuard {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+impl RwFileLock {
+    /// The sidecar file the locks are taken on
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 实际加锁的附属文件路径
+    /// </details>
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn open(&self) -> Result<File, AppDataError> {
+        Ok(OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&self.path)?)
+    }
+
+    /// Take a shared lock, waiting while a writer holds it
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取共享锁，有写者持有锁时等待
+    /// </details>
+    pub fn read(&self) -> Result<RwFileLockGuard, AppDataError> {
+        let file = self.open()?;
+        file.lock_shared()?;
+        Ok(RwFileLockGuard { file })
+    }
+
+    /// Take the exclusive lock, waiting while anyone else holds the lock
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取独占锁，有其他持有者时等待
+    /// </details>
+    pub fn write(&self) -> Result<RwFileLockGuard, AppDataError> {
+        let file = self.open()?;
+        file.lock()?;
+        Ok(RwFileLockGuard { file })
+    }
+
+    /// Take a shared lock unless a writer holds it, returning `None` instead of waiting
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在没有写者持有锁时获取共享锁，否则立即返回 `None` 而不等待
+    /// </details>
+    pub fn try_read(&self) -> Result<Option<RwFileLockGuard>, AppDataError> {
+        let file = self.open()?;
+        match file.try_lock_shared() {
+            Ok(()) => Ok(Some(RwFileLockGuard { file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err.into()),
+        }
+    }
+
+    /// Take the exclusive lock unless anyone else holds the lock, returning `None` instead of
+    /// waiting
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 在没有其他持有者时获取独占锁，否则立即返回 `None` 而不等待
+    /// </details>
+    pub fn try_write(&self) -> Result<Option<RwFileLockGuard>, AppDataError> {
+        let file = self.open()?;
+        match file.try_lock() {
+            Ok(()) => Ok(Some(RwFileLockGuard { file })),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err.into()),
+        }
+    }
+}
+
+impl AppData {
+    /// Get reader/writer locks coordinating access to a file in the data directory, see
+    /// [`RwFileLock`]
+    ///
+    /// The locks are taken on a `<file_name>.rwlock` sidecar, created on first use.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取用于协调访问数据目录中文件的读写锁，参见 [`RwFileLock`]。锁加在首次使用时创建的 `<file_name>.rwlock` 附属文件上
+    /// </details>
+    pub fn rw_lock_file(&self, file_name: &str) -> Result<RwFileLock, AppDataError> {
+        self.deny_custom_file_system("rw_lock_file")?;
+        self.deny_read_only(file_name)?;
+        Ok(RwFileLock {
+            path: self.get_file_path(&format!("{}.{}", file_name, RW_LOCK_EXTENSION))?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+    use std::{sync::mpsc, thread, time::Duration};
+
+    #[test]
+    fn test_shared_and_exclusive() {
+        let _base = SysBase::new("rw_lock");
+        let lock = AppData::new("test_app")
+            .rw_lock_file("config.toml")
+            .unwrap();
+        assert!(lock.path().ends_with("config.toml.rwlock"));
+
+        let first = lock.read().unwrap();
+        let second = lock.try_read().unwrap();
+        assert!(second.is_some());
+        assert!(lock.try_write().unwrap().is_none());
+        drop(first);
+        assert!(lock.try_write().unwrap().is_none());
+        drop(second);
+
+        let writer = lock.try_write().unwrap().unwrap();
+        assert!(lock.try_read().unwrap().is_none());
+        assert!(lock.try_write().unwrap().is_none());
+        drop(writer);
+        assert!(lock.try_read().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_reader_waits_for_writer_thread() {
+        let _base = SysBase::new("rw_lock_threads");
+        let lock = AppData::new("test_app")
+            .rw_lock_file("config.toml")
+            .unwrap();
+        let (locked, wait_locked) = mpsc::channel();
+        let (release, wait_release) = mpsc::channel::<()>();
+
+        let writer_lock = lock.clone();
+        let writer = thread::spawn(move || {
+            let _guard = writer_lock.write().unwrap();
+            locked.send(()).unwrap();
+            wait_release.recv().unwrap();
+        });
+        wait_locked.recv().unwrap();
+        assert!(lock.try_read().unwrap().is_none());
+
+        let (read_done, wait_read) = mpsc::channel();
+        let reader_lock = lock.clone();
+        let reader = thread::spawn(move || {
+            let _guard = reader_lock.read().unwrap();
+            read_done.send(()).unwrap();
+        });
+        // The reader stays blocked while the writer holds the lock
+        assert!(wait_read.recv_timeout(Duration::from_millis(100)).is_err());
+        release.send(()).unwrap();
+        wait_read.recv_timeout(Duration::from_secs(5)).unwrap();
+
+        writer.join().unwrap();
+        reader.join().unwrap();
+    }
+}
@@ -0,0 +1,163 @@
+//! Per-run scratch directories
+//!
+//! Each run gets `<temp>/<app_name>-sessions/<pid>-<millis>`, where `<millis>` is the time the
+//! run first asked for its session directory, in milliseconds since the Unix epoch. The pid
+//! tells whether the owning run is still alive, the time keeps a reused pid apart.
+
+use std::{
+    env, fs,
+    path::PathBuf,
+    process,
+    sync::OnceLock,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    AppData, AppDataError,
+    app_data::{create_dir_race_safe, sanitize_component},
+    pid,
+};
+
+/// Start of the current session, fixed on first use
+static SESSION_START: OnceLock<u128> = OnceLock::new();
+
+fn session_start() -> u128 {
+    *SESSION_START.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+    })
+}
+
+/// Parse a `<pid>-<millis>` session directory name
+fn parse_session(name: &str) -> Option<(u32, u128)> {
+    let (pid, millis) = name.split_once('-')?;
+    Some((pid.parse().ok()?, millis.parse().ok()?))
+}
+
+impl AppData {
+    /// Get a scratch directory private to the current run, creating it if it doesn't exist
+    ///
+    /// Concurrent runs of the app get distinct directories in the temporary directory, while
+    /// repeated calls within one run return the same one. Directories of runs that have exited
+    /// are removed by [`prune_sessions`](AppData::prune_sessions).
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 获取当前运行独享的临时目录，不存在时创建。同时运行的多个实例在临时目录中得到不同的目录，
+    /// 同一次运行中重复调用返回同一目录。已退出运行的目录由 `prune_sessions` 删除
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let dump = app_data.session_dir().unwrap().join("crash.dmp");
+    /// ```
+    pub fn session_dir(&self) -> Result<PathBuf, AppDataError> {
+        self.session_dir_for(process::id(), session_start())
+    }
+
+    /// [`session_dir`](AppData::session_dir) of the run `pid` started at `started` millis
+    fn session_dir_for(&self, pid: u32, started: u128) -> Result<PathBuf, AppDataError> {
+        self.deny_read_only("the session directory")?;
+        let dir = self.sessions_root().join(format!("{}-{}", pid, started));
+        create_dir_race_safe(&dir)?;
+        Ok(dir)
+    }
+
+    /// Remove the session directories of runs that are no longer alive, returning how many
+    /// were removed
+    ///
+    /// The current session and directories not named like sessions are left alone.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 删除已不在运行的实例的会话目录，返回删除的数量。当前会话以及名称不符合会话格式的目录不会被处理
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let pruned = AppData::new("my_app").prune_sessions().unwrap();
+    /// println!("removed {} old sessions", pruned);
+    /// ```
+    pub fn prune_sessions(&self) -> Result<usize, AppDataError> {
+        self.deny_read_only("session directories")?;
+        let root = self.sessions_root();
+        if !root.is_dir() {
+            return Ok(0);
+        }
+        let mut pruned = 0;
+        for entry in fs::read_dir(root)? {
+            let entry = entry?;
+            if !entry.file_type()?.is_dir() {
+                continue;
+            }
+            let name = entry.file_name();
+            let Some((pid, started)) = name.to_str().and_then(parse_session) else {
+                continue;
+            };
+            let current = pid == process::id() && started == session_start();
+            if !current && !pid::is_alive(pid) {
+                fs::remove_dir_all(entry.path())?;
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+
+    /// Directory holding the session directories of this app
+    fn sessions_root(&self) -> PathBuf {
+        let name = match &self.group {
+            Some(group) => format!("{}-{}", group, self.app_name),
+            None => self.app_name.clone(),
+        };
+        env::temp_dir().join(format!("{}-sessions", sanitize_component(&name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_dir() {
+        let app_data = AppData::new(&format!("test_session_{}", process::id()));
+        let session = app_data.session_dir().unwrap();
+        assert!(session.is_dir());
+        assert_eq!(app_data.session_dir().unwrap(), session);
+
+        let other = app_data
+            .session_dir_for(process::id() + 1, session_start())
+            .unwrap();
+        let reused = app_data.session_dir_for(process::id(), 1).unwrap();
+        assert_ne!(other, session);
+        assert_ne!(reused, session);
+        assert_ne!(other, reused);
+
+        let _ = fs::remove_dir_all(app_data.sessions_root());
+    }
+
+    #[cfg(any(unix, target_os = "windows"))]
+    #[test]
+    fn test_prune_sessions() {
+        let app_data = AppData::new(&format!("test_prune_sessions_{}", process::id()));
+        let current = app_data.session_dir().unwrap();
+        let exited = app_data
+            .session_dir_for(crate::test_util::exited_pid(), 1)
+            .unwrap();
+        let unrelated = app_data.sessions_root().join("keep-me");
+        fs::create_dir(&unrelated).unwrap();
+
+        assert_eq!(app_data.prune_sessions().unwrap(), 1);
+        assert!(current.is_dir());
+        assert!(!exited.exists());
+        assert!(unrelated.is_dir());
+
+        let _ = fs::remove_dir_all(app_data.sessions_root());
+    }
+}
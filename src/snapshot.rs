@@ -0,0 +1,196 @@
+//! Per-file version history
+//!
+//! A snapshot of `name` is a copy stored as `snapshots/name.<millis>`, where `<millis>` is the
+//! time it was taken in milliseconds since the Unix epoch.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::{AppData, AppDataError, app_data::checked_relative};
+
+/// Subdirectory of the data directory holding the snapshots
+const SNAPSHOTS_DIR: &str = "snapshots";
+
+impl AppData {
+    /// Copy a file to a new timestamped snapshot and return the snapshot's path
+    ///
+    /// With [`max_snapshots`](AppData::max_snapshots) set, the oldest snapshots of the file
+    /// beyond the cap are deleted.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将文件复制为带时间戳的新快照并返回快照路径。
+    /// 设置 [`max_snapshots`](AppData::max_snapshots) 后，超出上限的该文件最旧快照会被删除
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app").max_snapshots(10);
+    /// let snapshot = app_data.snapshot_file("notes.md").unwrap();
+    /// println!("saved {}", snapshot.display());
+    /// ```
+    pub fn snapshot_file(&self, file_name: &str) -> Result<PathBuf, AppDataError> {
+        self.deny_custom_file_system("snapshot_file")?;
+        self.deny_read_only(file_name)?;
+        let source = self.get_file_path(file_name)?;
+        let target = self.ensure_parent(&format!("{}/{}", SNAPSHOTS_DIR, file_name))?;
+        let mut millis = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        // Keep snapshots taken within the same millisecond apart
+        let snapshot = loop {
+            let mut snapshot = target.clone().into_os_string();
+            snapshot.push(format!(".{}", millis));
+            let snapshot = PathBuf::from(snapshot);
+            if !snapshot.exists() {
+                break snapshot;
+            }
+            millis += 1;
+        };
+        fs::copy(&source, &snapshot)?;
+        self.audit_event("write", &snapshot)?;
+        self.mirror_write(&snapshot)?;
+
+        if let Some(max) = self.max_snapshots {
+            let snapshots = self.list_snapshots(file_name)?;
+            for old in &snapshots[..snapshots.len().saturating_sub(max)] {
+                fs::remove_file(old)?;
+            }
+        }
+        Ok(snapshot)
+    }
+
+    /// List the snapshots of a file, oldest first
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 列出文件的所有快照，按时间从旧到新排序
+    /// </details>
+    pub fn list_snapshots(&self, file_name: &str) -> Result<Vec<PathBuf>, AppDataError> {
+        self.deny_custom_file_system("list_snapshots")?;
+        let relative = checked_relative(file_name)?;
+        let target = self.ensure_data_dir()?.join(SNAPSHOTS_DIR).join(relative);
+        let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+            return Ok(Vec::new());
+        };
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let prefix = format!("{}.", name.to_string_lossy());
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let entry_name = entry.file_name();
+            let taken_at = entry_name
+                .to_str()
+                .and_then(|entry_name| entry_name.strip_prefix(&prefix))
+                .and_then(|millis| millis.parse::<u128>().ok());
+            if let Some(taken_at) = taken_at.filter(|_| entry.path().is_file()) {
+                snapshots.push((taken_at, entry.path()));
+            }
+        }
+        snapshots.sort();
+        Ok(snapshots.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// Replace a file with the contents of one of its snapshots
+    ///
+    /// `snapshot` must be a path returned by [`snapshot_file`](AppData::snapshot_file) or
+    /// [`list_snapshots`](AppData::list_snapshots) for the same file, otherwise
+    /// [`AppDataError::InvalidPath`] is returned.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 用文件的某个快照内容替换该文件。`snapshot` 必须是同一文件由 `snapshot_file` 或 `list_snapshots` 返回的路径，
+    /// 否则返回 [`AppDataError::InvalidPath`]
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if let Some(previous) = app_data.list_snapshots("notes.md").unwrap().last() {
+    ///     app_data.restore_snapshot("notes.md", previous).unwrap();
+    /// }
+    /// ```
+    pub fn restore_snapshot(&self, file_name: &str, snapshot: &Path) -> Result<(), AppDataError> {
+        self.deny_custom_file_system("restore_snapshot")?;
+        self.deny_read_only(file_name)?;
+        if !self
+            .list_snapshots(file_name)?
+            .iter()
+            .any(|known| known == snapshot)
+        {
+            return Err(AppDataError::InvalidPath(snapshot.display().to_string()));
+        }
+        let contents = fs::read(snapshot)?;
+        self.write_file_atomic(file_name, contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FakeClock, test_util::SysBase};
+    use std::{sync::Arc, time::Duration};
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let base = SysBase::new("snapshot");
+        let clock = Arc::new(FakeClock::new(UNIX_EPOCH + Duration::from_millis(1000)));
+        let app_data = AppData::new("test_app").with_clock(clock);
+
+        app_data.write_file("notes.md", "first").unwrap();
+        let first = app_data.snapshot_file("notes.md").unwrap();
+        assert_eq!(first, base.path().join("test_app/snapshots/notes.md.1000"));
+        // A second snapshot in the same millisecond gets its own name
+        app_data.write_file("notes.md", "second").unwrap();
+        let second = app_data.snapshot_file("notes.md").unwrap();
+        assert_eq!(second, base.path().join("test_app/snapshots/notes.md.1001"));
+
+        app_data.write_file("notes.md", "third").unwrap();
+        assert_eq!(
+            app_data.list_snapshots("notes.md").unwrap(),
+            [first.clone(), second]
+        );
+        app_data.restore_snapshot("notes.md", &first).unwrap();
+        assert_eq!(app_data.read_to_string("notes.md").unwrap(), "first");
+
+        let outside = base.path().join("test_app/notes.md");
+        assert!(matches!(
+            app_data.restore_snapshot("notes.md", &outside),
+            Err(AppDataError::InvalidPath(_))
+        ));
+        assert!(app_data.list_snapshots("other.md").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_snapshot_pruning() {
+        let _base = SysBase::new("snapshot_pruning");
+        let clock = Arc::new(FakeClock::new(UNIX_EPOCH + Duration::from_secs(1)));
+        let app_data = AppData::new("test_app")
+            .with_clock(clock.clone())
+            .max_snapshots(2);
+
+        app_data.ensure_parent("dir/notes.md").unwrap();
+        let mut taken = Vec::new();
+        for version in 0..4 {
+            app_data
+                .write_file("dir/notes.md", version.to_string())
+                .unwrap();
+            taken.push(app_data.snapshot_file("dir/notes.md").unwrap());
+            clock.advance(Duration::from_secs(1));
+        }
+        assert_eq!(app_data.list_snapshots("dir/notes.md").unwrap(), taken[2..]);
+        assert!(!taken[0].exists());
+        assert_eq!(fs::read_to_string(&taken[3]).unwrap(), "3");
+    }
+}
@@ -0,0 +1,129 @@
+//! Helpers shared by the unit tests
+//!
+//! Resolution depends on process-wide state (environment variables and the `./data` directory
+//! under the current directory), so every test touching it must hold [`ENV_LOCK`].
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+/// Serialize tests that read or mutate process-wide state
+pub(crate) fn lock_env() -> MutexGuard<'static, ()> {
+    ENV_LOCK.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The environment variable that decides the system application directory on this host
+#[cfg(target_os = "windows")]
+const SYS_BASE_VAR: &str = "APPDATA";
+#[cfg(target_os = "macos")]
+const SYS_BASE_VAR: &str = "HOME";
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+const SYS_BASE_VAR: &str = "XDG_DATA_HOME";
+#[cfg(not(any(
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris"
+)))]
+const SYS_BASE_VAR: &str = "HOME";
+
+/// A temporary system base directory, restored and removed on drop
+pub(crate) struct SysBase {
+    path: PathBuf,
+    saved: Vec<(String, Option<OsString>)>,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl SysBase {
+    /// Point the system application directory at a fresh temporary directory
+    pub(crate) fn new(name: &str) -> Self {
+        let guard = lock_env();
+        let path = env::temp_dir().join(format!("app_data_test_{}_{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        let mut base = Self {
+            path,
+            saved: Vec::new(),
+            _guard: guard,
+        };
+        let path = base.path.clone();
+        base.set_var(SYS_BASE_VAR, &path);
+        base.remove_var("APPDATA_BASE_DIR");
+        base
+    }
+
+    /// The temporary directory backing this base
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Set an environment variable until this base is dropped
+    pub(crate) fn set_var(&mut self, key: &str, value: impl AsRef<std::ffi::OsStr>) {
+        self.save(key);
+        // SAFETY: every test touching the environment holds `ENV_LOCK`
+        unsafe { env::set_var(key, value) };
+    }
+
+    /// Remove an environment variable until this base is dropped
+    pub(crate) fn remove_var(&mut self, key: &str) {
+        self.save(key);
+        // SAFETY: every test touching the environment holds `ENV_LOCK`
+        unsafe { env::remove_var(key) };
+    }
+
+    fn save(&mut self, key: &str) {
+        if !self.saved.iter().any(|(saved, _)| saved == key) {
+            self.saved.push((key.to_string(), env::var_os(key)));
+        }
+    }
+}
+
+impl Drop for SysBase {
+    fn drop(&mut self) {
+        for (key, value) in self.saved.drain(..).rev() {
+            // SAFETY: `ENV_LOCK` is still held until `_guard` is dropped
+            unsafe {
+                match value {
+                    Some(value) => env::set_var(&key, value),
+                    None => env::remove_var(&key),
+                }
+            }
+        }
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Pid of a child process that has already exited and been reaped
+#[cfg(any(unix, target_os = "windows"))]
+pub(crate) fn exited_pid() -> u32 {
+    #[cfg(unix)]
+    let mut child = process::Command::new("true").spawn().unwrap();
+    #[cfg(target_os = "windows")]
+    let mut child = process::Command::new("cmd")
+        .args(["/C", "exit"])
+        .spawn()
+        .unwrap();
+    let pid = child.id();
+    child.wait().unwrap();
+    pid
+}
@@ -0,0 +1,118 @@
+//! Isolated, self-cleaning instances for tests of code that persists data
+
+use std::{env, fs, ops::Deref, path::PathBuf, process};
+
+use crate::AppData;
+
+/// An [`AppData`] in its own temporary directory, removed again when dropped, created by
+/// [`AppData::test_instance`]
+///
+/// It dereferences to [`AppData`], so every method can be called on it directly.
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 位于独立临时目录中的 [`AppData`]，丢弃时删除该目录，由 [`AppData::test_instance`] 创建。
+/// 可通过解引用直接调用 [`AppData`] 的所有方法
+/// </details>
+#[derive(Debug)]
+pub struct TestAppData {
+    app_data: AppData,
+    root: PathBuf,
+    keep: bool,
+}
+
+impl TestAppData {
+    /// Stop cleaning up on drop and return the instance, e.g. to inspect files after a failure
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 不再在丢弃时清理并返回实例，例如在测试失败后检查文件
+    /// </details>
+    pub fn keep(mut self) -> AppData {
+        self.keep = true;
+        self.app_data.clone()
+    }
+}
+
+impl Deref for TestAppData {
+    type Target = AppData;
+
+    fn deref(&self) -> &AppData {
+        &self.app_data
+    }
+}
+
+impl Drop for TestAppData {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+impl AppData {
+    /// Create an instance for tests, stored in a temporary directory unique to `name` and the
+    /// current process and removed when the returned guard is dropped
+    ///
+    /// The directory is set as [`base_dir`](AppData::base_dir), so the local `./data` directory
+    /// and environment overrides never leak into the test. Available with the `testing` feature.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 创建用于测试的实例，数据存放在由 `name` 和当前进程决定的唯一临时目录中，返回的守卫被丢弃时删除该目录。
+    /// 该目录被设为 `base_dir`，因此本地 `./data` 目录和环境变量覆盖都不会影响测试。需启用 `testing` 特性
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,ignore
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::test_instance("saves_settings");
+    /// app_data.write_file("settings.toml", "volume = 7").unwrap();
+    /// assert_eq!(app_data.read_to_string("settings.toml").unwrap(), "volume = 7");
+    /// ```
+    pub fn test_instance(name: &str) -> TestAppData {
+        let root = env::temp_dir().join(format!(
+            "app_data-test-{}-{}",
+            crate::app_data::sanitize_component(name),
+            process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        let app_data = AppData {
+            base_dir: Some(root.clone()),
+            ..AppData::new(name)
+        };
+        TestAppData {
+            app_data,
+            root,
+            keep: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instances_are_isolated() {
+        let first = AppData::test_instance("isolation_first");
+        let second = AppData::test_instance("isolation_second");
+        first.write_file("settings.toml", "first").unwrap();
+        second.write_file("settings.toml", "second").unwrap();
+        assert_eq!(first.read_to_string("settings.toml").unwrap(), "first");
+        assert_eq!(second.read_to_string("settings.toml").unwrap(), "second");
+        assert_ne!(
+            first.ensure_data_dir().unwrap(),
+            second.ensure_data_dir().unwrap()
+        );
+
+        let root = first.root.clone();
+        assert!(root.is_dir());
+        drop(first);
+        assert!(!root.exists());
+        assert_eq!(second.read_to_string("settings.toml").unwrap(), "second");
+
+        let kept = second.keep();
+        assert!(kept.file_exists("settings.toml").unwrap());
+        fs::remove_dir_all(kept.base_dir.unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Moving files to the system trash instead of deleting them
+
+use std::{fs, path::Path};
+
+use crate::{AppData, AppDataError, app_data::checked_relative};
+
+/// `YYYY-MM-DDThh:mm:ss` of `time` in UTC, the offset isn't available without the C library
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+fn format_deletion_date(time: std::time::SystemTime) -> String {
+    let secs = time
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    // Civil date from days since 1970-01-01, after Howard Hinnant's `civil_from_days`
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Move `path` into the freedesktop.org home trash, `$XDG_DATA_HOME/Trash`.
+/// Returns `false` when it can't be moved there, e.g. from another filesystem
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris"
+))]
+fn move_to_trash(path: &Path, app_data: &AppData) -> Result<bool, AppDataError> {
+    use std::io::{ErrorKind, Write};
+
+    let trash = crate::app_data::get_sys_app_data_dir()?.join("Trash");
+    let (files, info) = (trash.join("files"), trash.join("info"));
+    fs::create_dir_all(&files)?;
+    fs::create_dir_all(&info)?;
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut encoded = String::new();
+    crate::app_data::push_url_encoded(&mut encoded, path.as_os_str().as_encoded_bytes());
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encoded,
+        format_deletion_date(app_data.clock.now())
+    );
+    // Claim a free name by creating its info file first, as the specification requires
+    for attempt in 1.. {
+        let trashed = match attempt {
+            1 => name.to_string(),
+            n => format!("{}.{}", name, n),
+        };
+        let info_path = info.join(format!("{}.trashinfo", trashed));
+        let mut info_file = match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err.into()),
+        };
+        info_file.write_all(contents.as_bytes())?;
+        if fs::rename(path, files.join(&trashed)).is_err() {
+            let _ = fs::remove_file(&info_path);
+            return Ok(false);
+        }
+        return Ok(true);
+    }
+    unreachable!("attempts are unbounded")
+}
+
+/// Move `path` to the Trash through Finder.
+/// Returns `false` when Finder can't be scripted, e.g. over SSH
+#[cfg(target_os = "macos")]
+fn move_to_trash(path: &Path, _app_data: &AppData) -> Result<bool, AppDataError> {
+    let Some(path) = path.to_str() else {
+        return Ok(false);
+    };
+    let quoted = path.replace('\\', "\\\\").replace('"', "\\\"");
+    let status = std::process::Command::new("osascript")
+        .args([
+            "-e",
+            &format!(
+                "tell application \"Finder\" to delete POSIX file \"{}\"",
+                quoted
+            ),
+        ])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+    Ok(status.is_ok_and(|status| status.success()))
+}
+
+/// Move `path` to the Recycle Bin through .NET's `FileSystem.DeleteFile`.
+/// Returns `false` when PowerShell isn't available
+#[cfg(target_os = "windows")]
+fn move_to_trash(path: &Path, _app_data: &AppData) -> Result<bool, AppDataError> {
+    let Some(path) = path.to_str() else {
+        return Ok(false);
+    };
+    let quoted = path.replace('\'', "''");
+    let script = format!(
+        "Add-Type -AssemblyName Microsoft.VisualBasic; \
+         [Microsoft.VisualBasic.FileIO.FileSystem]::DeleteFile('{}', 'OnlyErrorDialogs', 'SendToRecycleBin')",
+        quoted
+    );
+    let status = std::process::Command::new("powershell")
+        .args(["-NoProfile", "-NonInteractive", "-Command", &script])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status();
+    Ok(status.is_ok_and(|status| status.success()))
+}
+
+/// There is no trash to move files to on this target
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly",
+    target_os = "illumos",
+    target_os = "solaris",
+    target_os = "macos",
+    target_os = "windows"
+)))]
+fn move_to_trash(_path: &Path, _app_data: &AppData) -> Result<bool, AppDataError> {
+    Ok(false)
+}
+
+impl AppData {
+    /// Move a file in the data directory to the system trash so the user can recover it
+    ///
+    /// Uses the freedesktop.org trash on Linux and the BSDs, Finder on macOS and the Recycle Bin
+    /// on Windows. When the file can't be trashed it is deleted permanently instead, and
+    /// `false` is returned as a warning. `file_name` must stay inside the data directory.
+    ///
+    /// The freedesktop.org spec records the `DeletionDate` in local time, but without the C
+    /// library the local offset isn't available, so it is written in UTC. File managers in other
+    /// time zones show the deletion time shifted by the zone's offset.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 将数据目录中的文件移到系统回收站，便于用户恢复。
+    /// Linux 和 BSD 使用 freedesktop.org 回收站，macOS 使用访达，Windows 使用回收站。
+    /// 无法移入回收站时改为永久删除，并返回 `false` 作为提示。`file_name` 必须位于数据目录内。
+    /// freedesktop.org 规范要求 `DeletionDate` 使用本地时间，但不依赖 C 库无法获取本地时区偏移，
+    /// 因此写入的是 UTC 时间，其他时区的文件管理器显示的删除时间会相差该时区的偏移
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// if !app_data.trash_file("old_project.json").unwrap() {
+    ///     eprintln!("no trash available, the file was deleted permanently");
+    /// }
+    /// ```
+    pub fn trash_file(&self, file_name: &str) -> Result<bool, AppDataError> {
+        self.deny_custom_file_system("trash_file")?;
+        self.deny_read_only(file_name)?;
+        let path = self.ensure_data_dir()?.join(checked_relative(file_name)?);
+        if !path.is_file() {
+            return Err(AppDataError::IoError(format!(
+                "{} is not a file",
+                path.display()
+            )));
+        }
+        if move_to_trash(&path, self)? {
+            return Ok(true);
+        }
+        fs::remove_file(&path)?;
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_os = "illumos",
+        target_os = "solaris"
+    ))]
+    #[test]
+    fn test_trash_file() {
+        use super::*;
+        use crate::{FakeClock, test_util::SysBase};
+        use std::{
+            sync::Arc,
+            time::{Duration, UNIX_EPOCH},
+        };
+
+        let base = SysBase::new("trash_file");
+        let deleted_at = UNIX_EPOCH + Duration::from_secs(951_782_400 + 3_723);
+        let app_data = AppData::new("test_app").with_clock(Arc::new(FakeClock::new(deleted_at)));
+        for _ in 0..2 {
+            app_data.write_file("old file.txt", "old").unwrap();
+            assert!(app_data.trash_file("old file.txt").unwrap());
+            assert!(!app_data.file_exists("old file.txt").unwrap());
+        }
+
+        let trash = base.path().join("Trash");
+        assert_eq!(
+            fs::read_to_string(trash.join("files/old file.txt")).unwrap(),
+            "old"
+        );
+        assert!(trash.join("files/old file.txt.2").is_file());
+        let info = fs::read_to_string(trash.join("info/old file.txt.trashinfo")).unwrap();
+        let expected_path = base.path().join("test_app/old file.txt");
+        let expected_path = expected_path.to_str().unwrap().replace(' ', "%20");
+        assert_eq!(
+            info,
+            format!(
+                "[Trash Info]\nPath={}\nDeletionDate=2000-02-29T01:02:03\n",
+                expected_path
+            )
+        );
+        assert!(matches!(
+            app_data.trash_file("../escape.txt"),
+            Err(AppDataError::InvalidPath(_))
+        ));
+    }
+}
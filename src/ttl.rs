@@ -0,0 +1,285 @@
+//! Time-based expiry for cached files
+//!
+//! The expiry of `name` is stored as milliseconds since the Unix epoch in a `name.ttl` sidecar,
+//! followed by a fingerprint of the contents it was set for. Once the file is overwritten by a
+//! write without TTL, the fingerprint no longer matches and the stale sidecar is ignored.
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{AppData, AppDataError};
+
+/// Extension appended to a file name to form its expiry sidecar
+const TTL_EXTENSION: &str = "ttl";
+
+fn millis_since_epoch(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+}
+
+/// 64-bit FNV-1a hash of `contents`, stable across builds unlike the std hashers
+fn fingerprint(contents: &[u8]) -> u64 {
+    contents.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Whether the file at `path` holding `contents` has an expiry sidecar in the past.
+/// Files without a readable sidecar, or whose sidecar was written for other contents, never
+/// expire
+fn is_expired(app_data: &AppData, path: &Path, contents: &[u8], now: SystemTime) -> bool {
+    let mut sidecar = path.as_os_str().to_os_string();
+    sidecar.push(".");
+    sidecar.push(TTL_EXTENSION);
+    let Ok(text) = app_data
+        .read_stored(Path::new(&sidecar))
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+    else {
+        return false;
+    };
+    let mut fields = text.split_whitespace();
+    let expires_at = fields.next().and_then(|field| field.parse::<u128>().ok());
+    let current = fields
+        .next()
+        .is_some_and(|field| u64::from_str_radix(field, 16) == Ok(fingerprint(contents)));
+    current && expires_at.is_some_and(|expires_at| expires_at <= millis_since_epoch(now))
+}
+
+impl AppData {
+    /// Write a file that expires after `ttl`, see [`read_file_if_fresh`](Self::read_file_if_fresh)
+    ///
+    /// Overwriting the file later with different contents and without TTL, e.g. through
+    /// [`write_file`](Self::write_file), removes the expiry. A `ttl` too large to represent,
+    /// such as `Duration::MAX`, means the file never expires.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 写入一个在 `ttl` 之后过期的文件，参见 [`read_file_if_fresh`](Self::read_file_if_fresh)。
+    /// 之后不带 TTL（例如通过 `write_file`）以不同内容覆盖该文件会取消过期时间。
+    /// 无法表示的过大 `ttl`（例如 `Duration::MAX`）表示文件永不过期
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// app_data
+    ///     .write_file_with_ttl("token.json", "{}", Duration::from_secs(3600))
+    ///     .unwrap();
+    /// ```
+    pub fn write_file_with_ttl(
+        &self,
+        file_name: &str,
+        contents: impl AsRef<[u8]>,
+        ttl: Duration,
+    ) -> Result<(), AppDataError> {
+        self.deny_read_only(file_name)?;
+        let contents = contents.as_ref();
+        let sidecar = format!("{}.{}", file_name, TTL_EXTENSION);
+        let Some(expires_at) = self.clock.now().checked_add(ttl) else {
+            self.write_file(file_name, contents)?;
+            if self.file_exists(&sidecar)? {
+                self.delete_file(&sidecar)?;
+            }
+            return Ok(());
+        };
+        self.write_file(file_name, contents)?;
+        self.write_file(
+            &sidecar,
+            format!(
+                "{} {:016x}",
+                millis_since_epoch(expires_at),
+                fingerprint(contents)
+            ),
+        )
+    }
+
+    /// Read a file unless it has expired, returning `None` for expired or missing files
+    ///
+    /// Files written without a TTL never expire.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 读取未过期的文件，文件已过期或不存在时返回 `None`。未设置 TTL 写入的文件永不过期
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// match app_data.read_file_if_fresh("token.json").unwrap() {
+    ///     Some(token) => println!("cached token: {} bytes", token.len()),
+    ///     None => println!("token expired, fetching a new one"),
+    /// }
+    /// ```
+    pub fn read_file_if_fresh(&self, file_name: &str) -> Result<Option<Vec<u8>>, AppDataError> {
+        let path = self.read_path(file_name)?;
+        if !self.stored_is_file(&path) {
+            return Ok(None);
+        }
+        let contents = self.read_stored(&path)?;
+        if is_expired(self, &path, &contents, self.clock.now()) {
+            return Ok(None);
+        }
+        Ok(Some(contents))
+    }
+
+    /// Delete every expired file and its sidecar, returning how many files were removed
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 删除所有已过期的文件及其附属文件，返回删除的文件数量
+    /// </details>
+    pub fn prune_expired(&self) -> Result<usize, AppDataError> {
+        self.deny_read_only("expired files")?;
+        let data_dir = self.data_dir_path()?;
+        let now = self.clock.now();
+        let mut pruned = 0;
+        for sidecar in self.stored_files(&data_dir)? {
+            if sidecar.extension().is_none_or(|ext| ext != TTL_EXTENSION) {
+                continue;
+            }
+            let path = sidecar.with_extension("");
+            if self.stored_is_file(&path) && is_expired(self, &path, &self.read_stored(&path)?, now)
+            {
+                self.remove_stored(&path)?;
+                self.remove_stored(&sidecar)?;
+                pruned += 1;
+            }
+        }
+        Ok(pruned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FakeClock;
+    use crate::test_util::SysBase;
+    use std::{sync::Arc, thread};
+
+    #[test]
+    fn test_ttl_expires() {
+        let _base = SysBase::new("ttl_expires");
+        let app_data = AppData::new("test_app");
+        app_data
+            .write_file_with_ttl("short.json", "short", Duration::from_millis(20))
+            .unwrap();
+        app_data
+            .write_file_with_ttl("long.json", "long", Duration::from_secs(3600))
+            .unwrap();
+        app_data.write_file("plain.json", "plain").unwrap();
+        assert_eq!(
+            app_data.read_file_if_fresh("short.json").unwrap().unwrap(),
+            b"short"
+        );
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(app_data.read_file_if_fresh("short.json").unwrap(), None);
+        assert_eq!(
+            app_data.read_file_if_fresh("long.json").unwrap().unwrap(),
+            b"long"
+        );
+        assert_eq!(
+            app_data.read_file_if_fresh("plain.json").unwrap().unwrap(),
+            b"plain"
+        );
+        assert_eq!(app_data.read_file_if_fresh("missing.json").unwrap(), None);
+    }
+
+    #[test]
+    fn test_ttl_with_fake_clock() {
+        let _base = SysBase::new("ttl_with_fake_clock");
+        let clock = Arc::new(FakeClock::new(SystemTime::now()));
+        let app_data = AppData::new("test_app").with_clock(clock.clone());
+        app_data
+            .write_file_with_ttl("token.json", "token", Duration::from_secs(3600))
+            .unwrap();
+
+        clock.advance(Duration::from_secs(3599));
+        assert!(app_data.read_file_if_fresh("token.json").unwrap().is_some());
+        assert_eq!(app_data.prune_expired().unwrap(), 0);
+
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(app_data.read_file_if_fresh("token.json").unwrap(), None);
+        assert_eq!(app_data.prune_expired().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_plain_overwrite_clears_ttl() {
+        let _base = SysBase::new("plain_overwrite_clears_ttl");
+        let clock = Arc::new(FakeClock::new(SystemTime::now()));
+        let app_data = AppData::new("test_app").with_clock(clock.clone());
+        app_data
+            .write_file_with_ttl("token.json", "old", Duration::from_secs(60))
+            .unwrap();
+        app_data.write_file("token.json", "new").unwrap();
+
+        clock.advance(Duration::from_secs(120));
+        assert_eq!(
+            app_data.read_file_if_fresh("token.json").unwrap().unwrap(),
+            b"new"
+        );
+        assert_eq!(app_data.prune_expired().unwrap(), 0);
+        assert_eq!(app_data.read_to_string("token.json").unwrap(), "new");
+
+        // A new TTL applies again
+        app_data
+            .write_file_with_ttl("token.json", "newer", Duration::from_secs(60))
+            .unwrap();
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(app_data.read_file_if_fresh("token.json").unwrap(), None);
+    }
+
+    #[test]
+    fn test_max_ttl_never_expires() {
+        let _base = SysBase::new("max_ttl_never_expires");
+        let clock = Arc::new(FakeClock::new(SystemTime::now()));
+        let app_data = AppData::new("test_app").with_clock(clock.clone());
+        app_data
+            .write_file_with_ttl("token.json", "token", Duration::from_secs(60))
+            .unwrap();
+        app_data
+            .write_file_with_ttl("token.json", "token", Duration::MAX)
+            .unwrap();
+        assert!(!app_data.file_exists("token.json.ttl").unwrap());
+
+        clock.advance(Duration::from_secs(120));
+        assert_eq!(
+            app_data.read_file_if_fresh("token.json").unwrap().unwrap(),
+            b"token"
+        );
+        assert_eq!(app_data.prune_expired().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_ttl_reads_dont_create_data_dir() {
+        let base = SysBase::new("ttl_reads_dont_create");
+        let app_data = AppData::new("test_app");
+        assert_eq!(app_data.read_file_if_fresh("token.json").unwrap(), None);
+        assert_eq!(app_data.prune_expired().unwrap(), 0);
+        assert!(!base.path().join("test_app").exists());
+    }
+
+    #[test]
+    fn test_prune_expired() {
+        let _base = SysBase::new("prune_expired");
+        let app_data = AppData::new("test_app");
+        app_data
+            .write_file_with_ttl("old.json", "old", Duration::ZERO)
+            .unwrap();
+        app_data
+            .write_file_with_ttl("new.json", "new", Duration::from_secs(3600))
+            .unwrap();
+        assert_eq!(app_data.prune_expired().unwrap(), 1);
+        assert!(!app_data.file_exists("old.json").unwrap());
+        assert!(!app_data.file_exists("old.json.ttl").unwrap());
+        assert!(app_data.file_exists("new.json").unwrap());
+        assert!(app_data.file_exists("new.json.ttl").unwrap());
+    }
+}
@@ -0,0 +1,225 @@
+//! Canonical composition (NFC) of app names, so composed and decomposed spellings of an
+//! accented name map to the same directory on macOS
+//!
+//! Only Latin letters with a single diacritic (Latin-1 Supplement and Latin Extended-A) are
+//! composed, which covers the accented names seen in practice without pulling in the full
+//! Unicode normalization tables.
+
+/// `(base, combining mark, composed)`, sorted by base then mark
+const COMPOSITIONS: &[(char, char, char)] = &[
+    ('A', '\u{0300}', '\u{00C0}'),
+    ('A', '\u{0301}', '\u{00C1}'),
+    ('A', '\u{0302}', '\u{00C2}'),
+    ('A', '\u{0303}', '\u{00C3}'),
+    ('A', '\u{0304}', '\u{0100}'),
+    ('A', '\u{0306}', '\u{0102}'),
+    ('A', '\u{0308}', '\u{00C4}'),
+    ('A', '\u{030A}', '\u{00C5}'),
+    ('A', '\u{0328}', '\u{0104}'),
+    ('C', '\u{0301}', '\u{0106}'),
+    ('C', '\u{0302}', '\u{0108}'),
+    ('C', '\u{0307}', '\u{010A}'),
+    ('C', '\u{030C}', '\u{010C}'),
+    ('C', '\u{0327}', '\u{00C7}'),
+    ('D', '\u{030C}', '\u{010E}'),
+    ('E', '\u{0300}', '\u{00C8}'),
+    ('E', '\u{0301}', '\u{00C9}'),
+    ('E', '\u{0302}', '\u{00CA}'),
+    ('E', '\u{0304}', '\u{0112}'),
+    ('E', '\u{0306}', '\u{0114}'),
+    ('E', '\u{0307}', '\u{0116}'),
+    ('E', '\u{0308}', '\u{00CB}'),
+    ('E', '\u{030C}', '\u{011A}'),
+    ('E', '\u{0328}', '\u{0118}'),
+    ('G', '\u{0302}', '\u{011C}'),
+    ('G', '\u{0306}', '\u{011E}'),
+    ('G', '\u{0307}', '\u{0120}'),
+    ('G', '\u{0327}', '\u{0122}'),
+    ('H', '\u{0302}', '\u{0124}'),
+    ('I', '\u{0300}', '\u{00CC}'),
+    ('I', '\u{0301}', '\u{00CD}'),
+    ('I', '\u{0302}', '\u{00CE}'),
+    ('I', '\u{0303}', '\u{0128}'),
+    ('I', '\u{0304}', '\u{012A}'),
+    ('I', '\u{0306}', '\u{012C}'),
+    ('I', '\u{0307}', '\u{0130}'),
+    ('I', '\u{0308}', '\u{00CF}'),
+    ('I', '\u{0328}', '\u{012E}'),
+    ('J', '\u{0302}', '\u{0134}'),
+    ('K', '\u{0327}', '\u{0136}'),
+    ('L', '\u{0301}', '\u{0139}'),
+    ('L', '\u{030C}', '\u{013D}'),
+    ('L', '\u{0327}', '\u{013B}'),
+    ('N', '\u{0301}', '\u{0143}'),
+    ('N', '\u{0303}', '\u{00D1}'),
+    ('N', '\u{030C}', '\u{0147}'),
+    ('N', '\u{0327}', '\u{0145}'),
+    ('O', '\u{0300}', '\u{00D2}'),
+    ('O', '\u{0301}', '\u{00D3}'),
+    ('O', '\u{0302}', '\u{00D4}'),
+    ('O', '\u{0303}', '\u{00D5}'),
+    ('O', '\u{0304}', '\u{014C}'),
+    ('O', '\u{0306}', '\u{014E}'),
+    ('O', '\u{0308}', '\u{00D6}'),
+    ('O', '\u{030B}', '\u{0150}'),
+    ('R', '\u{0301}', '\u{0154}'),
+    ('R', '\u{030C}', '\u{0158}'),
+    ('R', '\u{0327}', '\u{0156}'),
+    ('S', '\u{0301}', '\u{015A}'),
+    ('S', '\u{0302}', '\u{015C}'),
+    ('S', '\u{030C}', '\u{0160}'),
+    ('S', '\u{0327}', '\u{015E}'),
+    ('T', '\u{030C}', '\u{0164}'),
+    ('T', '\u{0327}', '\u{0162}'),
+    ('U', '\u{0300}', '\u{00D9}'),
+    ('U', '\u{0301}', '\u{00DA}'),
+    ('U', '\u{0302}', '\u{00DB}'),
+    ('U', '\u{0303}', '\u{0168}'),
+    ('U', '\u{0304}', '\u{016A}'),
+    ('U', '\u{0306}', '\u{016C}'),
+    ('U', '\u{0308}', '\u{00DC}'),
+    ('U', '\u{030A}', '\u{016E}'),
+    ('U', '\u{030B}', '\u{0170}'),
+    ('U', '\u{0328}', '\u{0172}'),
+    ('W', '\u{0302}', '\u{0174}'),
+    ('Y', '\u{0301}', '\u{00DD}'),
+    ('Y', '\u{0302}', '\u{0176}'),
+    ('Y', '\u{0308}', '\u{0178}'),
+    ('Z', '\u{0301}', '\u{0179}'),
+    ('Z', '\u{0307}', '\u{017B}'),
+    ('Z', '\u{030C}', '\u{017D}'),
+    ('a', '\u{0300}', '\u{00E0}'),
+    ('a', '\u{0301}', '\u{00E1}'),
+    ('a', '\u{0302}', '\u{00E2}'),
+    ('a', '\u{0303}', '\u{00E3}'),
+    ('a', '\u{0304}', '\u{0101}'),
+    ('a', '\u{0306}', '\u{0103}'),
+    ('a', '\u{0308}', '\u{00E4}'),
+    ('a', '\u{030A}', '\u{00E5}'),
+    ('a', '\u{0328}', '\u{0105}'),
+    ('c', '\u{0301}', '\u{0107}'),
+    ('c', '\u{0302}', '\u{0109}'),
+    ('c', '\u{0307}', '\u{010B}'),
+    ('c', '\u{030C}', '\u{010D}'),
+    ('c', '\u{0327}', '\u{00E7}'),
+    ('d', '\u{030C}', '\u{010F}'),
+    ('e', '\u{0300}', '\u{00E8}'),
+    ('e', '\u{0301}', '\u{00E9}'),
+    ('e', '\u{0302}', '\u{00EA}'),
+    ('e', '\u{0304}', '\u{0113}'),
+    ('e', '\u{0306}', '\u{0115}'),
+    ('e', '\u{0307}', '\u{0117}'),
+    ('e', '\u{0308}', '\u{00EB}'),
+    ('e', '\u{030C}', '\u{011B}'),
+    ('e', '\u{0328}', '\u{0119}'),
+    ('g', '\u{0302}', '\u{011D}'),
+    ('g', '\u{0306}', '\u{011F}'),
+    ('g', '\u{0307}', '\u{0121}'),
+    ('g', '\u{0327}', '\u{0123}'),
+    ('h', '\u{0302}', '\u{0125}'),
+    ('i', '\u{0300}', '\u{00EC}'),
+    ('i', '\u{0301}', '\u{00ED}'),
+    ('i', '\u{0302}', '\u{00EE}'),
+    ('i', '\u{0303}', '\u{0129}'),
+    ('i', '\u{0304}', '\u{012B}'),
+    ('i', '\u{0306}', '\u{012D}'),
+    ('i', '\u{0308}', '\u{00EF}'),
+    ('i', '\u{0328}', '\u{012F}'),
+    ('j', '\u{0302}', '\u{0135}'),
+    ('k', '\u{0327}', '\u{0137}'),
+    ('l', '\u{0301}', '\u{013A}'),
+    ('l', '\u{030C}', '\u{013E}'),
+    ('l', '\u{0327}', '\u{013C}'),
+    ('n', '\u{0301}', '\u{0144}'),
+    ('n', '\u{0303}', '\u{00F1}'),
+    ('n', '\u{030C}', '\u{0148}'),
+    ('n', '\u{0327}', '\u{0146}'),
+    ('o', '\u{0300}', '\u{00F2}'),
+    ('o', '\u{0301}', '\u{00F3}'),
+    ('o', '\u{0302}', '\u{00F4}'),
+    ('o', '\u{0303}', '\u{00F5}'),
+    ('o', '\u{0304}', '\u{014D}'),
+    ('o', '\u{0306}', '\u{014F}'),
+    ('o', '\u{0308}', '\u{00F6}'),
+    ('o', '\u{030B}', '\u{0151}'),
+    ('r', '\u{0301}', '\u{0155}'),
+    ('r', '\u{030C}', '\u{0159}'),
+    ('r', '\u{0327}', '\u{0157}'),
+    ('s', '\u{0301}', '\u{015B}'),
+    ('s', '\u{0302}', '\u{015D}'),
+    ('s', '\u{030C}', '\u{0161}'),
+    ('s', '\u{0327}', '\u{015F}'),
+    ('t', '\u{030C}', '\u{0165}'),
+    ('t', '\u{0327}', '\u{0163}'),
+    ('u', '\u{0300}', '\u{00F9}'),
+    ('u', '\u{0301}', '\u{00FA}'),
+    ('u', '\u{0302}', '\u{00FB}'),
+    ('u', '\u{0303}', '\u{0169}'),
+    ('u', '\u{0304}', '\u{016B}'),
+    ('u', '\u{0306}', '\u{016D}'),
+    ('u', '\u{0308}', '\u{00FC}'),
+    ('u', '\u{030A}', '\u{016F}'),
+    ('u', '\u{030B}', '\u{0171}'),
+    ('u', '\u{0328}', '\u{0173}'),
+    ('w', '\u{0302}', '\u{0175}'),
+    ('y', '\u{0301}', '\u{00FD}'),
+    ('y', '\u{0302}', '\u{0177}'),
+    ('y', '\u{0308}', '\u{00FF}'),
+    ('z', '\u{0301}', '\u{017A}'),
+    ('z', '\u{0307}', '\u{017C}'),
+    ('z', '\u{030C}', '\u{017E}'),
+];
+
+/// Compose `base` followed by the combining `mark`, if the pair is in the table
+fn compose_pair(base: char, mark: char) -> Option<char> {
+    COMPOSITIONS
+        .binary_search_by(|&(b, m, _)| (b, m).cmp(&(base, mark)))
+        .ok()
+        .map(|index| COMPOSITIONS[index].2)
+}
+
+/// Compose letter + combining mark sequences of `text` into precomposed characters
+pub(crate) fn compose(text: &str) -> String {
+    let mut composed = String::with_capacity(text.len());
+    let mut pending: Option<char> = None;
+    for c in text.chars() {
+        if let Some(base) = pending {
+            if let Some(precomposed) = compose_pair(base, c) {
+                pending = Some(precomposed);
+                continue;
+            }
+            composed.push(base);
+        }
+        pending = Some(c);
+    }
+    composed.extend(pending);
+    composed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compositions_sorted() {
+        assert!(
+            COMPOSITIONS
+                .windows(2)
+                .all(|pair| (pair[0].0, pair[0].1) < (pair[1].0, pair[1].1))
+        );
+    }
+
+    #[test]
+    fn test_compose() {
+        assert_eq!(compose("Cafe\u{0301}"), "Caf\u{00E9}");
+        assert_eq!(compose("Caf\u{00E9}"), "Caf\u{00E9}");
+        assert_eq!(
+            compose("Zu\u{0308}rich A\u{030A}l"),
+            "Z\u{00FC}rich \u{00C5}l"
+        );
+        assert_eq!(compose("my_app"), "my_app");
+        // Marks without a precomposed form are kept as they are
+        assert_eq!(compose("q\u{0301}"), "q\u{0301}");
+        assert_eq!(compose("\u{0301}"), "\u{0301}");
+    }
+}
@@ -0,0 +1,156 @@
+//! Resolution of another OS user's application directory, for services running under their
+//! own account
+
+use std::path::PathBuf;
+
+use crate::AppDataError;
+
+/// Home directory of `username` from the `/etc/passwd` user database
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) fn home_dir(username: &str) -> Result<PathBuf, AppDataError> {
+    let passwd = std::fs::read_to_string("/etc/passwd")?;
+    passwd
+        .lines()
+        .map(|line| line.split(':').collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 7 && fields[0] == username)
+        .map(|fields| PathBuf::from(fields[5]))
+        .ok_or_else(|| AppDataError::UserNotFound(username.to_string()))
+}
+
+/// Home directory of `username` from Directory Services, which `/etc/passwd` doesn't list
+/// regular accounts in on macOS
+#[cfg(target_os = "macos")]
+pub(crate) fn home_dir(username: &str) -> Result<PathBuf, AppDataError> {
+    let output = std::process::Command::new("dscl")
+        .args([
+            ".",
+            "-read",
+            &format!("/Users/{}", username),
+            "NFSHomeDirectory",
+        ])
+        .output()?;
+    if !output.status.success() {
+        return Err(AppDataError::UserNotFound(username.to_string()));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .strip_prefix("NFSHomeDirectory:")
+        .map(|home| PathBuf::from(home.trim()))
+        .ok_or_else(|| AppDataError::UserNotFound(username.to_string()))
+}
+
+/// Profile directory of `username` from the `ProfileList` registry key
+#[cfg(target_os = "windows")]
+pub(crate) fn home_dir(username: &str) -> Result<PathBuf, AppDataError> {
+    let output = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion\ProfileList",
+            "/s",
+            "/v",
+            "ProfileImagePath",
+        ])
+        .output()?;
+    let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("ProfileImagePath"))
+        .filter_map(|rest| rest.trim().split_once(char::is_whitespace))
+        .map(|(_, path)| PathBuf::from(path.trim().replace("%SystemDrive%", &system_drive)))
+        .find(|profile| {
+            profile
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().eq_ignore_ascii_case(username))
+        })
+        .ok_or_else(|| AppDataError::UserNotFound(username.to_string()))
+}
+
+/// Users can't be looked up on this target
+#[cfg(not(any(unix, target_os = "windows")))]
+pub(crate) fn home_dir(username: &str) -> Result<PathBuf, AppDataError> {
+    Err(AppDataError::UserNotFound(username.to_string()))
+}
+
+/// The platform application directory of `username`, like
+/// [`get_sys_app_data_dir`](crate::app_data::get_sys_app_data_dir) for the current user.
+/// The user's own environment, such as `XDG_DATA_HOME`, can't be seen and is not honored
+pub(crate) fn user_app_data_dir(username: &str) -> Result<PathBuf, AppDataError> {
+    let home = home_dir(username)?;
+    Ok(if cfg!(target_os = "windows") {
+        home.join(r"AppData\Roaming")
+    } else if cfg!(target_os = "macos") {
+        home.join("Library/Application Support")
+    } else {
+        home.join(".local/share")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AppData;
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn test_for_current_user() {
+        let _base = crate::test_util::SysBase::new("for_user");
+        let output = std::process::Command::new("id")
+            .arg("-un")
+            .output()
+            .unwrap();
+        let username = String::from_utf8(output.stdout).unwrap().trim().to_string();
+        let home = home_dir(&username).unwrap();
+        assert!(home.is_dir());
+
+        let app_data = AppData::new("test_app").for_user(&username);
+        assert_eq!(
+            app_data.resolve_data_dir().unwrap(),
+            home.join(".local/share/test_app")
+        );
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn test_respect_sudo_user() {
+        let mut base = crate::test_util::SysBase::new("respect_sudo_user");
+        let output = std::process::Command::new("id")
+            .arg("-un")
+            .output()
+            .unwrap();
+        let username = String::from_utf8(output.stdout).unwrap().trim().to_string();
+        let home = home_dir(&username).unwrap();
+        let root_home = base.path().join("root");
+        base.set_var("HOME", &root_home);
+        base.set_var("SUDO_USER", &username);
+        base.remove_var("XDG_DATA_HOME");
+        base.remove_var("XDG_CONFIG_HOME");
+
+        let app_data = AppData::new("test_app").respect_sudo_user(true);
+        assert_eq!(
+            app_data.resolve_data_dir().unwrap(),
+            home.join(".local/share/test_app")
+        );
+        assert_eq!(
+            app_data.paths().unwrap().config,
+            home.join(".config/test_app")
+        );
+        assert_eq!(
+            AppData::new("test_app").resolve_data_dir().unwrap(),
+            root_home.join(".local/share/test_app")
+        );
+
+        base.set_var("SUDO_USER", "app-data-no-such-user");
+        assert_eq!(
+            app_data.resolve_data_dir().unwrap(),
+            root_home.join(".local/share/test_app")
+        );
+    }
+
+    #[test]
+    fn test_unknown_user() {
+        match user_app_data_dir("app-data-no-such-user") {
+            Err(AppDataError::UserNotFound(name)) => assert_eq!(name, "app-data-no-such-user"),
+            other => panic!("Expected UserNotFound, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Detection of data directories on filesystems held in memory, which are wiped on reboot
+
+use std::path::Path;
+
+use crate::{AppData, AppDataError};
+
+/// Filesystem types that keep their contents in memory only
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const VOLATILE_FS_TYPES: &[&str] = &["tmpfs", "ramfs"];
+
+/// Undo the octal escapes (`\040` for a space) of a `/proc/self/mounts` field
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .and_then(|digits| std::str::from_utf8(digits).ok())
+            .and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match (bytes[i], octal) {
+            (b'\\', Some(byte)) => {
+                unescaped.push(byte);
+                i += 4;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&unescaped).into_owned()
+}
+
+/// Type of the filesystem mounted at the longest mount point containing `path`, looked up in
+/// `mounts` (the format of `/proc/self/mounts`). Later mounts shadow earlier ones
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn mount_fs_type(mounts: &str, path: &Path) -> Option<String> {
+    let mut best: Option<(usize, String)> = None;
+    for line in mounts.lines() {
+        let mut fields = line.split_whitespace();
+        let (Some(_source), Some(mount_point), Some(fs_type)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let mount_point = unescape_mount_field(mount_point);
+        if !path.starts_with(&mount_point) {
+            continue;
+        }
+        let depth = Path::new(&mount_point).components().count();
+        if best
+            .as_ref()
+            .is_none_or(|(best_depth, _)| depth >= *best_depth)
+        {
+            best = Some((depth, fs_type.to_string()));
+        }
+    }
+    best.map(|(_, fs_type)| fs_type)
+}
+
+/// The volatile filesystem type holding `path`, if any
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn volatile_fs_type(path: &Path) -> Result<Option<String>, AppDataError> {
+    let path = path.canonicalize()?;
+    let mounts = std::fs::read_to_string("/proc/self/mounts")?;
+    Ok(mount_fs_type(&mounts, &path)
+        .filter(|fs_type| VOLATILE_FS_TYPES.contains(&fs_type.as_str())))
+}
+
+/// Memory-backed filesystems can't be told apart from disks on this target
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn volatile_fs_type(_path: &Path) -> Result<Option<String>, AppDataError> {
+    Ok(None)
+}
+
+impl AppData {
+    /// Warn when the data directory is on a filesystem held in memory (tmpfs, ramfs), whose
+    /// contents are lost on reboot, returning a message the app can show to the user
+    ///
+    /// Detection reads the mount table on Linux; other platforms always return `None`.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 当数据目录位于内存文件系统（tmpfs、ramfs）上、重启后内容会丢失时发出警告，返回可展示给用户的提示信息。
+    /// Linux 上通过读取挂载表检测；其他平台始终返回 `None`
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// if let Some(warning) = AppData::new("my_app").warn_if_volatile().unwrap() {
+    ///     eprintln!("warning: {}", warning);
+    /// }
+    /// ```
+    pub fn warn_if_volatile(&self) -> Result<Option<String>, AppDataError> {
+        let dir = self.ensure_data_dir()?;
+        Ok(volatile_fs_type(&dir)?.map(|fs_type| {
+            format!(
+                "The data directory {} is on a {} filesystem held in memory, its contents will be lost on reboot",
+                dir.display(),
+                fs_type
+            )
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+
+    #[test]
+    fn test_warn_if_volatile() {
+        let _base = SysBase::new("warn_if_volatile");
+        // Only check that detection works, the temporary directory may or may not be a tmpfs
+        assert!(AppData::new("test_app").warn_if_volatile().is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_mount_fs_type() {
+        let mounts = "/dev/vda / ext4 rw 0 0\n\
+                      tmpfs /dev/shm tmpfs rw 0 0\n\
+                      /dev/vdb /home/my\\040user ext4 rw 0 0\n\
+                      tmpfs /home/my\\040user/ram tmpfs rw 0 0\n";
+        let fs_type = |path: &str| mount_fs_type(mounts, Path::new(path));
+        assert_eq!(fs_type("/var/lib/app").as_deref(), Some("ext4"));
+        assert_eq!(fs_type("/dev/shm/app").as_deref(), Some("tmpfs"));
+        assert_eq!(fs_type("/dev/shmem").as_deref(), Some("ext4"));
+        assert_eq!(fs_type("/home/my user/docs").as_deref(), Some("ext4"));
+        assert_eq!(fs_type("/home/my user/ram/app").as_deref(), Some("tmpfs"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_warn_if_volatile_tmpfs() {
+        let shm = Path::new("/dev/shm");
+        let mounts = std::fs::read_to_string("/proc/self/mounts").unwrap();
+        if mount_fs_type(&mounts, shm).as_deref() != Some("tmpfs") {
+            // No tmpfs to test with on this machine
+            return;
+        }
+        let root = shm.join(format!("app_data_test_volatile_{}", std::process::id()));
+        let app_data = AppData {
+            base_dir: Some(root.clone()),
+            ..AppData::new("test_app")
+        };
+        let warning = app_data.warn_if_volatile();
+        let _ = std::fs::remove_dir_all(&root);
+        let warning = warning.unwrap().unwrap();
+        assert!(warning.contains("tmpfs") && warning.contains("test_app"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Hot reloading of a single file in the data directory by polling it
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use crate::{AppData, AppDataError, SharedFileSystem, app_data::UTF8_BOM};
+
+/// How often a watched file is read. A change is reported once the contents stayed the same
+/// for one more interval, so partial writes settle first
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A running watch on a file, created by [`AppData::watch_file`]. Watching stops when the
+/// handle is dropped
+///
+/// <details><summary><b>中文说明</b></summary>
+/// 正在运行的文件监视，由 [`AppData::watch_file`] 创建。句柄被丢弃时停止监视
+/// </details>
+pub struct FileWatcher {
+    path: PathBuf,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FileWatcher {
+    /// The watched file
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 被监视的文件
+    /// </details>
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileWatcher")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// The text of `path`, or `None` when it is missing or unreadable
+fn read_text(file_system: &SharedFileSystem, path: &Path) -> Option<String> {
+    let bytes = match file_system.custom() {
+        Some(file_system) => file_system.read(path).ok()?,
+        None => fs::read(path).ok()?,
+    };
+    let text = String::from_utf8(bytes).ok()?;
+    Some(match text.strip_prefix(UTF8_BOM) {
+        Some(text) => text.to_string(),
+        None => text,
+    })
+}
+
+impl AppData {
+    /// Watch a file in the data directory and call `on_change` with the parsed value every time
+    /// its contents change, for hot reloading of configuration
+    ///
+    /// The file is polled in a background thread. A change is reported once the contents have
+    /// been stable for a short moment, and only when they parse with
+    /// [`FromStr`](std::str::FromStr): half-written or invalid contents are skipped until the
+    /// next valid version. The contents present when watching starts are not reported. The
+    /// location is resolved once, and watching stops when the returned handle is dropped.
+    ///
+    /// <details><summary><b>中文说明</b></summary>
+    /// 监视数据目录中的文件，每当内容变化时用解析后的值调用 `on_change`，用于配置热重载。
+    /// 文件在后台线程中轮询。内容稳定片刻后才会报告变化，且只有能通过 `FromStr` 解析时才报告：写了一半或无效的内容会被跳过，直到下一个有效版本。
+    /// 开始监视时已有的内容不会报告。文件位置只解析一次，返回的句柄被丢弃时停止监视
+    /// </details>
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use app_data::AppData;
+    ///
+    /// let app_data = AppData::new("my_app");
+    /// let _watcher = app_data
+    ///     .watch_file("volume.txt", |volume: u8| println!("volume is now {}", volume))
+    ///     .unwrap();
+    /// ```
+    pub fn watch_file<T>(
+        &self,
+        file_name: &str,
+        mut on_change: impl FnMut(T) + Send + 'static,
+    ) -> Result<FileWatcher, AppDataError>
+    where
+        T: FromStr,
+    {
+        let path = self.get_file_path(file_name)?;
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (path, stop) = (path.clone(), stop.clone());
+            let file_system = self.file_system.clone();
+            let mut last = read_text(&file_system, &path);
+            thread::spawn(move || {
+                let mut pending: Option<String> = None;
+                let mut rejected: Option<String> = None;
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(POLL_INTERVAL);
+                    let current = read_text(&file_system, &path);
+                    if current.is_none() || current == last || current == rejected {
+                        pending = None;
+                        continue;
+                    }
+                    if current != pending {
+                        pending = current;
+                        continue;
+                    }
+                    let Some(text) = pending.take() else {
+                        continue;
+                    };
+                    match text.parse() {
+                        Ok(value) => {
+                            on_change(value);
+                            last = Some(text);
+                        }
+                        Err(_) => rejected = Some(text),
+                    }
+                }
+            })
+        };
+        Ok(FileWatcher {
+            path,
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SysBase;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_watch_file() {
+        let _base = SysBase::new("watch_file");
+        let app_data = AppData::new("test_app");
+        app_data.write_file("volume.txt", "1").unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let watcher = app_data
+            .watch_file("volume.txt", move |volume: u32| {
+                sender.send(volume).unwrap()
+            })
+            .unwrap();
+        assert!(watcher.path().ends_with("volume.txt"));
+        let timeout = Duration::from_secs(5);
+
+        app_data.write_file("volume.txt", "2").unwrap();
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), 2);
+
+        // Invalid contents are skipped until the next valid version
+        app_data.write_file("volume.txt", "loud").unwrap();
+        assert!(receiver.recv_timeout(POLL_INTERVAL * 5).is_err());
+        app_data.write_file("volume.txt", "3").unwrap();
+        assert_eq!(receiver.recv_timeout(timeout).unwrap(), 3);
+
+        drop(watcher);
+        app_data.write_file("volume.txt", "4").unwrap();
+        assert!(receiver.recv_timeout(POLL_INTERVAL * 5).is_err());
+    }
+}